@@ -0,0 +1,116 @@
+use std::hash::Hash;
+
+use crate::graph::Graph;
+use crate::{CycleError, Set, TopoSort};
+
+// *** Checkpoint ***
+
+/// A snapshot of the nodes of a `TopoSort` at a point in time (see `TopoSort::checkpoint`)
+#[derive(Clone, Debug)]
+pub struct Checkpoint<T>
+where
+    T: Eq + Hash,
+{
+    nodes: Set<T>,
+}
+
+impl<T> Checkpoint<T>
+where
+    T: Eq + Hash,
+{
+    /// Returns true if `node` existed when the checkpoint was taken, else false
+    #[inline]
+    pub fn contains(&self, node: &T) -> bool {
+        self.nodes.contains(node)
+    }
+
+    /// Returns the number of nodes that existed when the checkpoint was taken
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// Returns true if there weren't any nodes when the checkpoint was taken otherwise false
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+}
+
+impl<T> TopoSort<T>
+where
+    T: Eq + Hash,
+{
+    /// Take a checkpoint of the current nodes for use with `nodes_since`
+    pub fn checkpoint(&self) -> Checkpoint<T>
+    where
+        T: Clone,
+    {
+        Checkpoint {
+            nodes: self.node_depends.keys().cloned().collect(),
+        }
+    }
+
+    /// Sort and return an iterator of only the nodes affected since `checkpoint` was taken: the nodes inserted
+    /// since then, plus any older nodes that now depend on them (directly or transitively), since a dependency
+    /// they referenced has since appeared. Older nodes that are only dependencies are assumed to be processed
+    /// already and are not yielded. Changes to the dependencies of older nodes aren't tracked. If a cycle is
+    /// detected, the affected nodes that could be sorted are yielded, followed by an error.
+    pub fn nodes_since<'d>(
+        &'d self,
+        checkpoint: &Checkpoint<T>,
+    ) -> impl Iterator<Item = Result<&'d T, CycleError>> + 'd {
+        let graph = Graph::new(&self.node_depends);
+        let order = graph.partial_sort_by_key(|node| node);
+        let err = (order.len() < graph.len()).then_some(Err(CycleError));
+
+        let mut affected: Vec<_> = graph
+            .nodes
+            .iter()
+            .map(|node| !checkpoint.contains(node))
+            .collect();
+        for &node in &order {
+            if !affected[node] && graph.depends[node].iter().any(|&dep| affected[dep]) {
+                affected[node] = true;
+            }
+        }
+
+        let order = order.into_iter().filter(|&node| affected[node]).collect();
+        graph.to_nodes(order).into_iter().map(Ok).chain(err)
+    }
+}
+
+// *** Tests ***
+
+#[cfg(test)]
+mod tests {
+    use crate::{CycleError, TopoSort};
+
+    #[test]
+    fn test_nodes_since() {
+        let mut topo_sort = TopoSort::with_capacity(8);
+        topo_sort.insert("core", vec![]);
+        topo_sort.insert("lib", vec!["core"]);
+        topo_sort.insert("app", vec!["lib", "plugin"]); // There is no 'plugin' yet
+        topo_sort.insert("docs", vec![]);
+
+        let checkpoint = topo_sort.checkpoint();
+        assert_eq!(4, checkpoint.len());
+        assert_eq!(0, topo_sort.nodes_since(&checkpoint).count());
+
+        topo_sort.insert("plugin", vec!["lib"]);
+        topo_sort.insert("tests", vec!["plugin"]);
+
+        let nodes: Vec<_> = topo_sort
+            .nodes_since(&checkpoint)
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(3, nodes.len());
+        assert_eq!(&"plugin", nodes[0]);
+        assert!(nodes.contains(&&"app") && nodes.contains(&&"tests"));
+
+        topo_sort.insert("plugin", vec!["tests"]); // cycle
+        let nodes: Vec<_> = topo_sort.nodes_since(&checkpoint).collect();
+        assert_eq!(vec![Err(CycleError)], nodes);
+    }
+}
@@ -0,0 +1,79 @@
+use std::mem;
+
+use crate::{Map, Set, TopoSort};
+
+// *** Compact ***
+
+impl TopoSort<usize> {
+    /// Renumber the nodes to the dense range `0..len()`, keeping their relative order, and update all dependencies
+    /// to match. Returns a map of old to new ids. This is useful after many removals to keep arrays that are
+    /// indexed by node id dense.
+    ///
+    /// NOTE: Dependencies that aren't nodes themselves are dropped, since they can't be renumbered without
+    /// colliding with the new ids. They are ignored during the sort anyway. The same goes for priorities (see
+    /// `set_priority`) of ids that aren't nodes.
+    pub fn compact(&mut self) -> Map<usize, usize> {
+        let mut old_ids: Vec<_> = self.node_depends.keys().copied().collect();
+        old_ids.sort_unstable();
+        let mapping: Map<_, _> = old_ids
+            .into_iter()
+            .enumerate()
+            .map(|(new, old)| (old, new))
+            .collect();
+
+        let edges: Vec<_> = self
+            .node_depends
+            .iter()
+            .flat_map(|(&node, depends)| depends.iter().map(move |&dependency| (node, dependency)))
+            .filter(|(_, dependency)| mapping.contains_key(dependency))
+            .collect();
+        self.edge_order.remap(&mapping, &edges);
+
+        self.node_depends = mem::take(self.node_depends_mut())
+            .into_iter()
+            .map(|(node, depends)| {
+                let depends: Set<_> = depends
+                    .iter()
+                    .filter_map(|dependency| mapping.get(dependency).copied())
+                    .collect();
+                (mapping[&node], depends)
+            })
+            .collect();
+        self.priorities = mem::take(&mut self.priorities)
+            .into_iter()
+            .filter_map(|(node, priority)| Some((*mapping.get(&node)?, priority)))
+            .collect();
+        mapping
+    }
+}
+
+// *** Tests ***
+
+#[cfg(test)]
+mod tests {
+    use crate::{Map, Set, TopoSort};
+
+    #[test]
+    fn test_compact() {
+        let mut topo_sort = TopoSort::with_capacity(6);
+        topo_sort.insert(10, vec![]);
+        topo_sort.insert(20, vec![10]);
+        topo_sort.insert(30, vec![20, 10]);
+        topo_sort.insert(40, vec![30, 99]); // There is no 99
+        topo_sort.insert(50, vec![40]);
+        topo_sort.take(&20);
+        topo_sort.take(&50);
+        topo_sort.set_priority(30, 7);
+        topo_sort.set_priority(1, 5); // Not a node, but 30 is renumbered to 1
+        topo_sort.set_priority(99, 3); // Not a node
+
+        let mapping = topo_sort.compact();
+        assert_eq!(Map::from_iter(vec![(10, 0), (30, 1), (40, 2)]), mapping);
+        assert_eq!(3, topo_sort.len());
+        assert_eq!(Set::from_iter(vec![1]), topo_sort[&2]);
+        assert_eq!(7, topo_sort.priority(&1));
+        assert_eq!(0, topo_sort.priority(&30));
+        assert_eq!(1, topo_sort.priorities.len());
+        assert_eq!(vec![0, 1, 2], topo_sort.try_owned_vec_nodes().unwrap());
+    }
+}
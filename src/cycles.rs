@@ -0,0 +1,719 @@
+use std::cmp::Reverse;
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::mem;
+
+use crate::graph::Graph;
+use crate::{Map, TopoSort};
+
+// *** CycleStrategy ***
+
+type EdgePriority<'a, T> = Box<dyn FnMut(&T, &T) -> i64 + 'a>;
+type CycleCallback<'a, T> = Box<dyn FnMut(&[&T]) -> usize + 'a>;
+
+/// The strategy used by `TopoSort::resolve_cycles` to pick which edge of a cycle is dropped
+pub enum CycleStrategy<'a, T> {
+    /// Drop the edge with the lowest priority, as returned by the function for each `(dependent, dependency)`
+    /// edge of the cycle. Ties are broken by dropping the most recently inserted edge (see `MostRecent`).
+    LowestPriority(EdgePriority<'a, T>),
+    /// Drop the most recently inserted edge of the cycle.
+    ///
+    /// NOTE: Insertion order is recorded by the methods that insert dependencies (`insert`, `add_dependencies`,
+    /// `merge_with`, etc.). Edges added through `entry`, `retain` or `for_each_mut`, or those of a deserialized
+    /// struct, count as the oldest, and ties are dropped by position (the dependent that comes last in iteration
+    /// order first).
+    MostRecent,
+    /// Call the function with a cycle (in the same form as `find_cycle`) and drop the edge from the node at the
+    /// returned index to the next node (or the first, for the last index)
+    Callback(CycleCallback<'a, T>),
+}
+
+// *** EdgeOrder ***
+
+// Insertion order of edges (as a running count), used by `CycleStrategy::MostRecent`. Edges are keyed by the hash of
+// their `(dependent, dependency)` pair, since nodes can't be cloned into a key. A collision at worst makes two edges
+// share an insertion time.
+#[derive(Clone, Default)]
+pub(crate) struct EdgeOrder {
+    count: u64,
+    inserted: Map<u64, u64>,
+}
+
+impl EdgeOrder {
+    pub(crate) fn key<T: Hash>(dependent: &T, dependency: &T) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        dependent.hash(&mut hasher);
+        dependency.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    // Record the edge as the most recently inserted one
+    #[inline]
+    pub(crate) fn insert<T: Hash>(&mut self, dependent: &T, dependency: &T) {
+        self.insert_key(Self::key(dependent, dependency));
+    }
+
+    // Same as `insert`, with the key of the edge (see `key`)
+    pub(crate) fn insert_key(&mut self, key: u64) {
+        self.count += 1;
+        self.inserted.insert(key, self.count);
+    }
+
+    #[inline]
+    pub(crate) fn remove<T: Hash>(&mut self, dependent: &T, dependency: &T) {
+        self.inserted.remove(&Self::key(dependent, dependency));
+    }
+
+    // Returns when the edge was inserted (zero if that wasn't recorded)
+    pub(crate) fn get<T: Hash>(&self, dependent: &T, dependency: &T) -> u64 {
+        self.inserted
+            .get(&Self::key(dependent, dependency))
+            .copied()
+            .unwrap_or(0)
+    }
+
+    // Rekey every edge after the nodes were renumbered
+    pub(crate) fn remap(&mut self, mapping: &Map<usize, usize>, edges: &[(usize, usize)]) {
+        let inserted = mem::take(&mut self.inserted);
+        for &(dependent, dependency) in edges {
+            if let Some(&count) = inserted.get(&Self::key(&dependent, &dependency)) {
+                let key = Self::key(&mapping[&dependent], &mapping[&dependency]);
+                self.inserted.insert(key, count);
+            }
+        }
+    }
+}
+
+// *** Cycles ***
+
+// NOTE: Like the sort, cycle analysis only follows "effective" edges, so self references are never part of a cycle
+
+impl<T> TopoSort<T>
+where
+    T: Eq + Hash,
+{
+    /// Returns the nodes of one cycle in order, if there is one, else None. Each node depends on the next and the
+    /// last node depends on the first (e.g. `[A, B, C]` for `A -> B -> C -> A`).
+    pub fn find_cycle(&self) -> Option<Vec<&T>> {
+        let graph = Graph::new(&self.node_depends);
+        let mut sorted = vec![false; graph.len()];
+        for node in graph.partial_sort_by_key(|node| node) {
+            sorted[node] = true;
+        }
+
+        let cycle = graph.cycle_within(&sorted);
+        (!cycle.is_empty()).then(|| graph.to_nodes(cycle))
+    }
+
+    /// Returns every elementary cycle (one that doesn't visit a node twice), each in the same form as `find_cycle`,
+    /// using Johnson's algorithm within each strongly connected component.
+    ///
+    /// NOTE: The number of elementary cycles can grow exponentially with the number of edges in a densely
+    /// connected component, so this is best suited to reporting on hand authored graphs
+    pub fn all_cycles(&self) -> Vec<Vec<&T>> {
+        let graph = Graph::new(&self.node_depends);
+        let mut search = Johnson {
+            depends: &graph.depends,
+            allowed: vec![false; graph.len()],
+            blocked: vec![false; graph.len()],
+            blocked_by: vec![Vec::new(); graph.len()],
+            path: Vec::new(),
+            cycles: Vec::new(),
+        };
+
+        for mut component in graph.components() {
+            if component.len() < 2 {
+                continue;
+            }
+
+            // Find the cycles whose smallest node is `start`, then exclude it from later searches
+            component.sort_unstable();
+            for &node in &component {
+                search.allowed[node] = true;
+            }
+            for &start in &component {
+                for &node in &component {
+                    search.blocked[node] = false;
+                    search.blocked_by[node].clear();
+                }
+                search.circuit(start, start);
+                search.allowed[start] = false;
+            }
+        }
+
+        search
+            .cycles
+            .into_iter()
+            .map(|cycle| graph.to_nodes(cycle))
+            .collect()
+    }
+
+    /// Returns the strongly connected components of the graph using Tarjan's algorithm. Each component is either a
+    /// group of nodes that all (transitively) depend on each other, or a single node that isn't part of a cycle.
+    /// Components are returned in dependency order: every component comes after those it depends on.
+    pub fn scc(&self) -> Vec<Vec<&T>> {
+        let graph = Graph::new(&self.node_depends);
+        graph
+            .components()
+            .into_iter()
+            .map(|component| graph.to_nodes(component))
+            .collect()
+    }
+
+    /// Returns every effective `(dependent, dependency)` edge that is part of at least one cycle - that is, both
+    /// nodes are in the same strongly connected component
+    pub fn cyclic_edges(&self) -> Vec<(&T, &T)> {
+        let graph = Graph::new(&self.node_depends);
+        let mut component_of = vec![0; graph.len()];
+        for (component, nodes) in graph.components().into_iter().enumerate() {
+            for node in nodes {
+                component_of[node] = component;
+            }
+        }
+
+        graph
+            .depends
+            .iter()
+            .enumerate()
+            .flat_map(|(node, depends)| depends.iter().map(move |&dep| (node, dep)))
+            .filter(|&(node, dep)| component_of[node] == component_of[dep])
+            .map(|(node, dep)| (graph.nodes[node], graph.nodes[dep]))
+            .collect()
+    }
+
+    /// Suggest a small set of `(dependent, dependency)` edges whose removal would make the graph acyclic (a
+    /// feedback arc set), or an empty vector if it already is. Only edges that are part of a cycle are suggested.
+    ///
+    /// NOTE: Finding the smallest such set is NP-hard, so this uses the greedy heuristic of Eades, Lin and Smyth:
+    /// nodes are ordered by repeatedly taking nodes without remaining dependents (placed last), then nodes without
+    /// remaining dependencies (placed first), and otherwise the node with the most dependents relative to its
+    /// dependencies. Every edge where the dependency comes after its dependent is suggested.
+    pub fn suggest_edges_to_break(&self) -> Vec<(&T, &T)> {
+        let graph = Graph::new(&self.node_depends);
+        let mut component_of = vec![0; graph.len()];
+        for (component, nodes) in graph.components().into_iter().enumerate() {
+            for node in nodes {
+                component_of[node] = component;
+            }
+        }
+
+        let mut depends = graph.edge_counts();
+        let mut dependents: Vec<_> = graph.dependents.iter().map(Vec::len).collect();
+        let mut removed = vec![false; graph.len()];
+        let (mut first, mut last) = (Vec::new(), Vec::new());
+
+        while let Some(start) = (0..graph.len()).find(|&node| !removed[node]) {
+            let remaining = (start..graph.len()).filter(|&node| !removed[node]);
+            let (node, is_last) =
+                if let Some(node) = remaining.clone().find(|&node| dependents[node] == 0) {
+                    (node, true)
+                } else if let Some(node) = remaining.clone().find(|&node| depends[node] == 0) {
+                    (node, false)
+                } else {
+                    let node = remaining
+                        .max_by_key(|&node| dependents[node] as isize - depends[node] as isize)
+                        .expect("remaining node");
+                    (node, false)
+                };
+
+            removed[node] = true;
+            for &dep in &graph.depends[node] {
+                dependents[dep] -= 1;
+            }
+            for &dependent in &graph.dependents[node] {
+                depends[dependent] -= 1;
+            }
+            if is_last {
+                last.push(node);
+            } else {
+                first.push(node);
+            }
+        }
+
+        let mut position = vec![0; graph.len()];
+        for (pos, node) in first.into_iter().chain(last.into_iter().rev()).enumerate() {
+            position[node] = pos;
+        }
+
+        graph
+            .depends
+            .iter()
+            .enumerate()
+            .flat_map(|(node, depends)| depends.iter().map(move |&dep| (node, dep)))
+            .filter(|&(node, dep)| {
+                component_of[node] == component_of[dep] && position[dep] > position[node]
+            })
+            .map(|(node, dep)| (graph.nodes[node], graph.nodes[dep]))
+            .collect()
+    }
+
+    /// Sort the nodes, breaking any cycles by dropping one edge of each cycle detected (picked by `strategy`) until
+    /// the sort succeeds. Returns the sorted nodes along with the `(dependent, dependency)` edges that were dropped
+    /// (empty if there was no cycle). The graph itself is not modified.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a `CycleStrategy::Callback` returns an index not within the cycle it was given
+    pub fn resolve_cycles(&self, mut strategy: CycleStrategy<'_, T>) -> (Vec<&T>, Vec<(&T, &T)>) {
+        let mut graph = Graph::new(&self.node_depends);
+        let mut dropped = Vec::new();
+
+        loop {
+            let order = graph.partial_sort_by_key(|node| node);
+            if order.len() == graph.len() {
+                return (graph.to_nodes(order), dropped);
+            }
+
+            let mut sorted = vec![false; graph.len()];
+            for node in order {
+                sorted[node] = true;
+            }
+            let cycle = graph.cycle_within(&sorted);
+            let edges = (0..cycle.len()).map(|idx| (cycle[idx], cycle[(idx + 1) % cycle.len()]));
+            // When the edge was inserted, then the position of the dependent and of the dependency within the
+            // dependencies of the dependent
+            let recency = |graph: &Graph<'_, T>, (node, dep): (usize, usize)| {
+                let inserted = self.edge_order.get(graph.nodes[node], graph.nodes[dep]);
+                let pos = graph.depends[node].iter().position(|&d| d == dep);
+                (inserted, node, pos)
+            };
+
+            let (node, dep) = match &mut strategy {
+                CycleStrategy::LowestPriority(priority) => edges
+                    .min_by_key(|&(node, dep)| {
+                        let priority = priority(graph.nodes[node], graph.nodes[dep]);
+                        (priority, Reverse(recency(&graph, (node, dep))))
+                    })
+                    .expect("empty cycle"),
+                CycleStrategy::MostRecent => edges
+                    .max_by_key(|&edge| recency(&graph, edge))
+                    .expect("empty cycle"),
+                CycleStrategy::Callback(choose) => {
+                    let idx = choose(&graph.to_nodes(cycle.clone()));
+                    assert!(idx < cycle.len(), "index not within the cycle");
+                    (cycle[idx], cycle[(idx + 1) % cycle.len()])
+                }
+            };
+
+            graph.depends[node].retain(|&d| d != dep);
+            graph.dependents[dep].retain(|&d| d != node);
+            dropped.push((graph.nodes[node], graph.nodes[dep]));
+        }
+    }
+
+    /// Returns the condensation of this graph: every strongly connected component (a cycle, or a single node that
+    /// isn't part of one) becomes a node holding its members in sorted order, and depends on every component that
+    /// any of its members depends on. The result is always acyclic, so it can be sorted without error.
+    ///
+    /// NOTE: If only an ordering of the components is needed (without cloning), `scc` already returns them in
+    /// dependency order
+    pub fn condensation(&self) -> TopoSort<Vec<T>>
+    where
+        T: Clone + Ord,
+    {
+        let graph = Graph::new(&self.node_depends);
+        let components = graph.components();
+        let mut component_of = vec![0; graph.len()];
+        let members: Vec<Vec<T>> = components
+            .iter()
+            .enumerate()
+            .map(|(component, nodes)| {
+                let mut members: Vec<_> = nodes
+                    .iter()
+                    .map(|&node| {
+                        component_of[node] = component;
+                        graph.nodes[node].clone()
+                    })
+                    .collect();
+                members.sort_unstable();
+                members
+            })
+            .collect();
+
+        let mut condensation = TopoSort::with_capacity(components.len());
+        for (component, nodes) in components.iter().enumerate() {
+            let depends = nodes
+                .iter()
+                .flat_map(|&node| &graph.depends[node])
+                .map(|&dep| component_of[dep])
+                .filter(|&dep| dep != component)
+                .map(|dep| members[dep].clone());
+            condensation.insert(members[component].clone(), depends);
+        }
+        condensation
+    }
+
+    /// Returns true if the edge where `from` depends on `to` is part of a cycle (or would be, if it was added),
+    /// else false. This is the case when there is a path from `to` back to `from`, and is cheaper than finding all
+    /// cyclic edges when only one edge is of interest.
+    #[inline]
+    pub fn edge_in_cycle(&self, from: &T, to: &T) -> bool {
+        self.depends_on(to, from)
+    }
+}
+
+// State of Johnson's elementary cycle search
+struct Johnson<'a> {
+    depends: &'a [Vec<usize>],
+    // Nodes the current search may visit
+    allowed: Vec<bool>,
+    blocked: Vec<bool>,
+    // Node -> Nodes to unblock when it is unblocked
+    blocked_by: Vec<Vec<usize>>,
+    path: Vec<usize>,
+    cycles: Vec<Vec<usize>>,
+}
+
+impl Johnson<'_> {
+    fn circuit(&mut self, node: usize, start: usize) -> bool {
+        let depends = self.depends;
+        let mut found = false;
+        self.path.push(node);
+        self.blocked[node] = true;
+
+        for &dep in &depends[node] {
+            if !self.allowed[dep] {
+                continue;
+            }
+            if dep == start {
+                self.cycles.push(self.path.clone());
+                found = true;
+            } else if !self.blocked[dep] && self.circuit(dep, start) {
+                found = true;
+            }
+        }
+
+        if found {
+            self.unblock(node);
+        } else {
+            for &dep in depends[node].iter().filter(|&&dep| self.allowed[dep]) {
+                if !self.blocked_by[dep].contains(&node) {
+                    self.blocked_by[dep].push(node);
+                }
+            }
+        }
+        self.path.pop();
+        found
+    }
+
+    fn unblock(&mut self, node: usize) {
+        self.blocked[node] = false;
+        for dependent in mem::take(&mut self.blocked_by[node]) {
+            if self.blocked[dependent] {
+                self.unblock(dependent);
+            }
+        }
+    }
+}
+
+// *** Tests ***
+
+#[cfg(test)]
+mod tests {
+    use crate::{CycleStrategy, Set, TopoSort};
+
+    #[test]
+    fn test_cyclic_edges() {
+        let mut topo_sort = TopoSort::with_capacity(8);
+        // First cycle
+        topo_sort.insert(1, vec![2]);
+        topo_sort.insert(2, vec![3]);
+        topo_sort.insert(3, vec![1, 4]);
+        // Second cycle
+        topo_sort.insert(5, vec![6, 5]); // Self dependency
+        topo_sort.insert(6, vec![5, 4]);
+        // Acyclic nodes, including ones depending on the cycles
+        topo_sort.insert(4, vec![]);
+        topo_sort.insert(7, vec![1, 6]);
+
+        let mut edges = topo_sort.cyclic_edges();
+        edges.sort_unstable();
+        assert_eq!(
+            vec![(&1, &2), (&2, &3), (&3, &1), (&5, &6), (&6, &5)],
+            edges
+        );
+
+        topo_sort.insert(3, vec![4]);
+        topo_sort.insert(6, vec![4]);
+        assert!(topo_sort.cyclic_edges().is_empty());
+    }
+
+    #[test]
+    fn test_detailed_cycle_path() {
+        let mut topo_sort = TopoSort::with_capacity(6);
+        topo_sort.insert("app", vec!["a"]); // Depends on the cycle
+        topo_sort.insert("a", vec!["b", "core"]);
+        topo_sort.insert("b", vec!["c", "b"]); // Self dependency
+        topo_sort.insert("c", vec!["a"]); // cycle
+        topo_sort.insert("core", vec![]);
+
+        let err = topo_sort
+            .nodes_detailed()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap_err();
+        assert_eq!(4, err.stuck().len());
+
+        // Any rotation of a -> b -> c -> a
+        let cycle = err.cycle();
+        assert_eq!(3, cycle.len());
+        let start = cycle.iter().position(|&&node| node == "a").unwrap();
+        assert_eq!(&"b", cycle[(start + 1) % 3]);
+        assert_eq!(&"c", cycle[(start + 2) % 3]);
+    }
+
+    #[test]
+    fn test_find_cycle() {
+        let mut topo_sort = TopoSort::with_capacity(5);
+        topo_sort.insert(1, vec![2, 1]); // Self dependency
+        topo_sort.insert(2, vec![3]);
+        topo_sort.insert(3, vec![]);
+        topo_sort.insert(4, vec![1]);
+        assert_eq!(None, topo_sort.find_cycle());
+
+        topo_sort.insert(3, vec![1]); // cycle
+        let mut cycle = topo_sort.find_cycle().unwrap();
+        while cycle[0] != &1 {
+            cycle.rotate_left(1);
+        }
+        assert_eq!(vec![&1, &2, &3], cycle);
+    }
+
+    #[test]
+    fn test_all_cycles() {
+        let mut topo_sort = TopoSort::with_capacity(6);
+        topo_sort.insert(1, vec![2]);
+        topo_sort.insert(2, vec![1, 3]);
+        topo_sort.insert(3, vec![1, 2, 3]); // Self dependency
+        topo_sort.insert(4, vec![5]);
+        topo_sort.insert(5, vec![4, 6]);
+        topo_sort.insert(6, vec![]);
+
+        let mut cycles: Vec<_> = topo_sort
+            .all_cycles()
+            .into_iter()
+            .map(|mut cycle| {
+                let min = cycle
+                    .iter()
+                    .enumerate()
+                    .min_by_key(|&(_, node)| node)
+                    .unwrap()
+                    .0;
+                cycle.rotate_left(min);
+                cycle
+            })
+            .collect();
+        cycles.sort_unstable();
+        assert_eq!(
+            vec![vec![&1, &2], vec![&1, &2, &3], vec![&2, &3], vec![&4, &5]],
+            cycles
+        );
+
+        topo_sort.insert(2, vec![3]);
+        topo_sort.insert(3, vec![]);
+        topo_sort.insert(5, vec![6]);
+        assert!(topo_sort.all_cycles().is_empty());
+    }
+
+    #[test]
+    fn test_scc() {
+        let mut topo_sort = TopoSort::with_capacity(6);
+        topo_sort.insert("app", vec!["b"]);
+        topo_sort.insert("a", vec!["b", "core"]);
+        topo_sort.insert("b", vec!["c"]);
+        topo_sort.insert("c", vec!["a"]); // cycle
+        topo_sort.insert("core", vec!["core"]); // Self dependency
+
+        let components: Vec<_> = topo_sort
+            .scc()
+            .into_iter()
+            .map(|mut component| {
+                component.sort_unstable();
+                component
+            })
+            .collect();
+        assert_eq!(
+            vec![vec![&"core"], vec![&"a", &"b", &"c"], vec![&"app"]],
+            components
+        );
+        assert!(TopoSort::<u32>::new().scc().is_empty());
+    }
+
+    #[test]
+    fn test_suggest_edges_to_break() {
+        let mut topo_sort = TopoSort::with_capacity(8);
+        topo_sort.insert("app", vec!["lib"]);
+        topo_sort.insert("lib", vec!["util"]);
+        topo_sort.insert("util", vec!["app", "core"]); // cycle
+        topo_sort.insert("core", vec!["log"]);
+        topo_sort.insert("log", vec!["core"]); // cycle
+        topo_sort.insert("docs", vec!["app"]);
+
+        let suggested: Vec<_> = topo_sort
+            .suggest_edges_to_break()
+            .into_iter()
+            .map(|(node, dep)| (*node, *dep))
+            .collect();
+        assert_eq!(2, suggested.len());
+        for (node, dep) in suggested {
+            let mut depends = topo_sort[&node].clone();
+            depends.remove(&dep);
+            topo_sort.insert(node, depends);
+        }
+        assert!(topo_sort.try_vec_nodes().is_ok());
+        assert!(topo_sort.suggest_edges_to_break().is_empty());
+    }
+
+    #[test]
+    fn test_resolve_cycles() {
+        let mut topo_sort = TopoSort::with_capacity(5);
+        topo_sort.insert(1, vec![2]);
+        topo_sort.insert(2, vec![3]);
+        topo_sort.insert(3, vec![1, 4]); // cycle
+        topo_sort.insert(4, vec![5]);
+        topo_sort.insert(5, vec![4]); // cycle
+
+        let priority = |dependent: &i32, dependency: &i32| (dependent * 10 + dependency) as i64;
+        let (order, mut dropped) =
+            topo_sort.resolve_cycles(CycleStrategy::LowestPriority(Box::new(priority)));
+        dropped.sort_unstable();
+        assert_eq!(vec![(&1, &2), (&4, &5)], dropped);
+        let pos = |node| order.iter().position(|&&n| n == node).unwrap();
+        assert!(pos(1) < pos(3) && pos(3) < pos(2));
+        assert!(pos(4) < pos(3) && pos(4) < pos(5));
+
+        let mut cycles = Vec::new();
+        let (order, dropped) =
+            topo_sort.resolve_cycles(CycleStrategy::Callback(Box::new(|cycle: &[&i32]| {
+                cycles.push(cycle.len());
+                cycle.iter().position(|&&node| node == 3).unwrap_or(0)
+            })));
+        assert_eq!(5, order.len());
+        assert_eq!(2, dropped.len());
+        assert!(dropped.contains(&(&3, &1)));
+        cycles.sort_unstable();
+        assert_eq!(vec![2, 3], cycles);
+
+        let (order, dropped) = topo_sort.resolve_cycles(CycleStrategy::MostRecent);
+        assert_eq!(5, order.len());
+        assert_eq!(2, dropped.len());
+        for (node, dep) in dropped {
+            assert!(topo_sort.edge_in_cycle(node, dep));
+        }
+
+        topo_sort.insert(3, vec![4]);
+        topo_sort.insert(5, vec![]);
+        let (order, dropped) = topo_sort.resolve_cycles(CycleStrategy::MostRecent);
+        assert_eq!(vec![&5, &4, &3, &2, &1], order);
+        assert!(dropped.is_empty());
+    }
+
+    #[test]
+    fn test_resolve_cycles_most_recent() {
+        let mut topo_sort = TopoSort::with_capacity(3);
+        topo_sort.insert(3, vec![1]);
+        topo_sort.insert(1, vec![2]);
+        topo_sort.insert(2, vec![3]); // cycle
+        let (order, dropped) = topo_sort.resolve_cycles(CycleStrategy::MostRecent);
+        assert_eq!(vec![(&2, &3)], dropped);
+        assert_eq!(vec![&2, &1, &3], order);
+
+        // Adding an edge that is already present doesn't make it more recent
+        topo_sort.add_dependency(3, 1);
+        let (_, dropped) = topo_sort.resolve_cycles(CycleStrategy::MostRecent);
+        assert_eq!(vec![(&2, &3)], dropped);
+
+        // ...but inserting it again after removing it does
+        topo_sort.remove_dependency(&3, &1);
+        topo_sort.add_dependency(3, 1);
+        let (order, dropped) = topo_sort.resolve_cycles(CycleStrategy::MostRecent);
+        assert_eq!(vec![(&3, &1)], dropped);
+        assert_eq!(vec![&3, &2, &1], order);
+
+        // Overwriting only counts the new dependencies
+        topo_sort.insert(1, vec![2, 9]);
+        let (_, dropped) = topo_sort.resolve_cycles(CycleStrategy::MostRecent);
+        assert_eq!(vec![(&3, &1)], dropped);
+    }
+
+    #[test]
+    fn test_condensation() {
+        let mut topo_sort = TopoSort::with_capacity(6);
+        topo_sort.insert("app", vec!["b"]);
+        topo_sort.insert("a", vec!["b", "core"]);
+        topo_sort.insert("b", vec!["c"]);
+        topo_sort.insert("c", vec!["a"]); // cycle
+        topo_sort.insert("core", vec!["core"]); // Self dependency
+        topo_sort.insert("docs", vec![]);
+        assert!(topo_sort.try_vec_nodes().is_err());
+
+        let condensation = topo_sort.condensation();
+        assert_eq!(4, condensation.len());
+        assert_eq!(
+            Set::from_iter(vec![vec!["a", "b", "c"]]),
+            condensation[&vec!["app"]]
+        );
+        assert_eq!(
+            Set::from_iter(vec![vec!["core"]]),
+            condensation[&vec!["a", "b", "c"]]
+        );
+        assert!(condensation[&vec!["core"]].is_empty());
+
+        let order = condensation.try_owned_vec_nodes().unwrap();
+        let position = |node: Vec<&str>| order.iter().position(|n| *n == node).unwrap();
+        assert!(position(vec!["core"]) < position(vec!["a", "b", "c"]));
+        assert!(position(vec!["a", "b", "c"]) < position(vec!["app"]));
+    }
+
+    #[test]
+    fn test_condensation_sorts() {
+        // Several cycles chained together, plus nodes around them
+        let mut topo_sort = TopoSort::with_capacity(10);
+        topo_sort.insert(1, vec![2]);
+        topo_sort.insert(2, vec![1, 3]);
+        topo_sort.insert(3, vec![4]);
+        topo_sort.insert(4, vec![5]);
+        topo_sort.insert(5, vec![3, 6]);
+        topo_sort.insert(6, vec![]);
+        topo_sort.insert(7, vec![1, 8]);
+        topo_sort.insert(8, vec![7]);
+        topo_sort.insert(9, vec![6]);
+        assert!(topo_sort.try_vec_nodes().is_err());
+
+        let order = topo_sort.condensation().try_into_vec_nodes().unwrap();
+        let expected: Vec<Vec<u32>> = vec![vec![6], vec![3, 4, 5], vec![1, 2], vec![7, 8]];
+        let groups: Vec<_> = order.iter().filter(|group| group != &&vec![9]).collect();
+        assert_eq!(expected.iter().collect::<Vec<_>>(), groups);
+        assert_eq!(9, order.iter().map(Vec::len).sum::<usize>());
+
+        // Same groups and order as `scc`
+        let scc: Vec<Vec<u32>> = topo_sort
+            .scc()
+            .into_iter()
+            .map(|component| {
+                let mut component: Vec<_> = component.into_iter().copied().collect();
+                component.sort_unstable();
+                component
+            })
+            .filter(|component| component != &vec![9])
+            .collect();
+        assert_eq!(expected, scc);
+    }
+
+    #[test]
+    fn test_edge_in_cycle() {
+        let mut topo_sort = TopoSort::with_capacity(4);
+        topo_sort.insert("A", vec!["B"]);
+        topo_sort.insert("B", vec!["C", "D"]);
+        topo_sort.insert("C", vec!["A"]); // cycle
+        topo_sort.insert("D", vec![]);
+
+        assert!(topo_sort.edge_in_cycle(&"C", &"A"));
+        assert!(topo_sort.edge_in_cycle(&"A", &"B"));
+        assert!(!topo_sort.edge_in_cycle(&"B", &"D"));
+        // Not an edge yet, but would close a cycle
+        assert!(topo_sort.edge_in_cycle(&"D", &"C"));
+        assert!(!topo_sort.edge_in_cycle(&"E", &"A"));
+
+        topo_sort.insert("C", vec![]);
+        assert!(!topo_sort.edge_in_cycle(&"A", &"B"));
+    }
+}
@@ -0,0 +1,109 @@
+use std::hash::Hash;
+
+use crate::{Set, TopoSort};
+
+#[cfg(not(any(feature = "indexmap", feature = "indexmap-serde")))]
+type MapEntry<'a, K, V> = std::collections::hash_map::Entry<'a, K, V>;
+#[cfg(any(feature = "indexmap", feature = "indexmap-serde"))]
+type MapEntry<'a, K, V> = indexmap::map::Entry<'a, K, V>;
+
+// *** Entry ***
+
+/// A view into a single node of a `TopoSort`, which may or may not be inserted yet (see `TopoSort::entry`)
+pub struct Entry<'a, T>(MapEntry<'a, T, Set<T>>);
+
+impl<'a, T> Entry<'a, T>
+where
+    T: Eq + Hash,
+{
+    /// Returns the node of this entry
+    #[inline]
+    pub fn key(&self) -> &T {
+        self.0.key()
+    }
+
+    /// Returns true if the node is already inserted, else false
+    #[inline]
+    pub fn is_inserted(&self) -> bool {
+        matches!(self.0, MapEntry::Occupied(_))
+    }
+
+    /// Insert the node without dependencies if it isn't inserted yet and return its dependency set
+    #[inline]
+    pub fn or_default(self) -> &'a mut Set<T> {
+        self.0.or_default()
+    }
+
+    /// Insert the node with the given dependencies if it isn't inserted yet and return its dependency set
+    #[inline]
+    pub fn or_insert(self, depends: Set<T>) -> &'a mut Set<T> {
+        self.0.or_insert(depends)
+    }
+
+    /// Insert the node with the dependencies returned by `f` if it isn't inserted yet and return its dependency set
+    #[inline]
+    pub fn or_insert_with<F: FnOnce() -> Set<T>>(self, f: F) -> &'a mut Set<T> {
+        self.0.or_insert_with(f)
+    }
+
+    /// Call `f` with the dependency set of the node if it is already inserted, and return the entry
+    #[inline]
+    pub fn and_modify<F: FnOnce(&mut Set<T>)>(self, f: F) -> Self {
+        Entry(self.0.and_modify(f))
+    }
+}
+
+impl<T> TopoSort<T>
+where
+    T: Eq + Hash,
+{
+    /// Returns the entry of the given node for in place insertion or modification of its dependency set, with a
+    /// single lookup (e.g. `topo_sort.entry(node).or_default().insert(dependency)`)
+    ///
+    /// # Panics
+    ///
+    /// Panics if a maximum degree was set (see `with_max_degree`), as dependency sets modified through the entry
+    /// can't be checked against it
+    #[inline]
+    pub fn entry(&mut self, node: T) -> Entry<'_, T> {
+        self.assert_no_max_degree("entry");
+        Entry(self.node_depends_mut().entry(node))
+    }
+}
+
+// *** Tests ***
+
+#[cfg(test)]
+mod tests {
+    use crate::{Set, TopoSort};
+
+    #[test]
+    fn test_entry() {
+        let mut topo_sort = TopoSort::with_capacity(3);
+        topo_sort.entry("A").or_default();
+        topo_sort.entry("B").or_default().insert("A");
+        topo_sort.entry("C").or_default().insert("A");
+        topo_sort.entry("C").or_default().insert("B");
+
+        let entry = topo_sort.entry("C");
+        assert_eq!(&"C", entry.key());
+        assert!(entry.is_inserted());
+        entry
+            .and_modify(|depends| {
+                depends.remove(&"A");
+            })
+            .or_default();
+        assert_eq!(Set::from_iter(vec!["B"]), topo_sort[&"C"]);
+
+        assert!(!topo_sort.entry("D").is_inserted());
+        topo_sort
+            .entry("D")
+            .or_insert_with(|| Set::from_iter(vec!["C"]));
+        topo_sort.entry("D").or_insert(Set::new()); // Already inserted
+        assert_eq!(Set::from_iter(vec!["C"]), topo_sort[&"D"]);
+        assert_eq!(
+            vec![&"A", &"B", &"C", &"D"],
+            topo_sort.try_vec_nodes().unwrap()
+        );
+    }
+}
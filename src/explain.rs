@@ -0,0 +1,96 @@
+use std::hash::Hash;
+
+use crate::{CycleError, Set, TopoSort};
+
+// *** OrderReason ***
+
+/// Why a node was emitted after the node before it in `TopoSort::sort_explained`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OrderReason {
+    /// The node was emitted first
+    First,
+    /// The node directly depends on the previous node
+    DirectEdge,
+    /// The node depends on the previous node through other nodes
+    Transitive,
+    /// The nodes are independent, so either order is valid (a tie)
+    Independent,
+}
+
+impl<T> TopoSort<T>
+where
+    T: Eq + Hash,
+{
+    /// Sort and return a vector (with borrowed nodes) of the results, each paired with the reason it was ordered
+    /// after the node before it. This is intended for debugging and auditing, as each reason may require a
+    /// search of the graph. If a cycle is detected, an error is returned instead.
+    pub fn sort_explained(&self) -> Result<Vec<(&T, OrderReason)>, CycleError> {
+        let order = self.nodes().collect::<Result<Vec<_>, _>>()?;
+        let mut visited = Set::new();
+        let mut previous = None;
+
+        Ok(order
+            .into_iter()
+            .map(|node| {
+                let reason = match previous.replace(node) {
+                    None => OrderReason::First,
+                    Some(previous) if self.effective_depends(node).any(|dep| dep == previous) => {
+                        OrderReason::DirectEdge
+                    }
+                    Some(previous) if self.depends_on_with(node, previous, &mut visited) => {
+                        OrderReason::Transitive
+                    }
+                    Some(_) => OrderReason::Independent,
+                };
+                (node, reason)
+            })
+            .collect())
+    }
+}
+
+// *** Tests ***
+
+#[cfg(test)]
+mod tests {
+    use crate::{CycleError, OrderReason, TopoSort};
+
+    #[test]
+    fn test_sort_explained() {
+        let mut topo_sort = TopoSort::with_capacity(5);
+        topo_sort.insert("C", vec!["A", "B"]);
+        topo_sort.insert("E", vec!["B", "C"]);
+        topo_sort.insert("A", vec![]);
+        topo_sort.insert("D", vec!["A", "C", "E"]);
+        topo_sort.insert("B", vec!["A"]);
+
+        assert_eq!(
+            vec![
+                (&"A", OrderReason::First),
+                (&"B", OrderReason::DirectEdge),
+                (&"C", OrderReason::DirectEdge),
+                (&"E", OrderReason::DirectEdge),
+                (&"D", OrderReason::DirectEdge),
+            ],
+            topo_sort.sort_explained().unwrap()
+        );
+
+        let mut topo_sort = TopoSort::with_capacity(4);
+        topo_sort.insert("app", vec!["lib"]);
+        topo_sort.insert("lib", vec!["core"]);
+        topo_sort.insert("core", vec![]);
+        topo_sort.insert("docs", vec![]);
+        let explained = topo_sort.sort_explained().unwrap();
+        assert_eq!(4, explained.len());
+        for pair in explained.windows(2) {
+            let expected = match (*pair[0].0, *pair[1].0) {
+                ("core", "lib") | ("lib", "app") => OrderReason::DirectEdge,
+                ("core", "app") => OrderReason::Transitive,
+                _ => OrderReason::Independent,
+            };
+            assert_eq!(expected, pair[1].1);
+        }
+
+        topo_sort.insert("core", vec!["app"]); // cycle
+        assert_eq!(Err(CycleError), topo_sort.sort_explained());
+    }
+}
@@ -0,0 +1,139 @@
+use std::hash::{Hash, Hasher};
+
+use crate::graph::Graph;
+use crate::{CycleError, TopoSort};
+
+// *** Fingerprints ***
+
+// NOTE: `std`'s default hasher is randomly seeded and its algorithm isn't guaranteed to stay the same between
+// releases, neither of which is acceptable for fingerprints that may be persisted, so we use FNV-1a instead
+
+const FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+struct FnvHasher(u64);
+
+impl FnvHasher {
+    #[inline]
+    fn new() -> Self {
+        FnvHasher(FNV_OFFSET_BASIS)
+    }
+}
+
+impl Hasher for FnvHasher {
+    #[inline]
+    fn finish(&self) -> u64 {
+        self.0
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.0 ^= u64::from(byte);
+            self.0 = self.0.wrapping_mul(FNV_PRIME);
+        }
+    }
+}
+
+#[inline]
+fn fnv_hash<H: Hash + ?Sized>(value: &H) -> u64 {
+    let mut hasher = FnvHasher::new();
+    value.hash(&mut hasher);
+    hasher.finish()
+}
+
+impl<T> TopoSort<T>
+where
+    T: Eq + Hash,
+{
+    /// Returns a fingerprint of the structure of the graph: its nodes and their dependency sets (as inserted). It
+    /// does not depend on insertion or iteration order and uses a fixed hash function, so equal graphs always
+    /// yield equal fingerprints, even across runs. Cache it and compare later to cheaply detect structural changes.
+    pub fn structure_fingerprint(&self) -> u64 {
+        // Sums of hashes are used to combine unordered elements
+        let nodes = self
+            .node_depends
+            .iter()
+            .map(|(node, depends)| {
+                let depends = depends.iter().map(fnv_hash).fold(0u64, u64::wrapping_add);
+                fnv_hash(&(fnv_hash(node), depends))
+            })
+            .fold(0u64, u64::wrapping_add);
+        fnv_hash(&(self.node_depends.len(), nodes))
+    }
+
+    /// Sort and return a fingerprint of the sorted order (nodes only). Ties are broken by `Ord` (the
+    /// lexicographically smallest order is hashed), and a fixed hash function is used, so the same graph
+    /// always yields the same fingerprint, even across runs. If a cycle is detected, an error is returned instead.
+    pub fn order_fingerprint(&self) -> Result<u64, CycleError>
+    where
+        T: Ord,
+    {
+        let graph = Graph::new(&self.node_depends);
+        let mut hasher = FnvHasher::new();
+
+        for node in graph.sort_min()? {
+            graph.nodes[node].hash(&mut hasher);
+        }
+        Ok(hasher.finish())
+    }
+}
+
+// *** Tests ***
+
+#[cfg(test)]
+mod tests {
+    use crate::{CycleError, TopoSort};
+
+    #[test]
+    fn test_order_fingerprint() {
+        let mut topo_sort = TopoSort::with_capacity(4);
+        topo_sort.insert("B", vec!["A"]);
+        topo_sort.insert("C", vec!["A"]);
+        topo_sort.insert("D", vec!["B", "C"]);
+        topo_sort.insert("A", vec![]);
+
+        // Same graph inserted in a different order (and so likely iterated in a different order)
+        let mut topo_sort2 = TopoSort::with_capacity(16);
+        topo_sort2.insert("A", vec![]);
+        topo_sort2.insert("D", vec!["C", "B"]);
+        topo_sort2.insert("C", vec!["A"]);
+        topo_sort2.insert("B", vec!["A"]);
+
+        let fingerprint = topo_sort.order_fingerprint().unwrap();
+        assert_eq!(fingerprint, topo_sort2.order_fingerprint().unwrap());
+        assert_eq!(fingerprint, topo_sort.clone().order_fingerprint().unwrap());
+
+        topo_sort2.insert("B", vec!["C"]);
+        assert_ne!(fingerprint, topo_sort2.order_fingerprint().unwrap());
+
+        topo_sort2.insert("A", vec!["D"]); // cycle
+        assert_eq!(Err(CycleError), topo_sort2.order_fingerprint());
+    }
+
+    #[test]
+    fn test_structure_fingerprint() {
+        let mut topo_sort = TopoSort::with_capacity(3);
+        topo_sort.insert("A", vec![]);
+        topo_sort.insert("B", vec!["A"]);
+        topo_sort.insert("C", vec!["A", "B"]);
+        let fingerprint = topo_sort.structure_fingerprint();
+
+        // Sorting doesn't change it
+        topo_sort.try_vec_nodes().unwrap();
+        assert_eq!(fingerprint, topo_sort.structure_fingerprint());
+
+        let mut topo_sort2 = TopoSort::with_capacity(16);
+        topo_sort2.insert("C", vec!["B", "A"]);
+        topo_sort2.insert("B", vec!["A"]);
+        topo_sort2.insert("A", vec![]);
+        assert_eq!(fingerprint, topo_sort2.structure_fingerprint());
+
+        topo_sort2.add_dependencies("B", vec!["C"]);
+        assert_ne!(fingerprint, topo_sort2.structure_fingerprint());
+
+        // Moving an edge to another node changes it too
+        topo_sort2.insert("B", vec![]);
+        topo_sort2.insert("A", vec!["A"]);
+        assert_ne!(fingerprint, topo_sort2.structure_fingerprint());
+    }
+}
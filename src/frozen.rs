@@ -0,0 +1,354 @@
+use std::hash::Hash;
+use std::mem;
+use std::sync::Arc;
+
+use crate::workspace::{reuse, SortWorkspace};
+use crate::{
+    Comparator, CycleError, InnerIter, Map, Set, TopoSort, TopoSortIter, TopoSortNodeIter,
+};
+
+// *** Csr ***
+
+// Compressed sparse row adjacency. Node indices are positions in `node_depends` iteration order.
+pub(crate) struct Csr {
+    // Dependents of node `i` are `targets[offsets[i]..offsets[i + 1]]`
+    offsets: Vec<u32>,
+    targets: Vec<u32>,
+    // Number of (effective) dependencies of each node
+    pub(crate) in_degree: Vec<u32>,
+}
+
+impl Csr {
+    #[inline]
+    pub(crate) fn new<T>(entries: &[(&T, &Set<T>)]) -> Self
+    where
+        T: Eq + Hash,
+    {
+        Self::new_in(entries, &mut SortWorkspace::new())
+    }
+
+    // Builds the adjacency of `entries` (where the position of each entry is its index) in the buffers of
+    // `workspace` (see `recycle`)
+    pub(crate) fn new_in<T>(entries: &[(&T, &Set<T>)], workspace: &mut SortWorkspace) -> Self
+    where
+        T: Eq + Hash,
+    {
+        let len = entries.len();
+        assert!(u32::try_from(len).is_ok(), "too many nodes to sort");
+        let index: Map<&T, u32> = entries
+            .iter()
+            .enumerate()
+            .map(|(idx, &(node, _))| (node, idx as u32))
+            .collect();
+
+        // Dependency -> Dependent
+        let edges = &mut workspace.pairs;
+        edges.clear();
+        for (dependent, &(node, dependencies)) in entries.iter().enumerate() {
+            for dependency in dependencies {
+                // Filter any self references and dependencies that were never inserted as nodes
+                if dependency != node {
+                    if let Some(&dependency) = index.get(dependency) {
+                        edges.push((dependency, dependent as u32));
+                    }
+                }
+            }
+        }
+
+        Self::from_pairs(len, workspace)
+    }
+
+    // Same as `new`, but every edge is resolved in parallel shards (one per chunk of dependents), which are merged
+    // in order at the end. The result is identical to `new`.
+    #[cfg(feature = "rayon")]
+    pub(crate) fn par_new<T>(entries: &[(&T, &Set<T>)]) -> Self
+    where
+        T: Eq + Hash + Sync,
+    {
+        use rayon::prelude::*;
+
+        const SHARD_SIZE: usize = 1024;
+
+        let len = entries.len();
+        assert!(u32::try_from(len).is_ok(), "too many nodes to sort");
+        let index: Map<&T, u32> = entries
+            .iter()
+            .enumerate()
+            .map(|(idx, &(node, _))| (node, idx as u32))
+            .collect();
+
+        let shards: Vec<Vec<_>> = entries
+            .par_chunks(SHARD_SIZE)
+            .enumerate()
+            .map(|(shard, chunk)| {
+                let mut edges = Vec::new();
+                for (offset, (node, dependencies)) in chunk.iter().enumerate() {
+                    let dependent = (shard * SHARD_SIZE + offset) as u32;
+                    for dependency in *dependencies {
+                        if dependency != *node {
+                            if let Some(&dependency) = index.get(dependency) {
+                                edges.push((dependency, dependent));
+                            }
+                        }
+                    }
+                }
+                edges
+            })
+            .collect();
+
+        let mut workspace = SortWorkspace::new();
+        workspace.pairs = shards.concat();
+        Self::from_pairs(len, &mut workspace)
+    }
+
+    // Builds the adjacency from the (dependency, dependent) pairs in `workspace`
+    fn from_pairs(len: usize, workspace: &mut SortWorkspace) -> Self {
+        let edges = &workspace.pairs;
+        assert!(u32::try_from(edges.len()).is_ok(), "too many edges to sort");
+        let mut offsets = reuse(&mut workspace.offsets, len + 1);
+        let mut in_degree = reuse(&mut workspace.in_degree, len);
+        for &(dependency, dependent) in edges {
+            offsets[dependency as usize + 1] += 1;
+            in_degree[dependent as usize] += 1;
+        }
+
+        for idx in 0..len {
+            offsets[idx + 1] += offsets[idx];
+        }
+        let next = &mut workspace.cursors;
+        next.clone_from(&offsets);
+        let mut targets = reuse(&mut workspace.targets, edges.len());
+        for &(dependency, dependent) in edges {
+            let slot = &mut next[dependency as usize];
+            targets[*slot as usize] = dependent;
+            *slot += 1;
+        }
+
+        Csr {
+            offsets,
+            targets,
+            in_degree,
+        }
+    }
+
+    // Hands the buffers back to `workspace` so the next sort can reuse them
+    pub(crate) fn recycle(&mut self, workspace: &mut SortWorkspace) {
+        workspace.offsets = mem::take(&mut self.offsets);
+        workspace.targets = mem::take(&mut self.targets);
+        workspace.in_degree = mem::take(&mut self.in_degree);
+    }
+
+    #[inline]
+    pub(crate) fn dependents(&self, node: usize) -> &[u32] {
+        &self.targets[self.offsets[node] as usize..self.offsets[node + 1] as usize]
+    }
+
+    // Panics with a description of the first way this adjacency disagrees with `entries` (it was built from)
+    #[cfg(any(test, debug_assertions))]
+    pub(crate) fn check_invariants<T>(&self, entries: &[(&T, &Set<T>)])
+    where
+        T: Eq + Hash,
+    {
+        let len = entries.len();
+        assert_eq!(len + 1, self.offsets.len(), "offsets length mismatch");
+        assert_eq!(len, self.in_degree.len(), "in degree length mismatch");
+        assert_eq!(0, self.offsets[0], "offsets must start at zero");
+        assert!(
+            self.offsets.windows(2).all(|pair| pair[0] <= pair[1]),
+            "offsets must be non-decreasing"
+        );
+        assert_eq!(
+            self.targets.len(),
+            self.offsets[len] as usize,
+            "offsets must end at the target count"
+        );
+
+        let index: Map<&T, usize> = entries
+            .iter()
+            .enumerate()
+            .map(|(idx, &(node, _))| (node, idx))
+            .collect();
+        let mut found = vec![0_u32; len];
+        for dependency in 0..len {
+            for &dependent in self.dependents(dependency) {
+                let dependent = dependent as usize;
+                assert!(dependent < len, "target out of range");
+                assert_ne!(dependency, dependent, "self reference not filtered");
+                assert!(
+                    entries[dependent].1.contains(entries[dependency].0),
+                    "dependency not declared"
+                );
+                found[dependent] += 1;
+            }
+        }
+
+        for (node, &(dependent, dependencies)) in entries.iter().enumerate() {
+            let effective = dependencies
+                .iter()
+                .filter(|&dep| dep != dependent && index.contains_key(dep))
+                .count();
+            assert_eq!(
+                effective, found[node] as usize,
+                "effective dependencies mismatch"
+            );
+            assert_eq!(
+                effective, self.in_degree[node] as usize,
+                "in degree mismatch"
+            );
+        }
+    }
+}
+
+// *** FrozenTopoSort ***
+
+/// A read-only snapshot of a `TopoSort` with its adjacency prebuilt in compressed sparse row form. Sorting
+/// it any number of times skips hashing every edge again, which is useful when the same large graph is
+/// sorted repeatedly. The comparator (if any) of the `TopoSort` is kept.
+pub struct FrozenTopoSort<'d, T> {
+    // Dependent -> Dependencies
+    node_depends: &'d Map<T, Set<T>>,
+    comparator: Option<Comparator<T>>,
+    // Index -> (Dependent, Dependencies)
+    entries: Vec<(&'d T, &'d Set<T>)>,
+    csr: Arc<Csr>,
+}
+
+impl<T> TopoSort<T>
+where
+    T: Eq + Hash,
+{
+    /// Build a `FrozenTopoSort` that can be sorted repeatedly without rebuilding the graph each time
+    pub fn freeze(&self) -> FrozenTopoSort<'_, T> {
+        let entries: Vec<_> = self.node_depends.iter().collect();
+        FrozenTopoSort {
+            node_depends: &self.node_depends,
+            comparator: self.comparator.clone(),
+            csr: Arc::new(Csr::new(&entries)),
+            entries,
+        }
+    }
+
+    /// Same as `freeze`, but the graph is built in parallel, which is faster for graphs with many edges.
+    /// Iteration order is identical to `freeze`.
+    #[cfg(feature = "rayon")]
+    pub fn par_freeze(&self) -> FrozenTopoSort<'_, T>
+    where
+        T: Sync,
+    {
+        let entries: Vec<_> = self.node_depends.iter().collect();
+        FrozenTopoSort {
+            node_depends: &self.node_depends,
+            comparator: self.comparator.clone(),
+            csr: Arc::new(Csr::par_new(&entries)),
+            entries,
+        }
+    }
+}
+
+impl<'d, T> FrozenTopoSort<'d, T>
+where
+    T: Eq + Hash,
+{
+    /// Returns the number of nodes
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns true if there are no nodes
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Returns the number of (effective) edges
+    #[inline]
+    pub fn edge_count(&self) -> usize {
+        self.csr.targets.len()
+    }
+
+    /// Sort and return an iterator over the node and dependent set, just like `TopoSort::iter`
+    pub fn iter(&self) -> TopoSortIter<'d, T> {
+        TopoSortIter {
+            inner: InnerIter::from_csr(Arc::clone(&self.csr), self.comparator.clone()),
+            entries: self.entries.clone(),
+            node_depends: self.node_depends,
+        }
+    }
+
+    /// Sort and return an iterator over the nodes only, just like `TopoSort::nodes`
+    #[inline]
+    pub fn nodes(&self) -> TopoSortNodeIter<'d, T> {
+        TopoSortNodeIter(self.iter())
+    }
+
+    /// Sort and return a vector of nodes (or a `CycleError` if a cycle is detected)
+    #[inline]
+    pub fn try_vec_nodes(&self) -> Result<Vec<&'d T>, CycleError> {
+        self.nodes().collect()
+    }
+}
+
+// *** Tests ***
+
+#[cfg(test)]
+mod tests {
+    use crate::{CycleError, TopoSort};
+
+    #[test]
+    fn test_freeze() {
+        let mut topo_sort = TopoSort::with_capacity(5);
+        topo_sort.insert("C", vec!["A", "B"]);
+        topo_sort.insert("E", vec!["B", "C"]);
+        topo_sort.insert("A", vec![]);
+        topo_sort.insert("D", vec!["A", "C", "E", "Z"]); // There is no Z
+        topo_sort.insert("B", vec!["A", "B"]);
+
+        let frozen = topo_sort.freeze();
+        assert_eq!(5, frozen.len());
+        assert_eq!(8, frozen.edge_count());
+        for _ in 0..3 {
+            assert_eq!(
+                vec![&"A", &"B", &"C", &"E", &"D"],
+                frozen.try_vec_nodes().unwrap()
+            );
+        }
+        let (node, depends) = frozen.iter().last().unwrap().unwrap();
+        assert_eq!(&"D", node);
+        assert_eq!(&topo_sort[&"D"], depends);
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn test_par_freeze() {
+        // Spans several shards
+        let mut topo_sort = TopoSort::with_capacity(5000);
+        for node in 0..5000_u32 {
+            topo_sort.insert(node, (1..=3).filter_map(|step| node.checked_sub(step * 7)));
+        }
+
+        let frozen = topo_sort.freeze();
+        let par_frozen = topo_sort.par_freeze();
+        assert_eq!(frozen.edge_count(), par_frozen.edge_count());
+        assert_eq!(
+            frozen.try_vec_nodes().unwrap(),
+            par_frozen.try_vec_nodes().unwrap()
+        );
+    }
+
+    #[test]
+    fn test_freeze_cycle() {
+        let mut topo_sort = TopoSort::with_comparator(|a: &u32, b: &u32| a.cmp(b));
+        topo_sort.insert(1, vec![]);
+        topo_sort.insert(2, vec![1, 3]);
+        topo_sort.insert(3, vec![2]);
+        topo_sort.insert(4, vec![1]);
+
+        let frozen = topo_sort.freeze();
+        let mut nodes = frozen.nodes();
+        assert_eq!(Some(Ok(&1)), nodes.next());
+        assert_eq!(Some(Ok(&4)), nodes.next());
+        assert_eq!(Some(Err(CycleError)), nodes.next());
+        assert_eq!(Err(CycleError), frozen.try_vec_nodes());
+    }
+}
@@ -0,0 +1,533 @@
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, VecDeque};
+use std::hash::Hash;
+use std::mem;
+
+use crate::{CycleError, Map, Set};
+
+// *** Graph ***
+
+/// A dense, index based snapshot of the "effective" edges of a `TopoSort` - self references and
+/// dependencies on nodes that were never inserted are filtered out, exactly as the sort does.
+/// Node order follows the iteration order of the source map.
+pub(crate) struct Graph<'d, T> {
+    pub(crate) nodes: Vec<&'d T>,
+    pub(crate) index: Map<&'d T, usize>,
+    // Dependent -> Dependencies
+    pub(crate) depends: Vec<Vec<usize>>,
+    // Dependency -> Dependents
+    pub(crate) dependents: Vec<Vec<usize>>,
+}
+
+impl<'d, T> Graph<'d, T>
+where
+    T: Eq + Hash,
+{
+    #[inline]
+    pub(crate) fn new(node_depends: &'d Map<T, Set<T>>) -> Self {
+        let depends: Vec<_> = node_depends.values().collect();
+        Self::from_fn(node_depends.keys().collect(), |node| depends[node])
+    }
+
+    /// Build from a list of nodes and a function returning the declared dependencies of the node at an index
+    pub(crate) fn from_fn<I, F>(nodes: Vec<&'d T>, mut depends_of: F) -> Self
+    where
+        I: IntoIterator<Item = &'d T>,
+        F: FnMut(usize) -> I,
+    {
+        let index = Self::index_of(&nodes);
+        // Filter any dependencies that aren't nodes themselves
+        let mut depends: Vec<Vec<_>> = (0..nodes.len())
+            .map(|dependent| {
+                depends_of(dependent)
+                    .into_iter()
+                    .filter_map(|dependency| index.get(dependency).copied())
+                    .collect()
+            })
+            .collect();
+        Self::from_parts(nodes, index, |dependent| mem::take(&mut depends[dependent]))
+    }
+
+    /// Build from a list of nodes and a function returning the indices of the dependencies of the node at an
+    /// index. Self references are filtered, but each index must be unique and in range.
+    #[inline]
+    pub(crate) fn from_indices<I, F>(nodes: Vec<&'d T>, depends_of: F) -> Self
+    where
+        I: IntoIterator<Item = usize>,
+        F: FnMut(usize) -> I,
+    {
+        let index = Self::index_of(&nodes);
+        Self::from_parts(nodes, index, depends_of)
+    }
+
+    fn index_of(nodes: &[&'d T]) -> Map<&'d T, usize> {
+        nodes
+            .iter()
+            .enumerate()
+            .map(|(idx, &node)| (node, idx))
+            .collect()
+    }
+
+    fn from_parts<I, F>(nodes: Vec<&'d T>, index: Map<&'d T, usize>, mut depends_of: F) -> Self
+    where
+        I: IntoIterator<Item = usize>,
+        F: FnMut(usize) -> I,
+    {
+        let mut dependents = vec![Vec::new(); nodes.len()];
+
+        let depends = (0..nodes.len())
+            .map(|dependent| {
+                let mut depends = Vec::new();
+                for dependency in depends_of(dependent) {
+                    // Filter any self references
+                    if dependency != dependent {
+                        depends.push(dependency);
+                        dependents[dependency].push(dependent);
+                    }
+                }
+                depends
+            })
+            .collect();
+
+        Graph {
+            nodes,
+            index,
+            depends,
+            dependents,
+        }
+    }
+
+    #[inline]
+    pub(crate) fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// Map a list of node indices back to their nodes
+    #[inline]
+    pub(crate) fn to_nodes(&self, indices: Vec<usize>) -> Vec<&'d T> {
+        indices.into_iter().map(|node| self.nodes[node]).collect()
+    }
+
+    /// Returns the number of effective dependencies of each node
+    pub(crate) fn edge_counts(&self) -> Vec<usize> {
+        self.depends.iter().map(|depends| depends.len()).collect()
+    }
+
+    /// Returns all nodes without any effective dependencies
+    pub(crate) fn no_edges(&self) -> Vec<usize> {
+        (0..self.len())
+            .filter(|&node| self.depends[node].is_empty())
+            .collect()
+    }
+
+    /// Returns the neighbors of each node when edge direction is ignored (dependencies and dependents). A
+    /// neighbor is listed twice if the two nodes depend on each other.
+    pub(crate) fn undirected(&self) -> Vec<Vec<usize>> {
+        self.depends
+            .iter()
+            .zip(&self.dependents)
+            .map(|(depends, dependents)| depends.iter().chain(dependents).copied().collect())
+            .collect()
+    }
+
+    /// Returns the weakly connected components of the graph (edge direction is ignored). Components are ordered
+    /// by their first node, and nodes are in index order within each component.
+    pub(crate) fn weak_components(&self) -> Vec<Vec<usize>> {
+        let adjacent = self.undirected();
+        let mut component_of = vec![usize::MAX; self.len()];
+        let mut count = 0;
+
+        for root in 0..self.len() {
+            if component_of[root] != usize::MAX {
+                continue;
+            }
+            component_of[root] = count;
+            let mut stack = vec![root];
+            while let Some(node) = stack.pop() {
+                for &neighbor in &adjacent[node] {
+                    if component_of[neighbor] == usize::MAX {
+                        component_of[neighbor] = count;
+                        stack.push(neighbor);
+                    }
+                }
+            }
+            count += 1;
+        }
+
+        let mut components = vec![Vec::new(); count];
+        for (node, component) in component_of.into_iter().enumerate() {
+            components[component].push(node);
+        }
+        components
+    }
+
+    /// Returns true if the graph doesn't contain a cycle else false
+    pub(crate) fn is_acyclic(&self) -> bool {
+        let mut edges = self.edge_counts();
+        let mut ready = self.no_edges();
+        let mut remaining = self.len();
+
+        // Kahn's algorithm, but only counting the nodes instead of recording the order
+        while let Some(node) = ready.pop() {
+            remaining -= 1;
+            for &dependent in &self.dependents[node] {
+                edges[dependent] -= 1;
+                if edges[dependent] == 0 {
+                    ready.push(dependent);
+                }
+            }
+        }
+
+        remaining == 0
+    }
+
+    /// Sort using Kahn's algorithm, always emitting the smallest ready node next. This yields the
+    /// lexicographically smallest topological order.
+    #[inline]
+    pub(crate) fn sort_min(&self) -> Result<Vec<usize>, CycleError>
+    where
+        T: Ord,
+    {
+        self.sort_by_key(|node| self.nodes[node])
+    }
+
+    /// Sort using Kahn's algorithm, always emitting the ready node with the smallest key next. Keys are
+    /// calculated once per node as it becomes ready and ties are broken by node index.
+    pub(crate) fn sort_by_key<K, F>(&self, f: F) -> Result<Vec<usize>, CycleError>
+    where
+        K: Ord,
+        F: FnMut(usize) -> K,
+    {
+        let order = self.partial_sort_by_key(f);
+        if order.len() == self.len() {
+            Ok(order)
+        } else {
+            Err(CycleError)
+        }
+    }
+
+    /// Same as `sort_by_key`, but if a cycle is detected, the nodes sorted before it was detected are
+    /// returned instead of an error (nodes not in the result are either in a cycle or depend on one)
+    pub(crate) fn partial_sort_by_key<K, F>(&self, mut f: F) -> Vec<usize>
+    where
+        K: Ord,
+        F: FnMut(usize) -> K,
+    {
+        let mut edges = self.edge_counts();
+        let mut ready: BinaryHeap<_> = self
+            .no_edges()
+            .into_iter()
+            .map(|node| Reverse((f(node), node)))
+            .collect();
+        let mut order = Vec::with_capacity(self.len());
+
+        while let Some(Reverse((_, node))) = ready.pop() {
+            order.push(node);
+
+            for &dependent in &self.dependents[node] {
+                edges[dependent] -= 1;
+                if edges[dependent] == 0 {
+                    ready.push(Reverse((f(dependent), dependent)));
+                }
+            }
+        }
+
+        order
+    }
+
+    /// Returns one cycle among the nodes left over by a partial sort (`sorted` is false for those), where each
+    /// node depends on the next and the last depends on the first. Every stuck node has an effective dependency
+    /// that is stuck as well (or it would have been sorted), so following those must eventually revisit a node.
+    pub(crate) fn cycle_within(&self, sorted: &[bool]) -> Vec<usize> {
+        let mut path = Vec::new();
+        let mut position = vec![usize::MAX; self.len()];
+        let mut node = match (0..self.len()).find(|&node| !sorted[node]) {
+            Some(node) => node,
+            None => return path,
+        };
+
+        loop {
+            position[node] = path.len();
+            path.push(node);
+            let next = *self.depends[node]
+                .iter()
+                .find(|&&dep| !sorted[dep])
+                .expect("stuck node without a stuck dependency");
+            if position[next] != usize::MAX {
+                path.drain(..position[next]);
+                return path;
+            }
+            node = next;
+        }
+    }
+
+    /// Returns the strongly connected components of the graph using Tarjan's algorithm. Components are
+    /// returned in dependency order: every component comes after the components it depends on.
+    pub(crate) fn components(&self) -> Vec<Vec<usize>> {
+        let mut index = vec![usize::MAX; self.len()];
+        let mut low = vec![0; self.len()];
+        let mut on_stack = vec![false; self.len()];
+        let mut stack = Vec::new();
+        let mut components = Vec::new();
+        let mut next_index = 0;
+
+        for root in 0..self.len() {
+            if index[root] != usize::MAX {
+                continue;
+            }
+            index[root] = next_index;
+            low[root] = next_index;
+            next_index += 1;
+            stack.push(root);
+            on_stack[root] = true;
+
+            // Node plus the index of the next dependency to visit
+            let mut calls = vec![(root, 0)];
+
+            while let Some((node, next)) = calls.last_mut() {
+                let node = *node;
+                if let Some(&dep) = self.depends[node].get(*next) {
+                    *next += 1;
+                    if index[dep] == usize::MAX {
+                        index[dep] = next_index;
+                        low[dep] = next_index;
+                        next_index += 1;
+                        stack.push(dep);
+                        on_stack[dep] = true;
+                        calls.push((dep, 0));
+                    } else if on_stack[dep] {
+                        low[node] = low[node].min(index[dep]);
+                    }
+                    continue;
+                }
+
+                calls.pop();
+                if let Some(&(parent, _)) = calls.last() {
+                    low[parent] = low[parent].min(low[node]);
+                }
+
+                if low[node] == index[node] {
+                    let mut component = Vec::new();
+                    while let Some(member) = stack.pop() {
+                        on_stack[member] = false;
+                        component.push(member);
+                        if member == node {
+                            break;
+                        }
+                    }
+                    components.push(component);
+                }
+            }
+        }
+
+        components
+    }
+}
+
+// *** Kahn ***
+
+// The set of ready nodes, which decides the node emitted next
+enum Ready {
+    Stack(Vec<usize>),
+    Queue(VecDeque<usize>),
+    // Ready nodes along with their rank, plus the rank of every node (smallest first)
+    Heap(BinaryHeap<Reverse<(usize, usize)>>, Vec<usize>),
+}
+
+impl Ready {
+    #[inline]
+    fn push(&mut self, node: usize) {
+        match self {
+            Ready::Stack(stack) => stack.push(node),
+            Ready::Queue(queue) => queue.push_back(node),
+            Ready::Heap(heap, rank) => heap.push(Reverse((rank[node], node))),
+        }
+    }
+
+    #[inline]
+    fn pop(&mut self) -> Option<usize> {
+        match self {
+            Ready::Stack(stack) => stack.pop(),
+            Ready::Queue(queue) => queue.pop_front(),
+            Ready::Heap(heap, _) => heap.pop().map(|Reverse((_, node))| node),
+        }
+    }
+}
+
+/// Lazy Kahn's algorithm over a graph, where the order of ready nodes is decided by how it was created
+pub(crate) struct Kahn<'d, T> {
+    pub(crate) graph: Graph<'d, T>,
+    edges: Vec<usize>,
+    ready: Ready,
+    remaining: usize,
+}
+
+impl<'d, T> Kahn<'d, T>
+where
+    T: Eq + Hash,
+{
+    fn new(graph: Graph<'d, T>, mut ready: Ready) -> Self {
+        for node in graph.no_edges() {
+            ready.push(node);
+        }
+
+        Kahn {
+            edges: graph.edge_counts(),
+            ready,
+            remaining: graph.len(),
+            graph,
+        }
+    }
+
+    /// Create from a graph, emitting the node that became ready last first
+    #[inline]
+    pub(crate) fn new_lifo(graph: Graph<'d, T>) -> Self {
+        Self::new(graph, Ready::Stack(Vec::new()))
+    }
+
+    /// Create from a graph, emitting the node that became ready first first
+    #[inline]
+    pub(crate) fn new_fifo(graph: Graph<'d, T>) -> Self {
+        Self::new(graph, Ready::Queue(VecDeque::new()))
+    }
+
+    /// Create from a graph and the rank of each node, emitting the ready node with the smallest rank first (ties
+    /// are broken by node index)
+    #[inline]
+    pub(crate) fn new_ranked(graph: Graph<'d, T>, rank: Vec<usize>) -> Self {
+        Self::new(graph, Ready::Heap(BinaryHeap::new(), rank))
+    }
+
+    /// Create from a graph, emitting the smallest ready node (by its `Ord` implementation) first
+    #[inline]
+    pub(crate) fn new_min(graph: Graph<'d, T>) -> Self
+    where
+        T: Ord,
+    {
+        Self::new_min_by_key(graph, |node| node)
+    }
+
+    /// Create from a graph, emitting the ready node with the smallest key first (ties are broken by node index)
+    pub(crate) fn new_min_by_key<K, F>(graph: Graph<'d, T>, mut f: F) -> Self
+    where
+        K: Ord,
+        F: FnMut(&'d T) -> K,
+    {
+        let keys: Vec<_> = graph.nodes.iter().map(|&node| f(node)).collect();
+        let mut order: Vec<_> = (0..graph.len()).collect();
+        // NOTE: The sort is stable, so equal keys keep their index order
+        order.sort_by(|&a, &b| keys[a].cmp(&keys[b]));
+        let mut rank = vec![0; graph.len()];
+        for (pos, node) in order.into_iter().enumerate() {
+            rank[node] = pos;
+        }
+        Self::new_ranked(graph, rank)
+    }
+}
+
+impl<T> Iterator for Kahn<'_, T> {
+    type Item = Result<usize, CycleError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let node = match self.ready.pop() {
+            Some(node) => node,
+            None if self.remaining == 0 => return None,
+            None => {
+                self.remaining = 0;
+                return Some(Err(CycleError));
+            }
+        };
+
+        self.remaining -= 1;
+        for &dependent in &self.graph.dependents[node] {
+            self.edges[dependent] -= 1;
+            if self.edges[dependent] == 0 {
+                self.ready.push(dependent);
+            }
+        }
+        Some(Ok(node))
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, Some(self.remaining))
+    }
+}
+
+// *** Layers ***
+
+/// Iterator over the "layers" of a sort - each layer holds every node whose dependencies were all in
+/// earlier layers, so the nodes within a layer are independent of each other
+pub(crate) struct Layers<'d, T> {
+    pub(crate) graph: Graph<'d, T>,
+    steps: LayerSteps,
+}
+
+impl<'d, T> Layers<'d, T>
+where
+    T: Eq + Hash,
+{
+    pub(crate) fn new(graph: Graph<'d, T>) -> Self {
+        Layers {
+            steps: LayerSteps::new(&graph),
+            graph,
+        }
+    }
+}
+
+impl<T> Iterator for Layers<'_, T> {
+    type Item = Result<Vec<usize>, CycleError>;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        self.steps.next_layer(&self.graph.dependents)
+    }
+}
+
+// The state of `Layers` without the graph, so it can outlive the borrow of the nodes (the dependents are passed
+// to each step instead)
+pub(crate) struct LayerSteps {
+    edges: Vec<usize>,
+    ready: Vec<usize>,
+    remaining: usize,
+}
+
+impl LayerSteps {
+    pub(crate) fn new<T>(graph: &Graph<'_, T>) -> Self
+    where
+        T: Eq + Hash,
+    {
+        LayerSteps {
+            edges: graph.edge_counts(),
+            ready: graph.no_edges(),
+            remaining: graph.len(),
+        }
+    }
+
+    pub(crate) fn next_layer(
+        &mut self,
+        dependents: &[Vec<usize>],
+    ) -> Option<Result<Vec<usize>, CycleError>> {
+        if self.ready.is_empty() {
+            return match self.remaining {
+                0 => None,
+                _ => {
+                    self.remaining = 0;
+                    Some(Err(CycleError))
+                }
+            };
+        }
+
+        let layer = std::mem::take(&mut self.ready);
+        for &node in &layer {
+            for &dependent in &dependents[node] {
+                self.edges[dependent] -= 1;
+                if self.edges[dependent] == 0 {
+                    self.ready.push(dependent);
+                }
+            }
+        }
+        self.remaining -= layer.len();
+        Some(Ok(layer))
+    }
+}
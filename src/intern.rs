@@ -0,0 +1,211 @@
+use std::sync::Arc;
+
+use crate::{CycleError, Map, TopoSort};
+
+// *** Interner ***
+
+/// Maps strings to dense `u32` ids (assigned in order of first appearance) and back again. Each string is only
+/// stored once.
+#[derive(Clone, Debug, Default)]
+pub struct Interner {
+    ids: Map<Arc<str>, u32>,
+    names: Vec<Arc<str>>,
+}
+
+impl Interner {
+    /// Initialize a new interner with zero capacity
+    #[inline]
+    pub fn new() -> Self {
+        Interner {
+            ids: Map::new(),
+            names: Vec::new(),
+        }
+    }
+
+    /// Initialize an empty interner with a given capacity
+    #[inline]
+    pub fn with_capacity(capacity: usize) -> Self {
+        Interner {
+            ids: Map::with_capacity(capacity),
+            names: Vec::with_capacity(capacity),
+        }
+    }
+
+    /// Returns the id of a string, assigning the next one if it hasn't been seen before
+    ///
+    /// # Panics
+    ///
+    /// Panics if more than `u32::MAX` strings are interned
+    pub fn intern(&mut self, name: &str) -> u32 {
+        if let Some(&id) = self.ids.get(name) {
+            return id;
+        }
+
+        let id = u32::try_from(self.names.len()).expect("too many interned strings");
+        let name: Arc<str> = Arc::from(name);
+        self.ids.insert(Arc::clone(&name), id);
+        self.names.push(name);
+        id
+    }
+
+    /// Returns the id of a string, if it was interned, else None
+    #[inline]
+    pub fn get(&self, name: &str) -> Option<u32> {
+        self.ids.get(name).copied()
+    }
+
+    /// Returns the string of an id, if it was assigned, else None
+    #[inline]
+    pub fn resolve(&self, id: u32) -> Option<&str> {
+        self.names.get(id as usize).map(|name| &**name)
+    }
+
+    /// Returns true if no strings were interned otherwise false
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty()
+    }
+
+    /// Returns the number of interned strings
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.names.len()
+    }
+}
+
+// *** InternedTopoSort ***
+
+/// A `TopoSort` over strings that interns every node and sorts over small `u32` ids internally, so long strings
+/// are only hashed once on insertion instead of once per edge on every sort.
+#[derive(Clone, Default)]
+pub struct InternedTopoSort {
+    interner: Interner,
+    topo_sort: TopoSort<u32>,
+}
+
+impl InternedTopoSort {
+    /// Initialize a new struct with zero capacity. It will not allocate until the first insertion
+    #[inline]
+    pub fn new() -> Self {
+        InternedTopoSort {
+            interner: Interner::new(),
+            topo_sort: TopoSort::new(),
+        }
+    }
+
+    /// Initialize an empty struct with a given capacity
+    #[inline]
+    pub fn with_capacity(capacity: usize) -> Self {
+        InternedTopoSort {
+            interner: Interner::with_capacity(capacity),
+            topo_sort: TopoSort::with_capacity(capacity),
+        }
+    }
+
+    /// Insert into this struct with the given node and an iterator of its dependencies. If the node was
+    /// already inserted, its dependencies are overwritten
+    pub fn insert<I, S>(&mut self, node: &str, i: I)
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let node = self.interner.intern(node);
+        let depends: Vec<_> = i
+            .into_iter()
+            .map(|dependency| self.interner.intern(dependency.as_ref()))
+            .collect();
+        self.topo_sort.insert(node, depends);
+    }
+
+    /// Returns true if the node was inserted otherwise false
+    #[inline]
+    pub fn contains(&self, node: &str) -> bool {
+        self.interner
+            .get(node)
+            .is_some_and(|id| self.topo_sort.get(&id).is_some())
+    }
+
+    /// Start the sort process and return an iterator of the resolved nodes
+    pub fn nodes(&self) -> impl Iterator<Item = Result<&str, CycleError>> + '_ {
+        self.topo_sort
+            .nodes()
+            .map(|result| result.map(|&id| self.resolve(id)))
+    }
+
+    /// Sort and return a vector of the resolved nodes. If a cycle is detected, an error is returned instead.
+    #[inline]
+    pub fn try_vec_nodes(&self) -> Result<Vec<&str>, CycleError> {
+        self.nodes().collect()
+    }
+
+    /// Returns the interner used to map nodes to ids
+    #[inline]
+    pub fn interner(&self) -> &Interner {
+        &self.interner
+    }
+
+    /// Returns the inner `TopoSort` over ids (see `interner` to resolve them)
+    #[inline]
+    pub fn as_topo_sort(&self) -> &TopoSort<u32> {
+        &self.topo_sort
+    }
+
+    /// Returns true if there aren't any nodes added otherwise false
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.topo_sort.is_empty()
+    }
+
+    /// Returns the number of nodes added to the collection
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.topo_sort.len()
+    }
+
+    fn resolve(&self, id: u32) -> &str {
+        self.interner
+            .resolve(id)
+            .expect("id was not assigned by this interner")
+    }
+}
+
+// *** Tests ***
+
+#[cfg(test)]
+mod tests {
+    use crate::{CycleError, InternedTopoSort, Interner};
+
+    #[test]
+    fn test_interner() {
+        let mut interner = Interner::new();
+        assert_eq!(0, interner.intern("alpha"));
+        assert_eq!(1, interner.intern("beta"));
+        assert_eq!(0, interner.intern("alpha"));
+        assert_eq!(Some(1), interner.get("beta"));
+        assert_eq!(None, interner.get("gamma"));
+        assert_eq!(Some("beta"), interner.resolve(1));
+        assert_eq!(None, interner.resolve(2));
+        assert_eq!(2, interner.len());
+    }
+
+    #[test]
+    fn test_interned_topo_sort() {
+        let mut topo_sort = InternedTopoSort::with_capacity(5);
+        topo_sort.insert("C", ["A", "B"]);
+        topo_sort.insert("E", vec![String::from("B"), String::from("C")]);
+        topo_sort.insert("A", Vec::<&str>::new());
+        topo_sort.insert("D", ["A", "C", "E"]);
+        topo_sort.insert("B", ["A"]);
+
+        assert_eq!(5, topo_sort.len());
+        assert!(topo_sort.contains("E"));
+        assert!(!topo_sort.contains("F"));
+        assert_eq!(
+            vec!["A", "B", "C", "E", "D"],
+            topo_sort.try_vec_nodes().unwrap()
+        );
+
+        topo_sort.insert("A", ["D"]); // cycle
+        assert_eq!(Err(CycleError), topo_sort.try_vec_nodes());
+    }
+}
@@ -0,0 +1,201 @@
+use std::hash::Hash;
+
+use crate::graph::Graph;
+use crate::{Map, Set, TopoSort};
+
+// *** Isomorphism ***
+
+impl<T> TopoSort<T>
+where
+    T: Eq + Hash,
+{
+    /// Returns true if this graph and `other` have the same structure under some renaming of their nodes (they
+    /// are isomorphic), else false. Only effective edges are compared, just as the sort sees them.
+    ///
+    /// Nodes are first colored by their degrees and then the colors are refined by those of their neighbors
+    /// until stable (Weisfeiler-Leman). Different color counts prove the graphs differ, otherwise a backtracking
+    /// search only pairs nodes of the same color. The answer is exact, but the search is exponential in the
+    /// worst case (e.g. large regular graphs), so this is intended for small graphs such as in tests.
+    pub fn is_isomorphic_to(&self, other: &TopoSort<T>) -> bool {
+        let graphs = [
+            Graph::new(&self.node_depends),
+            Graph::new(&other.node_depends),
+        ];
+        let edge_count = |graph: &Graph<'_, T>| graph.depends.iter().map(Vec::len).sum::<usize>();
+        if graphs[0].len() != graphs[1].len() || edge_count(&graphs[0]) != edge_count(&graphs[1]) {
+            return false;
+        }
+
+        let colors = match refine_colors(&graphs) {
+            Some(colors) => colors,
+            None => return false,
+        };
+
+        let edges: Set<_> = graphs[1]
+            .depends
+            .iter()
+            .enumerate()
+            .flat_map(|(node, depends)| depends.iter().map(move |&dep| (node, dep)))
+            .collect();
+        let mut search = Search {
+            first: &graphs[0],
+            colors: &colors,
+            edges: &edges,
+            mapping: vec![None; graphs[0].len()],
+            used: vec![false; graphs[1].len()],
+        };
+        search.extend(0)
+    }
+}
+
+// Color the nodes of both graphs with a shared palette, refining until the number of colors is stable. Returns
+// None if the graphs end up with different numbers of nodes of some color.
+fn refine_colors<T>(graphs: &[Graph<'_, T>; 2]) -> Option<[Vec<usize>; 2]>
+where
+    T: Eq + Hash,
+{
+    let mut palette = Map::new();
+    let mut colors = graphs.each_ref().map(|graph| {
+        (0..graph.len())
+            .map(|node| {
+                let next = palette.len();
+                let degree = (graph.depends[node].len(), graph.dependents[node].len());
+                *palette.entry(degree).or_insert(next)
+            })
+            .collect::<Vec<_>>()
+    });
+    let mut color_count = palette.len();
+
+    loop {
+        let histograms = colors.each_ref().map(|colors| {
+            let mut histogram = colors.clone();
+            histogram.sort_unstable();
+            histogram
+        });
+        if histograms[0] != histograms[1] {
+            return None;
+        }
+
+        let mut palette = Map::new();
+        let refined = [0, 1].map(|side| {
+            let (graph, colors) = (&graphs[side], &colors[side]);
+            (0..graph.len())
+                .map(|node| {
+                    let neighbors = |nodes: &[usize]| {
+                        let mut colors: Vec<_> = nodes.iter().map(|&node| colors[node]).collect();
+                        colors.sort_unstable();
+                        colors
+                    };
+                    let signature = (
+                        colors[node],
+                        neighbors(&graph.depends[node]),
+                        neighbors(&graph.dependents[node]),
+                    );
+                    let next = palette.len();
+                    *palette.entry(signature).or_insert(next)
+                })
+                .collect::<Vec<_>>()
+        });
+
+        if palette.len() == color_count {
+            return Some(colors);
+        }
+        color_count = palette.len();
+        colors = refined;
+    }
+}
+
+// Backtracking search for a color preserving mapping of the nodes of the first graph onto the second
+struct Search<'a, 'd, T> {
+    first: &'a Graph<'d, T>,
+    colors: &'a [Vec<usize>; 2],
+    // Edges of the second graph
+    edges: &'a Set<(usize, usize)>,
+    mapping: Vec<Option<usize>>,
+    used: Vec<bool>,
+}
+
+impl<T> Search<'_, '_, T> {
+    fn extend(&mut self, node: usize) -> bool {
+        if node == self.mapping.len() {
+            return true;
+        }
+
+        for candidate in 0..self.used.len() {
+            if self.used[candidate]
+                || self.colors[0][node] != self.colors[1][candidate]
+                || !self.consistent(node, candidate)
+            {
+                continue;
+            }
+
+            self.mapping[node] = Some(candidate);
+            self.used[candidate] = true;
+            if self.extend(node + 1) {
+                return true;
+            }
+            self.mapping[node] = None;
+            self.used[candidate] = false;
+        }
+        false
+    }
+
+    // Every edge between `node` and an already mapped node must exist between their images. Since both graphs
+    // have the same number of edges, a complete mapping is then an isomorphism.
+    fn consistent(&self, node: usize, candidate: usize) -> bool {
+        let depends = self.first.depends[node]
+            .iter()
+            .filter_map(|&dep| self.mapping[dep].map(|dep| (candidate, dep)));
+        let dependents = self.first.dependents[node].iter().filter_map(|&dependent| {
+            self.mapping[dependent].map(|dependent| (dependent, candidate))
+        });
+        depends
+            .chain(dependents)
+            .all(|edge| self.edges.contains(&edge))
+    }
+}
+
+// *** Tests ***
+
+#[cfg(test)]
+mod tests {
+    use crate::TopoSort;
+
+    #[test]
+    fn test_is_isomorphic_to() {
+        let mut topo_sort = TopoSort::with_capacity(5);
+        topo_sort.insert("C", vec!["A", "B", "F"]); // There is no 'F'
+        topo_sort.insert("E", vec!["B", "C"]);
+        topo_sort.insert("A", vec!["A"]); // Self dependency
+        topo_sort.insert("D", vec!["A", "C", "E"]);
+        topo_sort.insert("B", vec!["A"]);
+
+        let mut relabeled = TopoSort::with_capacity(5);
+        relabeled.insert("c", vec!["a", "b"]);
+        relabeled.insert("e", vec!["b", "c"]);
+        relabeled.insert("a", vec![]);
+        relabeled.insert("d", vec!["a", "c", "e"]);
+        relabeled.insert("b", vec!["a"]);
+        assert!(topo_sort.is_isomorphic_to(&relabeled));
+        assert!(relabeled.is_isomorphic_to(&topo_sort));
+
+        // Same number of nodes and edges, but "D" now depends on "B" instead of "A"
+        relabeled.insert("d", vec!["b", "c", "e"]);
+        assert!(!topo_sort.is_isomorphic_to(&relabeled));
+    }
+
+    #[test]
+    fn test_is_isomorphic_to_regular() {
+        // Every node has one dependency and one dependent, so only the search can tell these apart
+        let ring = |len: u32, offset: u32| {
+            (0..len).map(move |node| (offset + node, offset + (node + 1) % len))
+        };
+        let six = TopoSort::from_edges(ring(6, 0));
+        let three_three = TopoSort::from_edges(ring(3, 0).chain(ring(3, 10)));
+
+        assert!(six.is_isomorphic_to(&TopoSort::from_edges(ring(6, 100))));
+        assert!(!six.is_isomorphic_to(&three_three));
+        assert!(three_three.is_isomorphic_to(&three_three.clone()));
+        assert!(TopoSort::<u32>::new().is_isomorphic_to(&TopoSort::new()));
+    }
+}
@@ -0,0 +1,160 @@
+use std::hash::Hash;
+
+use crate::graph::Graph;
+use crate::{CycleError, Map, Set, TopoSort};
+
+// *** LabeledTopoSort ***
+
+/// A variant of `TopoSort` where every edge carries a set of labels, such as the sources that contributed it.
+/// Labels are ignored by the sort, but are preserved (and combined) when merging graphs.
+#[derive(Clone)]
+pub struct LabeledTopoSort<T, L>
+where
+    T: Eq + Hash,
+    L: Eq + Hash,
+{
+    // Dependent -> Dependency -> Labels
+    node_depends: Map<T, Map<T, Set<L>>>,
+}
+
+impl<T, L> LabeledTopoSort<T, L>
+where
+    T: Eq + Hash,
+    L: Eq + Hash,
+{
+    /// Initialize a new struct with zero capacity. It will not allocate until the first insertion
+    #[inline]
+    pub fn new() -> Self {
+        LabeledTopoSort {
+            node_depends: Map::new(),
+        }
+    }
+
+    /// Insert the given node without any dependencies if it doesn't already exist
+    #[inline]
+    pub fn insert_node(&mut self, node: T) {
+        self.node_depends.entry(node).or_default();
+    }
+
+    /// Insert an edge where `node` depends on `dependency`, adding `label` to the edge's labels. The node is
+    /// inserted if it doesn't exist, but (just like `TopoSort`) the dependency must be inserted as a node
+    /// itself to be sorted.
+    pub fn insert_labeled(&mut self, node: T, dependency: T, label: L) {
+        self.node_depends
+            .entry(node)
+            .or_default()
+            .entry(dependency)
+            .or_default()
+            .insert(label);
+    }
+
+    /// Returns the labels of the edge where `node` depends on `dependency`, if found, else None
+    #[inline]
+    pub fn edge_labels(&self, node: &T, dependency: &T) -> Option<&Set<L>> {
+        self.node_depends.get(node)?.get(dependency)
+    }
+
+    /// Merge all nodes and edges of `other` into this struct. The labels of edges that exist in both are combined.
+    pub fn merge(&mut self, other: LabeledTopoSort<T, L>) {
+        for (node, depends) in other.node_depends {
+            let entry = self.node_depends.entry(node).or_default();
+            for (dependency, labels) in depends {
+                entry.entry(dependency).or_default().extend(labels);
+            }
+        }
+    }
+
+    /// Sort and return a vector (with borrowed nodes) of the results. If a cycle is detected, an error is
+    /// returned instead
+    pub fn try_vec_nodes(&self) -> Result<Vec<&T>, CycleError> {
+        let depends: Vec<_> = self.node_depends.values().collect();
+        let graph = Graph::from_fn(self.node_depends.keys().collect(), |node| {
+            depends[node].keys()
+        });
+        let order = graph.sort_by_key(|node| node)?;
+        Ok(graph.to_nodes(order))
+    }
+
+    /// Returns an unlabeled copy of this graph as a `TopoSort`
+    pub fn to_topo_sort(&self) -> TopoSort<T>
+    where
+        T: Clone,
+    {
+        let mut topo_sort = TopoSort::with_capacity(self.node_depends.len());
+        for (node, depends) in &self.node_depends {
+            topo_sort.insert(node.clone(), depends.keys().cloned());
+        }
+        topo_sort
+    }
+
+    /// Returns true if there aren't any nodes added otherwise false
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.node_depends.is_empty()
+    }
+
+    /// Returns the number of nodes added to the collection
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.node_depends.len()
+    }
+}
+
+impl<T, L> Default for LabeledTopoSort<T, L>
+where
+    T: Eq + Hash,
+    L: Eq + Hash,
+{
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// *** Tests ***
+
+#[cfg(test)]
+mod tests {
+    use crate::{LabeledTopoSort, Set};
+
+    #[test]
+    fn test_labeled_merge() {
+        let mut cargo = LabeledTopoSort::new();
+        cargo.insert_node("core");
+        cargo.insert_labeled("lib", "core", "Cargo.toml");
+        cargo.insert_labeled("app", "lib", "Cargo.toml");
+
+        let mut lock = LabeledTopoSort::new();
+        lock.insert_node("core");
+        lock.insert_labeled("lib", "core", "Cargo.lock");
+        lock.insert_labeled("app", "core", "Cargo.lock");
+
+        cargo.merge(lock);
+        assert_eq!(3, cargo.len());
+        assert_eq!(
+            Some(&Set::from_iter(vec!["Cargo.toml", "Cargo.lock"])),
+            cargo.edge_labels(&"lib", &"core")
+        );
+        assert_eq!(
+            Some(&Set::from_iter(vec!["Cargo.toml"])),
+            cargo.edge_labels(&"app", &"lib")
+        );
+        assert_eq!(
+            Some(&Set::from_iter(vec!["Cargo.lock"])),
+            cargo.edge_labels(&"app", &"core")
+        );
+        assert_eq!(None, cargo.edge_labels(&"core", &"app"));
+
+        assert_eq!(
+            vec![&"core", &"lib", &"app"],
+            cargo.try_vec_nodes().unwrap()
+        );
+        assert_eq!(
+            vec!["core", "lib", "app"],
+            cargo.to_topo_sort().try_owned_vec_nodes().unwrap()
+        );
+
+        cargo.insert_labeled("core", "app", "oops"); // cycle
+        assert!(cargo.try_vec_nodes().is_err());
+    }
+}
@@ -1,3 +1,4 @@
+#![forbid(unsafe_code)]
 #![warn(missing_docs)]
 
 //! A "cycle-safe" topological sort for a set of nodes with dependencies in Rust.
@@ -82,28 +83,87 @@
 //!       partial results (when cycle detected)
 //!     * `try_[into]_vec` functions - returns a `Vec` wrapped in a `Result` (full or no results)
 //!
+//! ## Deterministic Order
+//!
+//! By default, the order of nodes that are ready at the same time (ties) depends on `HashMap` iteration
+//! order and can vary between runs. With the `indexmap` feature enabled, ties are instead broken by
+//! insertion order (unless a comparator is set via `with_comparator`), so the sort is fully reproducible.
+//! Replacing the dependencies of an existing node keeps its original position. Removing a node via `take` or
+//! `remove` keeps the order of the others, while `remove_unchecked` stays O(1) by moving the last inserted node
+//! into the position of the removed one.
+//!
 //! ## Safety
 //!
-//! The crate uses two tiny `unsafe` blocks which use the addresses of `HashMap`
-//! keys in a new `HashMap`. This was necessary to avoid cloning inserted data on
-//! owned iteration by self referencing the struct. Since there is no removal in
-//! regular iteration (`iter()` or `for` loop using `&`), this should be safe as
-//! there is no chance of the data moving during borrowed iteration. During
-//! owned/consuming iteration (`into_iter()` or `for` without `&`), we remove the
-//! entries as we go. If Rust's `HashMap` were to change and shrink during removals,
-//! this iterator could break. If this makes you uncomfortable, simply don't use
-//! consuming iteration.
+//! The crate contains no `unsafe` code. Sorting assigns each node a dense index up front and operates on
+//! indices, so nodes are never cloned (outside of `owned` methods) and no references into the map are held
+//! across removals during owned/consuming iteration.
 //!
 
+mod checkpoint;
+mod compact;
+mod cycles;
+mod entry;
+mod explain;
+mod fingerprint;
+mod frozen;
+mod graph;
+mod intern;
+mod isomorphism;
+mod labeled;
+mod meta;
+mod ordered;
+mod orderings;
+#[cfg(feature = "rayon")]
+mod parallel;
+mod priority;
+mod query;
+#[cfg(feature = "json")]
+mod resume;
+mod schedule;
+mod scheduler;
+mod small;
+mod stable;
+mod text;
+mod verify;
+mod workspace;
+
+use std::cmp::Ordering;
 use std::hash::Hash;
 use std::ops::Index;
-use std::{error, fmt};
+use std::sync::mpsc::Sender;
+use std::sync::{Arc, OnceLock};
+use std::{error, fmt, mem};
+
+pub use checkpoint::Checkpoint;
+pub use cycles::CycleStrategy;
+use cycles::EdgeOrder;
+pub use entry::Entry;
+pub use explain::OrderReason;
+use frozen::Csr;
+pub use frozen::FrozenTopoSort;
+use graph::{Graph, Kahn};
+pub use intern::{InternedTopoSort, Interner};
+pub use labeled::LabeledTopoSort;
+pub use meta::TopoSortWith;
+pub use ordered::OrdTopoSort;
+pub use query::Reachability;
+#[cfg(feature = "json")]
+pub use resume::SortState;
+pub use scheduler::{ConcurrentTopoSort, Scheduler};
+pub use small::{SmallSet, SmallSetIter, SmallTopoSort};
+pub use stable::StableTopoSort;
+pub use text::ParseError;
+pub use verify::OrderViolation;
+use workspace::reuse;
+pub use workspace::{SortWorkspace, TopoSortWorkspaceIter};
 
 #[cfg(not(any(feature = "indexmap", feature = "indexmap-serde")))]
 use std::collections::{HashMap, HashSet};
 
 #[cfg(any(feature = "indexmap", feature = "indexmap-serde"))]
 use indexmap::{IndexMap, IndexSet};
+#[cfg(any(feature = "indexmap", feature = "indexmap-serde"))]
+use std::{cmp::Reverse, collections::BinaryHeap};
 
 #[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
@@ -118,9 +178,38 @@ type Map<K, V> = IndexMap<K, V>;
 #[cfg(any(feature = "indexmap", feature = "indexmap-serde"))]
 type Set<T> = IndexSet<T>;
 
+// Tie-break comparator shared by a `TopoSort` and its iterators
+type Comparator<T> = Arc<dyn Fn(&T, &T) -> Ordering + Send + Sync>;
+
+// Remove a node while preserving the insertion order of the rest (`IndexMap::remove` swaps in the last node)
+#[cfg(not(any(feature = "indexmap", feature = "indexmap-serde")))]
+#[inline]
+fn remove_node<T: Eq + Hash, V>(map: &mut Map<T, V>, node: &T) -> Option<(T, V)> {
+    map.remove_entry(node)
+}
+#[cfg(any(feature = "indexmap", feature = "indexmap-serde"))]
+#[inline]
+fn remove_node<T: Eq + Hash, V>(map: &mut Map<T, V>, node: &T) -> Option<(T, V)> {
+    map.shift_remove_entry(node)
+}
+
+// Remove a node in O(1). With `indexmap`, the last node is moved into the position of the removed one.
+#[cfg(not(any(feature = "indexmap", feature = "indexmap-serde")))]
+#[inline]
+fn swap_remove_node<T: Eq + Hash, V>(map: &mut Map<T, V>, node: &T) -> Option<V> {
+    map.remove(node)
+}
+#[cfg(any(feature = "indexmap", feature = "indexmap-serde"))]
+#[inline]
+fn swap_remove_node<T: Eq + Hash, V>(map: &mut Map<T, V>, node: &T) -> Option<V> {
+    map.swap_remove(node)
+}
+
 // *** Error ***
 
-/// An error type returned by the iterator when a cycle is detected in the dependency graph
+/// An error type returned by the iterator when a cycle is detected in the dependency graph. It carries no nodes:
+/// use `TopoSort::nodes_detailed`, `TopoSort::try_vec_detailed` or `TopoSort::try_owned_vec_detailed` to get a
+/// `DetailedCycleError` instead, or `TopoSort::find_cycle` to get the path of a cycle after the fact.
 #[derive(Clone, Copy, fmt::Debug, PartialEq)]
 pub struct CycleError;
 
@@ -132,6 +221,123 @@ impl fmt::Display for CycleError {
 
 impl error::Error for CycleError {}
 
+/// An error type returned when a cycle is detected in the dependency graph. Unlike `CycleError`, it carries the
+/// "stuck" nodes - those that could not be sorted because they are either part of a cycle or depend on one - as
+/// well as the path of one concrete cycle among them
+#[derive(Clone, fmt::Debug, PartialEq)]
+pub struct DetailedCycleError<T>
+where
+    T: Eq + Hash,
+{
+    stuck: Set<T>,
+    cycle: Vec<T>,
+}
+
+impl<T> DetailedCycleError<T>
+where
+    T: Eq + Hash,
+{
+    /// Returns the nodes that could not be sorted
+    #[inline]
+    pub fn stuck(&self) -> &Set<T> {
+        &self.stuck
+    }
+
+    /// Consume the error and return the nodes that could not be sorted
+    #[inline]
+    pub fn into_stuck(self) -> Set<T> {
+        self.stuck
+    }
+
+    /// Returns the nodes of one cycle in order, where each node depends on the next and the last node depends
+    /// on the first (e.g. `[A, B, C]` for `A -> B -> C -> A`)
+    #[inline]
+    pub fn cycle(&self) -> &[T] {
+        &self.cycle
+    }
+}
+
+impl<T> From<DetailedCycleError<T>> for CycleError
+where
+    T: Eq + Hash,
+{
+    #[inline]
+    fn from(_: DetailedCycleError<T>) -> Self {
+        CycleError
+    }
+}
+
+impl<T> fmt::Display for DetailedCycleError<T>
+where
+    T: Eq + Hash + fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("cycle detected:")?;
+        for (pos, node) in self.cycle.iter().chain(self.cycle.first()).enumerate() {
+            let sep = if pos == 0 { " " } else { " -> " };
+            write!(f, "{}{:?}", sep, node)?;
+        }
+        write!(f, " ({} node(s) could not be sorted)", self.stuck.len())
+    }
+}
+
+impl<T> error::Error for DetailedCycleError<T> where T: Eq + Hash + fmt::Debug {}
+
+/// An error type returned when an insertion would leave a node with more dependencies than the maximum allowed
+#[derive(Clone, Copy, fmt::Debug, PartialEq)]
+pub struct DegreeExceeded {
+    /// The number of dependencies the node would have had
+    pub degree: usize,
+    /// The maximum number of dependencies allowed per node
+    pub max_degree: usize,
+}
+
+impl fmt::Display for DegreeExceeded {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "node would have {} dependencies, but the maximum is {}",
+            self.degree, self.max_degree
+        )
+    }
+}
+
+impl error::Error for DegreeExceeded {}
+
+/// An error type returned by `TopoSort::try_add_dependency` when a dependency can't be added
+#[derive(Clone, Copy, fmt::Debug, PartialEq)]
+pub enum AddDependencyError {
+    /// Adding the dependency would introduce a cycle
+    Cycle(CycleError),
+    /// Adding the dependency would exceed the maximum degree (see `TopoSort::with_max_degree`)
+    DegreeExceeded(DegreeExceeded),
+}
+
+impl fmt::Display for AddDependencyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AddDependencyError::Cycle(err) => fmt::Display::fmt(err, f),
+            AddDependencyError::DegreeExceeded(err) => fmt::Display::fmt(err, f),
+        }
+    }
+}
+
+impl error::Error for AddDependencyError {}
+
+impl From<CycleError> for AddDependencyError {
+    #[inline]
+    fn from(err: CycleError) -> Self {
+        AddDependencyError::Cycle(err)
+    }
+}
+
+impl From<DegreeExceeded> for AddDependencyError {
+    #[inline]
+    fn from(err: DegreeExceeded) -> Self {
+        AddDependencyError::DegreeExceeded(err)
+    }
+}
+
 // *** SortResult ***
 
 /// Results of the sort - either full or partial results (if a cycle is detected)
@@ -162,10 +368,74 @@ impl<U> SortResults<U> {
     }
 }
 
+// *** NodeInspection ***
+
+/// Diagnostic view of a single node's declared dependencies as returned by `TopoSort::inspect`
+#[derive(Clone, fmt::Debug, PartialEq)]
+pub struct NodeInspection<'d, T>
+where
+    T: Eq + Hash,
+{
+    /// Declared dependencies that are nodes themselves and are honored by the sort
+    pub present_deps: Set<&'d T>,
+    /// Declared dependencies that were never inserted as nodes and are ignored by the sort
+    pub missing_deps: Set<&'d T>,
+    /// True if the node declared itself as a dependency (ignored by the sort)
+    pub self_loop: bool,
+}
+
+// *** CleanReport ***
+
+/// Report of the entries removed while building a `TopoSort` via `TopoSort::from_map_cleaned`
+#[derive(Clone, fmt::Debug, PartialEq)]
+pub struct CleanReport<T> {
+    /// Nodes that declared themselves as a dependency
+    pub self_loops: Vec<T>,
+    /// `(dependent, dependency)` edges whose dependency was never inserted as a node
+    pub dangling: Vec<(T, T)>,
+}
+
+impl<T> CleanReport<T> {
+    /// Returns true if nothing had to be removed else false
+    #[inline]
+    pub fn is_clean(&self) -> bool {
+        self.self_loops.is_empty() && self.dangling.is_empty()
+    }
+}
+
+// *** Edit ***
+
+/// A single proposed modification to a `TopoSort` (see `TopoSort::check_edits`)
+#[derive(Clone, fmt::Debug, PartialEq)]
+pub enum Edit<T> {
+    /// Add a `(dependent, dependency)` edge, inserting the dependent if it doesn't exist
+    AddEdge(T, T),
+    /// Remove a `(dependent, dependency)` edge
+    RemoveEdge(T, T),
+    /// Add a node without dependencies (no effect if it already exists)
+    AddNode(T),
+    /// Remove a node and scrub it from the dependency sets of all other nodes
+    RemoveNode(T),
+}
+
+// *** QueuePolicy ***
+
+/// The order in which nodes that are ready at the same time are emitted (see `TopoSort::nodes_with_policy`)
+#[derive(Clone, Copy, fmt::Debug, Default, PartialEq, Eq)]
+pub enum QueuePolicy {
+    /// The node that became ready last is emitted first, which favors depth
+    #[default]
+    Lifo,
+    /// The node that became ready first is emitted first, which favors breadth
+    Fifo,
+    /// The ready node with the smallest key is emitted first (see `TopoSort::nodes_with_policy_by_key`)
+    MinHeap,
+}
+
 // *** TopoSort ***
 
 /// TopoSort is used as a collection to map nodes to their dependencies. The actual sort is "lazy" and is performed during iteration.
-#[derive(Clone, Default)]
+#[derive(Default)]
 #[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
 pub struct TopoSort<T>
 where
@@ -173,6 +443,22 @@ where
 {
     // Dependent -> Dependencies
     node_depends: Map<T, Set<T>>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    comparator: Option<Comparator<T>>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    max_degree: Option<usize>,
+    // Node -> Priority (only nodes with a non-default priority)
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Map::is_empty")
+    )]
+    priorities: Map<T, i64>,
+    // Last successful order (as indices into `node_depends` iteration order), cleared on every mutation
+    #[cfg_attr(feature = "serde", serde(skip))]
+    cache: OnceLock<Vec<u32>>,
+    // When each edge was inserted (see `CycleStrategy::MostRecent`)
+    #[cfg_attr(feature = "serde", serde(skip))]
+    edge_order: EdgeOrder,
 }
 
 impl<T> TopoSort<T>
@@ -186,15 +472,90 @@ where
     pub fn new() -> Self {
         TopoSort {
             node_depends: Map::new(),
+            comparator: None,
+            max_degree: None,
+            priorities: Map::new(),
+            cache: OnceLock::new(),
+            edge_order: EdgeOrder::default(),
         }
     }
 
     /// Initialize a new struct from a map. The key represents the node to be sorted and the set is its dependencies
     #[inline]
     pub fn from_map(nodes: Map<T, Set<T>>) -> Self {
+        let mut edge_order = EdgeOrder::default();
+        for (node, depends) in &nodes {
+            for dependency in depends {
+                edge_order.insert(node, dependency);
+            }
+        }
+
         TopoSort {
             node_depends: nodes,
+            comparator: None,
+            max_degree: None,
+            priorities: Map::new(),
+            cache: OnceLock::new(),
+            edge_order,
+        }
+    }
+
+    /// Initialize a new struct from a list of `(dependent, dependency)` edges. Every node that appears on either
+    /// side of an edge is inserted as a node.
+    pub fn from_edges<I: IntoIterator<Item = (T, T)>>(edges: I) -> Self
+    where
+        T: Clone,
+    {
+        let mut topo_sort = Self::new();
+        topo_sort.insert_edges(edges);
+        topo_sort
+    }
+
+    /// Initialize a new struct from `(node, dependency)` pairs, such as rows with one dependency each. Pairs are
+    /// grouped by node, so a node that appears in several pairs gets all of their dependencies. Like `from_edges`
+    /// (which this is equivalent to), every dependency is inserted as a node as well.
+    #[inline]
+    pub fn from_pairs_grouped<I: IntoIterator<Item = (T, T)>>(pairs: I) -> Self
+    where
+        T: Clone,
+    {
+        Self::from_edges(pairs)
+    }
+
+    /// Initialize a new struct from a map (like `from_map`), but remove any self references and dependencies
+    /// that aren't nodes from the dependency sets first. A report of everything removed is returned as well.
+    pub fn from_map_cleaned(mut nodes: Map<T, Set<T>>) -> (Self, CleanReport<T>)
+    where
+        T: Clone,
+    {
+        let mut report = CleanReport {
+            self_loops: Vec::new(),
+            dangling: Vec::new(),
+        };
+        let depends: Vec<_> = nodes.values_mut().map(mem::take).collect();
+
+        let cleaned: Vec<_> = nodes
+            .keys()
+            .zip(depends)
+            .map(|(node, depends)| {
+                let mut cleaned = Set::with_capacity(depends.len());
+                for dependency in depends {
+                    if dependency == *node {
+                        report.self_loops.push(dependency);
+                    } else if nodes.contains_key(&dependency) {
+                        cleaned.insert(dependency);
+                    } else {
+                        report.dangling.push((node.clone(), dependency));
+                    }
+                }
+                cleaned
+            })
+            .collect();
+
+        for (depends, cleaned) in nodes.values_mut().zip(cleaned) {
+            *depends = cleaned;
         }
+        (Self::from_map(nodes), report)
     }
 
     /// Initialize an empty struct with a given capacity
@@ -202,11 +563,128 @@ where
     pub fn with_capacity(capacity: usize) -> Self {
         TopoSort {
             node_depends: Map::with_capacity(capacity),
+            comparator: None,
+            max_degree: None,
+            priorities: Map::new(),
+            cache: OnceLock::new(),
+            edge_order: EdgeOrder::default(),
+        }
+    }
+
+    /// Initialize a new struct with zero capacity and a comparator used to break ties. Whenever more than one node
+    /// is ready to be emitted, every sort (iteration, `to/into_vec`, `try_[into]_vec`, etc.) will emit the node that
+    /// compares as the least first, making the output deterministic.
+    ///
+    /// NOTE: The comparator is boxed (hence the `Send + Sync + 'static` bounds) and picking the next node becomes
+    /// a linear scan over all ready nodes, so this is slower than the default (unordered) sort
+    pub fn with_comparator<F>(comparator: F) -> Self
+    where
+        F: Fn(&T, &T) -> Ordering + Send + Sync + 'static,
+    {
+        TopoSort {
+            node_depends: Map::new(),
+            comparator: Some(Arc::new(comparator)),
+            max_degree: None,
+            priorities: Map::new(),
+            cache: OnceLock::new(),
+            edge_order: EdgeOrder::default(),
+        }
+    }
+
+    /// Initialize a new struct with zero capacity that rejects any node with more than `max_degree` dependencies
+    /// (as inserted). See `try_insert`, `try_add_dependencies`, `merge_with` and `extend_from_edges`.
+    ///
+    /// NOTE: `entry`, `retain` and `for_each_mut` hand out dependency sets that can't be checked, so they panic if a
+    /// maximum is set. The maximum is not serialized, so a deserialized struct has none.
+    #[inline]
+    pub fn with_max_degree(max_degree: usize) -> Self {
+        TopoSort {
+            node_depends: Map::new(),
+            comparator: None,
+            max_degree: Some(max_degree),
+            priorities: Map::new(),
+            cache: OnceLock::new(),
+            edge_order: EdgeOrder::default(),
         }
     }
 
+    // # Cache #
+
+    // All mutation of `node_depends` goes through here so the cached order is never stale
+    #[inline]
+    fn node_depends_mut(&mut self) -> &mut Map<T, Set<T>> {
+        self.cache.take();
+        &mut self.node_depends
+    }
+
+    // Returns the sorted order (as indices into `node_depends` iteration order), only sorting on first use after a
+    // mutation. A cycle is not cached.
+    fn cached_order(&self) -> Result<&[u32], CycleError> {
+        if let Some(order) = self.cache.get() {
+            return Ok(order);
+        }
+
+        let entries: Vec<_> = self.node_depends.iter().collect();
+        let mut inner = InnerIter::new(&entries, self.comparator.clone());
+        let order = std::iter::from_fn(|| inner.next(|idx| entries[idx as usize].0))
+            .map(|result| result.map(|idx| idx as u32))
+            .collect::<Result<_, _>>()?;
+        Ok(self.cache.get_or_init(|| order))
+    }
+
     // # Insertion #
 
+    // Overwrite the dependencies of a node, recording those it didn't have yet as the most recently inserted edges
+    fn replace_depends(&mut self, node: T, depends: Set<T>) {
+        if let Some(previous) = self.node_depends.get(&node) {
+            for dependency in previous.iter().filter(|&dep| !depends.contains(dep)) {
+                self.edge_order.remove(&node, dependency);
+            }
+        }
+        for dependency in &depends {
+            if !self
+                .node_depends
+                .get(&node)
+                .is_some_and(|previous| previous.contains(dependency))
+            {
+                self.edge_order.insert(&node, dependency);
+            }
+        }
+        self.node_depends_mut().insert(node, depends);
+    }
+
+    // Add to the dependencies of a node, recording those it didn't have yet as the most recently inserted edges
+    fn extend_depends<I: IntoIterator<Item = T>>(&mut self, node: T, i: I) {
+        let keyed: Vec<_> = i
+            .into_iter()
+            .map(|dependency| (EdgeOrder::key(&node, &dependency), dependency))
+            .collect();
+        let depends = self.node_depends_mut().entry(node).or_default();
+        let inserted: Vec<_> = keyed
+            .into_iter()
+            .filter_map(|(key, dependency)| depends.insert(dependency).then_some(key))
+            .collect();
+        for key in inserted {
+            self.edge_order.insert_key(key);
+        }
+    }
+
+    fn check_degree(&self, degree: usize) -> Result<(), DegreeExceeded> {
+        match self.max_degree {
+            Some(max_degree) if degree > max_degree => Err(DegreeExceeded { degree, max_degree }),
+            _ => Ok(()),
+        }
+    }
+
+    // Methods that hand out `&mut Set<T>` can't check the maximum degree, so they aren't available once one is set
+    pub(crate) fn assert_no_max_degree(&self, method: &str) {
+        assert!(
+            self.max_degree.is_none(),
+            "`{}` is not available with a maximum degree (see `with_max_degree`)",
+            method
+        );
+    }
+
     /// Insert into this struct with the given node and a slice of its dependencies
     pub fn insert_from_slice(&mut self, node: T, slice: &[T])
     where
@@ -216,139 +694,782 @@ where
     }
 
     /// Insert into this struct with the given node and a set of its dependencies
+    ///
+    /// # Panics
+    ///
+    /// Panics if a maximum degree was set (see `with_max_degree`) and the set exceeds it
     #[inline]
     pub fn insert_from_set(&mut self, node: T, depends: Set<T>) {
-        self.node_depends.insert(node, depends);
+        if let Err(err) = self.try_insert_from_set(node, depends) {
+            panic!("{}", err);
+        }
     }
 
-    /// Insert into this struct with the given node and an iterator of its dependencies
+    /// Insert into this struct with the given node and a set of its dependencies. If a maximum degree was set
+    /// (see `with_max_degree`) and the set exceeds it, an error is returned and nothing is inserted
+    pub fn try_insert_from_set(&mut self, node: T, depends: Set<T>) -> Result<(), DegreeExceeded> {
+        self.check_degree(depends.len())?;
+        self.replace_depends(node, depends);
+        Ok(())
+    }
+
+    /// Insert into this struct with the given node and an iterator of its dependencies. If the node was
+    /// already inserted, its dependencies are overwritten (use `add_dependencies` to merge them instead)
+    ///
+    /// # Panics
+    ///
+    /// Panics if a maximum degree was set (see `with_max_degree`) and the dependencies exceed it
     #[inline]
     pub fn insert<I: IntoIterator<Item = T>>(&mut self, node: T, i: I) {
         self.insert_from_set(node, i.into_iter().collect());
     }
 
-    // # Iterators #
-
-    /// Start the sort process and return an iterator of the results
+    /// Insert into this struct with the given node and an iterator of its dependencies. If a maximum degree was
+    /// set (see `with_max_degree`) and the dependencies exceed it, an error is returned and nothing is inserted
     #[inline]
-    pub fn nodes(&self) -> TopoSortNodeIter<'_, T> {
-        TopoSortNodeIter::new(&self.node_depends)
+    pub fn try_insert<I: IntoIterator<Item = T>>(
+        &mut self,
+        node: T,
+        i: I,
+    ) -> Result<(), DegreeExceeded> {
+        self.try_insert_from_set(node, i.into_iter().collect())
     }
 
-    /// Start the sort process and return a consuming iterator of the results
+    /// Set the dependencies of the given node from an iterator, overwriting any existing dependencies. This is
+    /// identical to `insert`, but makes the intent to overwrite explicit
     #[inline]
-    pub fn into_nodes(self) -> IntoTopoSortNodeIter<T> {
-        IntoTopoSortNodeIter::new(self.node_depends)
+    pub fn set_dependencies<I: IntoIterator<Item = T>>(&mut self, node: T, i: I) {
+        self.insert(node, i);
     }
 
-    /// Start the sort process and return an iterator of the results and a set of its dependents
+    /// Add dependencies from an iterator to the given node, merging them with any existing dependencies. If the
+    /// node was not yet inserted, it is inserted with just these dependencies
+    ///
+    /// # Panics
+    ///
+    /// Panics if a maximum degree was set (see `with_max_degree`) and the merged dependencies exceed it
     #[inline]
-    pub fn iter(&self) -> TopoSortIter<'_, T> {
-        TopoSortIter::new(&self.node_depends)
+    pub fn add_dependencies<I: IntoIterator<Item = T>>(&mut self, node: T, i: I) {
+        if let Err(err) = self.try_add_dependencies(node, i) {
+            panic!("{}", err);
+        }
     }
 
-    // # Cycles #
+    /// Add dependencies from an iterator to the given node, merging them with any existing dependencies. If a
+    /// maximum degree was set (see `with_max_degree`) and the merged dependencies exceed it, an error is returned
+    /// and the node is left unchanged
+    pub fn try_add_dependencies<I: IntoIterator<Item = T>>(
+        &mut self,
+        node: T,
+        i: I,
+    ) -> Result<(), DegreeExceeded> {
+        if self.max_degree.is_none() {
+            self.extend_depends(node, i);
+            return Ok(());
+        }
 
-    /// Sort and return true if a cycle was detected or false if it wasn't
-    pub fn cycle_detected(&self) -> bool {
-        self.iter().any(|result| result.is_err())
+        let depends: Set<T> = i.into_iter().collect();
+        let degree = match self.node_depends.get(&node) {
+            Some(existing) => {
+                existing.len()
+                    + depends
+                        .iter()
+                        .filter(|&dep| !existing.contains(dep))
+                        .count()
+            }
+            None => depends.len(),
+        };
+        self.check_degree(degree)?;
+        self.extend_depends(node, depends);
+        Ok(())
     }
 
-    // # to Vec #
+    /// Add a single dependency to the given node, keeping any existing dependencies. If the node was not yet
+    /// inserted, it is inserted with just this dependency
+    ///
+    /// # Panics
+    ///
+    /// Panics if a maximum degree was set (see `with_max_degree`) and the merged dependencies exceed it
+    #[inline]
+    pub fn add_dependency(&mut self, node: T, dependency: T) {
+        self.add_dependencies(node, std::iter::once(dependency));
+    }
 
-    /// Sort and return a vector (with borrowed nodes/dependencies) of the results. If a cycle is detected,
-    /// partial results will be inside the `Partial` variant, otherwise full results will be in the
-    /// `Full` variant.
-    pub fn to_vec(&self) -> SortResults<(&T, &Set<T>)> {
-        SortResults::new(self.iter().flatten().collect(), self.node_depends.len())
+    /// Add a single dependency to the given node (like `add_dependency`), unless doing so would introduce a cycle
+    /// or exceed the maximum degree (see `with_max_degree`), in which case an error is returned and the graph is
+    /// left unchanged. A self reference is never a cycle, as it is ignored by the sort.
+    pub fn try_add_dependency(&mut self, node: T, dependency: T) -> Result<(), AddDependencyError> {
+        if let Some(depends) = self.node_depends.get(&node) {
+            if !depends.contains(&dependency) {
+                self.check_degree(depends.len() + 1)?;
+            }
+        } else {
+            self.check_degree(1)?;
+        }
+
+        // A cycle is introduced if the dependency already (transitively) depends on the node. Since the node may
+        // not be inserted yet, edges to it count even while they are dangling.
+        if node != dependency {
+            if let Some((dependency, _)) = self.node_depends.get_key_value(&dependency) {
+                let mut visited = Set::new();
+                let mut stack = vec![dependency];
+
+                while let Some(current) = stack.pop() {
+                    for dep in &self.node_depends[current] {
+                        if *dep == node {
+                            return Err(AddDependencyError::Cycle(CycleError));
+                        }
+                        if let Some((dep, _)) = self.node_depends.get_key_value(dep) {
+                            if visited.insert(dep) {
+                                stack.push(dep);
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        self.extend_depends(node, Some(dependency));
+        Ok(())
     }
 
-    /// Sort and return a vector (with owned/consumed nodes/dependencies) of the results. If a cycle is detected,
-    /// partial results will be inside the `Partial` variant, otherwise full results will be in the
-    /// `Full` variant.
-    pub fn into_vec(self) -> SortResults<(T, Set<T>)> {
-        let len = self.node_depends.len();
-        let nodes: Vec<_> = self.into_iter().flatten().collect();
-        SortResults::new(nodes, len)
+    /// Merge all nodes of `other` into this struct. For nodes present in both, `resolve` is called with the node,
+    /// its existing dependencies and those from `other`, and returns the dependencies to keep (e.g. their union or
+    /// intersection). Other nodes are inserted as is. Priorities (see `insert_with_priority`) from `other` are
+    /// only kept for nodes without one. If a maximum degree was set (see `with_max_degree`) and a node's
+    /// dependencies exceed it, an error is returned and nothing is merged.
+    pub fn merge_with<F>(&mut self, other: TopoSort<T>, resolve: F) -> Result<(), DegreeExceeded>
+    where
+        F: Fn(&T, &Set<T>, &Set<T>) -> Set<T>,
+    {
+        let merged: Vec<_> = other
+            .node_depends
+            .into_iter()
+            .map(|(node, depends)| {
+                let depends = match self.node_depends.get(&node) {
+                    Some(existing) => resolve(&node, existing, &depends),
+                    None => depends,
+                };
+                (node, depends)
+            })
+            .collect();
+        for (_, depends) in &merged {
+            self.check_degree(depends.len())?;
+        }
+
+        for (node, priority) in other.priorities {
+            self.priorities.entry(node).or_insert(priority);
+        }
+        for (node, depends) in merged {
+            self.replace_depends(node, depends);
+        }
+        Ok(())
     }
 
-    /// Sort and return a vector (with owned/cloned nodes/dependencies) of the results. If a cycle is detected,
-    /// partial results will be inside the `Partial` variant, otherwise full results will be in the
-    /// `Full` variant.
-    pub fn to_owned_vec(&self) -> SortResults<(T, Set<T>)>
+    /// Merge a list of `(dependent, dependency)` edges into this struct. Both sides of every edge are inserted as
+    /// nodes if they don't already exist, and each dependency is added to those already present for its dependent.
+    /// If a maximum degree was set (see `with_max_degree`) and a node's merged dependencies exceed it, an error is
+    /// returned and nothing is inserted.
+    pub fn extend_from_edges<I: IntoIterator<Item = (T, T)>>(
+        &mut self,
+        edges: I,
+    ) -> Result<(), DegreeExceeded>
     where
         T: Clone,
     {
-        SortResults::new(
-            self.iter()
-                .flatten()
-                .map(|(node, depends)| (node.clone(), depends.clone()))
-                .collect(),
-            self.node_depends.len(),
-        )
-    }
+        if self.max_degree.is_none() {
+            self.insert_edges(edges);
+            return Ok(());
+        }
 
-    /// Sort and return a vector (with borrowed nodes) of the results. If a cycle is detected,
-    /// partial results will be inside the `Partial` variant, otherwise full results will be in the
-    /// `Full` variant.
-    pub fn to_vec_nodes(&self) -> SortResults<&T> {
-        SortResults::new(self.nodes().flatten().collect(), self.node_depends.len())
-    }
+        let edges: Vec<_> = edges.into_iter().collect();
+        // Dependent -> Dependencies it doesn't have yet
+        let mut added: Map<&T, Set<&T>> = Map::new();
+        for (dependent, dependency) in &edges {
+            let existing = self.node_depends.get(dependent);
+            if !existing.is_some_and(|depends| depends.contains(dependency)) {
+                added.entry(dependent).or_default().insert(dependency);
+            }
+        }
+        for (dependent, added) in added {
+            let existing = self.node_depends.get(dependent).map_or(0, Set::len);
+            self.check_degree(existing + added.len())?;
+        }
 
-    /// Sort and return a vector (with owned/consumed nodes) of the results. If a cycle is detected,
-    /// partial results will be inside the `Partial` variant, otherwise full results will be in the
-    /// `Full` variant.
-    pub fn into_vec_nodes(self) -> SortResults<T> {
-        let len = self.node_depends.len();
-        let nodes: Vec<_> = self.into_nodes().flatten().collect();
-        SortResults::new(nodes, len)
+        self.insert_edges(edges);
+        Ok(())
     }
 
-    /// Sort and return a vector (with owned/cloned nodes) of the results. If a cycle is detected,
-    /// partial results will be inside the `Partial` variant, otherwise full results will be in the
-    /// `Full` variant.
-    pub fn to_owned_vec_nodes(&self) -> SortResults<T>
+    // Inserts the edges without checking the maximum degree
+    fn insert_edges<I: IntoIterator<Item = (T, T)>>(&mut self, edges: I)
     where
         T: Clone,
     {
-        SortResults::new(
-            self.nodes().flatten().map(|node| node.clone()).collect(),
-            self.node_depends.len(),
-        )
+        for (dependent, dependency) in edges {
+            self.node_depends_mut()
+                .entry(dependency.clone())
+                .or_default();
+            self.extend_depends(dependent, Some(dependency));
+        }
     }
 
-    // # try Vec #
+    // # Removal #
+
+    /// Remove a node, returning the owned node and its dependency set (as inserted), if found, else None. The
+    /// removed node is also scrubbed from the dependency sets of all remaining nodes.
+    pub fn take(&mut self, node: &T) -> Option<(T, Set<T>)> {
+        let entry = remove_node(self.node_depends_mut(), node)?;
+        self.priorities.remove(node);
+        self.forget_edges(&entry.0, &entry.1);
+        for (dependent, depends) in &mut self.node_depends {
+            if depends.remove(node) {
+                self.edge_order.remove(dependent, node);
+            }
+        }
+        Some(entry)
+    }
 
-    /// Sort and return a vector (with borrowed nodes/dependencies) of the results. If a cycle is detected,
-    /// an error is returned instead
+    /// Remove a node, returning its dependency set (as inserted), if found, else None. The removed node is also
+    /// scrubbed from the dependency sets of all remaining nodes, so a node inserted later with the same value
+    /// doesn't silently become their dependency again.
     #[inline]
-    pub fn try_vec(&self) -> Result<Vec<(&T, &Set<T>)>, CycleError> {
-        self.iter().collect()
+    pub fn remove(&mut self, node: &T) -> Option<Set<T>> {
+        self.take(node).map(|(_, depends)| depends)
     }
 
-    /// Sort and return a vector (with owned/consumed nodes/dependencies) of the results. If a cycle is detected,
-    /// an error is returned instead
+    /// Remove a node, returning its dependency set (as inserted), if found, else None. Unlike `remove`, the node is
+    /// NOT scrubbed from the dependency sets of other nodes, which avoids visiting every node.
+    ///
+    /// NOTE: This is intended for nodes that no other node depends on (e.g. a leaf that was just added). If another
+    /// node does depend on it, that dependency is left dangling and is ignored by the sort, but it will come back
+    /// into effect if a node equal to the removed one is inserted again. With `indexmap`, the last inserted node
+    /// takes over the position of the removed one (see "Deterministic Order" in the crate docs).
     #[inline]
-    pub fn try_into_vec(self) -> Result<Vec<(T, Set<T>)>, CycleError> {
-        self.into_iter().collect()
+    pub fn remove_unchecked(&mut self, node: &T) -> Option<Set<T>> {
+        self.priorities.remove(node);
+        let depends = swap_remove_node(self.node_depends_mut(), node)?;
+        self.forget_edges(node, &depends);
+        Some(depends)
     }
 
-    /// Sort and return a vector (with owned/cloned nodes/dependencies) of the results. If a cycle is detected,
-    /// an error is returned instead
-    pub fn try_owned_vec(&self) -> Result<Vec<(T, Set<T>)>, CycleError>
+    // Forget when the edges from a removed node were inserted
+    fn forget_edges(&mut self, node: &T, depends: &Set<T>) {
+        for dependency in depends {
+            self.edge_order.remove(node, dependency);
+        }
+    }
+
+    /// Remove a node, returning its dependency set (as inserted) along with the nodes that depended on it,
+    /// if found, else None. The removed node is also scrubbed from the dependency set of each of those
+    /// dependents so they can be re-evaluated.
+    pub fn remove_tracked(&mut self, node: &T) -> Option<(Set<T>, Vec<T>)>
     where
         T: Clone,
     {
-        self.iter()
-            .map(|result| result.map(|(node, depends)| (node.clone(), depends.clone())))
-            .collect()
+        let (_, depends) = remove_node(self.node_depends_mut(), node)?;
+        self.priorities.remove(node);
+        self.forget_edges(node, &depends);
+        let edge_order = &mut self.edge_order;
+        let dependents = self
+            .node_depends
+            .iter_mut()
+            .filter_map(|(dependent, depends)| {
+                depends.remove(node).then(|| {
+                    edge_order.remove(dependent, node);
+                    dependent.clone()
+                })
+            })
+            .collect();
+        Some((depends, dependents))
+    }
+
+    /// Retain only the nodes for which `f` returns true, which is also given a mutable reference to the node's
+    /// dependency set. Removed nodes are also scrubbed from the dependency sets of all remaining nodes.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a maximum degree was set (see `with_max_degree`), before anything is changed, as the dependency
+    /// sets handed to `f` can't be checked against it
+    pub fn retain<F: FnMut(&T, &mut Set<T>) -> bool>(&mut self, mut f: F) {
+        self.assert_no_max_degree("retain");
+        let nodes = mem::take(self.node_depends_mut());
+        let mut removed = Set::new();
+        self.node_depends = Map::with_capacity(nodes.len());
+
+        for (node, mut depends) in nodes {
+            if f(&node, &mut depends) {
+                self.node_depends.insert(node, depends);
+            } else {
+                self.forget_edges(&node, &depends);
+                removed.insert(node);
+            }
+        }
+
+        if !removed.is_empty() {
+            for (dependent, depends) in &mut self.node_depends {
+                depends.retain(|dep| {
+                    let keep = !removed.contains(dep);
+                    if !keep {
+                        self.edge_order.remove(dependent, dep);
+                    }
+                    keep
+                });
+            }
+            self.priorities.retain(|node, _| !removed.contains(node));
+        }
+    }
+
+    /// Remove a single dependency of a node, leaving its other dependencies (and the dependency itself, if it is
+    /// a node) in place. Returns true if the node had that dependency, else false.
+    #[inline]
+    pub fn remove_dependency(&mut self, node: &T, dependency: &T) -> bool {
+        let removed = self
+            .node_depends_mut()
+            .get_mut(node)
+            .is_some_and(|depends| depends.remove(dependency));
+        if removed {
+            self.edge_order.remove(node, dependency);
+        }
+        removed
+    }
+
+    // # Iterators #
+
+    /// Start the sort process and return an iterator of the results
+    #[inline]
+    pub fn nodes(&self) -> TopoSortNodeIter<'_, T> {
+        TopoSortNodeIter::new(&self.node_depends, self.comparator.clone())
+    }
+
+    /// Start the sort process and return a consuming iterator of the results
+    #[inline]
+    pub fn into_nodes(self) -> IntoTopoSortNodeIter<T> {
+        IntoTopoSortNodeIter::new(self.node_depends, self.comparator)
+    }
+
+    /// Start the sort process and return an iterator of the results. Unlike `nodes`, if a cycle is detected the error
+    /// includes the set of nodes that could not be sorted.
+    #[inline]
+    pub fn nodes_detailed(&self) -> TopoSortDetailedNodeIter<'_, T> {
+        TopoSortDetailedNodeIter(TopoSortIter::new(
+            &self.node_depends,
+            self.comparator.clone(),
+        ))
+    }
+
+    /// Start the sort process and return a "best effort" iterator of the results. Instead of an error, it ends
+    /// once no more nodes can be sorted, after which the nodes trapped by a cycle (part of one or depending on
+    /// one) are available via `TopoSortLenientIter::stuck`. This allows the sortable nodes to still be processed.
+    #[inline]
+    pub fn iter_lenient(&self) -> TopoSortLenientIter<'_, T> {
+        TopoSortLenientIter {
+            iter: TopoSortIter::new(&self.node_depends, self.comparator.clone()),
+            stuck: Set::new(),
+        }
+    }
+
+    /// Start the sort process and return an iterator of the results and a set of its dependents
+    #[inline]
+    pub fn iter(&self) -> TopoSortIter<'_, T> {
+        TopoSortIter::new(&self.node_depends, self.comparator.clone())
+    }
+
+    /// Start the sort process and return an iterator of the results, always emitting the smallest of the nodes
+    /// that are ready next (using a binary heap). This yields the lexicographically smallest topological order,
+    /// which is deterministic and easy to read (e.g. for string nodes). If a cycle is detected, an error is
+    /// yielded last.
+    pub fn sorted_nodes(&self) -> impl Iterator<Item = Result<&T, CycleError>> + '_
+    where
+        T: Ord,
+    {
+        let mut kahn = Kahn::new_min(Graph::new(&self.node_depends));
+        std::iter::from_fn(move || {
+            let result = kahn.next()?;
+            Some(result.map(|node| kahn.graph.nodes[node]))
+        })
+    }
+
+    /// Start the sort process and return an iterator of the results, emitting nodes that are ready at the same
+    /// time in the order decided by `policy`. If a cycle is detected, an error is yielded last.
+    ///
+    /// NOTE: There is no key here, so `QueuePolicy::MinHeap` emits the ready node that comes first in iteration
+    /// order (insertion order with the `indexmap` feature). Use `nodes_with_policy_by_key` (e.g. with
+    /// `|node| node`) to compare the nodes themselves. A comparator set by `with_comparator` is not used.
+    pub fn nodes_with_policy(
+        &self,
+        policy: QueuePolicy,
+    ) -> impl Iterator<Item = Result<&T, CycleError>> + '_ {
+        let graph = Graph::new(&self.node_depends);
+        let mut kahn = match policy {
+            QueuePolicy::Lifo => Kahn::new_lifo(graph),
+            QueuePolicy::Fifo => Kahn::new_fifo(graph),
+            QueuePolicy::MinHeap => {
+                let rank = (0..graph.len()).collect();
+                Kahn::new_ranked(graph, rank)
+            }
+        };
+        std::iter::from_fn(move || {
+            let result = kahn.next()?;
+            Some(result.map(|node| kahn.graph.nodes[node]))
+        })
+    }
+
+    /// Start the sort process and return an iterator of the results, emitting nodes that are ready at the same
+    /// time in the order decided by `policy`, where `QueuePolicy::MinHeap` emits the ready node with the smallest
+    /// key (as returned by `f`) first. The key is ignored by the other policies. If a cycle is detected, an error
+    /// is yielded last.
+    ///
+    /// NOTE: A comparator set by `with_comparator` is not used
+    pub fn nodes_with_policy_by_key<'a, K, F>(
+        &'a self,
+        policy: QueuePolicy,
+        f: F,
+    ) -> impl Iterator<Item = Result<&'a T, CycleError>> + 'a
+    where
+        K: Ord,
+        F: FnMut(&'a T) -> K,
+    {
+        let graph = Graph::new(&self.node_depends);
+        let mut kahn = match policy {
+            QueuePolicy::Lifo => Kahn::new_lifo(graph),
+            QueuePolicy::Fifo => Kahn::new_fifo(graph),
+            QueuePolicy::MinHeap => Kahn::new_min_by_key(graph, f),
+        };
+        std::iter::from_fn(move || {
+            let result = kahn.next()?;
+            Some(result.map(|node| kahn.graph.nodes[node]))
+        })
+    }
+
+    /// Start the sort process in reverse and return an iterator of the results: every node is yielded before all
+    /// of its dependencies (dependents first), as needed for tear-down. The sort runs lazily over the reversed
+    /// edges, so nothing is collected up front. If a cycle is detected, an error is yielded last.
+    ///
+    /// NOTE: A comparator set by `with_comparator` is not used
+    pub fn rev_nodes(&self) -> impl Iterator<Item = Result<&T, CycleError>> + '_ {
+        let mut graph = Graph::new(&self.node_depends);
+        mem::swap(&mut graph.depends, &mut graph.dependents);
+        let mut edges = graph.edge_counts();
+        let mut ready = graph.no_edges();
+        let mut remaining = graph.len();
+
+        std::iter::from_fn(move || match ready.pop() {
+            Some(node) => {
+                remaining -= 1;
+                for &dependency in &graph.dependents[node] {
+                    edges[dependency] -= 1;
+                    if edges[dependency] == 0 {
+                        ready.push(dependency);
+                    }
+                }
+                Some(Ok(graph.nodes[node]))
+            }
+            None if remaining == 0 => None,
+            None => {
+                remaining = 0;
+                Some(Err(CycleError))
+            }
+        })
+    }
+
+    /// Start the sort process and return an iterator of the results and a set of its dependents, using
+    /// `comparator` to break ties instead of the one set by `with_comparator` (if any). Whenever more than one
+    /// node is ready, the node that compares as the least is emitted first (e.g. compare in reverse to emit the
+    /// most expensive ready node first).
+    ///
+    /// NOTE: Picking the next node is a linear scan over all ready nodes (see `with_comparator`)
+    #[inline]
+    pub fn iter_by<F>(&self, comparator: F) -> TopoSortIter<'_, T>
+    where
+        F: Fn(&T, &T) -> Ordering + Send + Sync + 'static,
+    {
+        TopoSortIter::new(&self.node_depends, Some(Arc::new(comparator)))
+    }
+
+    /// Start the sort process and return an iterator of the results, using `comparator` to break ties (see
+    /// `iter_by`)
+    #[inline]
+    pub fn nodes_by<F>(&self, comparator: F) -> TopoSortNodeIter<'_, T>
+    where
+        F: Fn(&T, &T) -> Ordering + Send + Sync + 'static,
+    {
+        TopoSortNodeIter::new(&self.node_depends, Some(Arc::new(comparator)))
+    }
+
+    /// Start the sort process and return an iterator of the effective `(dependent, dependency)` edges,
+    /// grouped by the position of the dependent in the sort. Self references and dependencies that
+    /// aren't nodes are skipped, just as they are during the sort.
+    pub fn edges_sorted(&self) -> impl Iterator<Item = Result<(&T, &T), CycleError>> + '_ {
+        self.iter().flat_map(move |result| {
+            let (entry, err) = match result {
+                Ok(entry) => (Some(entry), None),
+                Err(err) => (None, Some(Err(err))),
+            };
+
+            entry
+                .into_iter()
+                .flat_map(move |(node, depends)| {
+                    depends
+                        .iter()
+                        .filter(move |&dependency| {
+                            dependency != node && self.node_depends.contains_key(dependency)
+                        })
+                        .map(move |dependency| Ok((node, dependency)))
+                })
+                .chain(err)
+        })
+    }
+
+    /// Start the sort process and return a stepper which emits one node per step, allowing the
+    /// ready set to be inspected (and the next node chosen) between steps
+    #[inline]
+    pub fn stepper(&self) -> TopoStepper<'_, T> {
+        TopoStepper::new(&self.node_depends, self.comparator.clone())
+    }
+
+    /// Sort and then call `f` with each node and a mutable reference to its dependency set in sorted order. The
+    /// order is computed up front, so mutations made by `f` do not affect it. If a cycle is detected, an error is
+    /// returned instead and `f` is never called.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a maximum degree was set (see `with_max_degree`), before anything is changed, as the dependency
+    /// sets handed to `f` can't be checked against it
+    pub fn for_each_mut<F: FnMut(&T, &mut Set<T>)>(&mut self, mut f: F) -> Result<(), CycleError> {
+        self.assert_no_max_degree("for_each_mut");
+        let order: Vec<usize> = {
+            let positions: Map<_, _> = self
+                .node_depends
+                .keys()
+                .enumerate()
+                .map(|(pos, node)| (node, pos))
+                .collect();
+            self.nodes()
+                .map(|result| result.map(|node| positions[node]))
+                .collect::<Result<_, _>>()?
+        };
+
+        // NOTE: `iter_mut` yields in the same order as `keys` above since nothing was modified in between
+        let mut entries: Vec<_> = self.node_depends_mut().iter_mut().map(Some).collect();
+        for pos in order {
+            let (node, depends) = entries[pos].take().expect("node visited twice");
+            f(node, depends);
+        }
+        Ok(())
+    }
+
+    // # Cycles #
+
+    /// Apply the given edits to a temporary copy and sort it, returning an error if the result would contain a
+    /// cycle. This collection is not modified.
+    pub fn check_edits<I: IntoIterator<Item = Edit<T>>>(&self, edits: I) -> Result<(), CycleError>
+    where
+        T: Clone,
+    {
+        let mut topo_sort = self.clone();
+        for edit in edits {
+            match edit {
+                Edit::AddEdge(node, dependency) => {
+                    topo_sort
+                        .node_depends_mut()
+                        .entry(node)
+                        .or_default()
+                        .insert(dependency);
+                }
+                Edit::RemoveEdge(node, dependency) => {
+                    if let Some(depends) = topo_sort.node_depends_mut().get_mut(&node) {
+                        depends.remove(&dependency);
+                    }
+                }
+                Edit::AddNode(node) => {
+                    topo_sort.node_depends_mut().entry(node).or_default();
+                }
+                Edit::RemoveNode(node) => {
+                    topo_sort.node_depends_mut().remove(&node);
+                    for depends in topo_sort.node_depends.values_mut() {
+                        depends.remove(&node);
+                    }
+                }
+            }
+        }
+
+        topo_sort.try_vec_nodes().map(drop)
+    }
+
+    /// Sort and return true if a cycle was detected or false if it wasn't
+    pub fn cycle_detected(&self) -> bool {
+        self.iter().any(|result| result.is_err())
+    }
+
+    /// Returns true if the graph contains a cycle else false. This is cheaper than `cycle_detected` as the sort
+    /// works on node indices and the sorted order isn't kept.
+    #[inline]
+    pub fn is_cyclic(&self) -> bool {
+        !self.is_acyclic()
+    }
+
+    /// Returns true if the graph doesn't contain a cycle (and can therefore be sorted) else false
+    #[inline]
+    pub fn is_acyclic(&self) -> bool {
+        Graph::new(&self.node_depends).is_acyclic()
+    }
+
+    // # to Vec #
+
+    /// Sort and return a vector (with borrowed nodes/dependencies) of the results. If a cycle is detected,
+    /// partial results will be inside the `Partial` variant, otherwise full results will be in the
+    /// `Full` variant.
+    pub fn to_vec(&self) -> SortResults<(&T, &Set<T>)> {
+        SortResults::new(self.iter().flatten().collect(), self.node_depends.len())
+    }
+
+    /// Sort and return a vector (with owned/consumed nodes/dependencies) of the results. If a cycle is detected,
+    /// partial results will be inside the `Partial` variant, otherwise full results will be in the
+    /// `Full` variant.
+    pub fn into_vec(self) -> SortResults<(T, Set<T>)> {
+        let len = self.node_depends.len();
+        let nodes: Vec<_> = self.into_iter().flatten().collect();
+        SortResults::new(nodes, len)
+    }
+
+    /// Sort and return a vector (with owned/cloned nodes/dependencies) of the results. If a cycle is detected,
+    /// partial results will be inside the `Partial` variant, otherwise full results will be in the
+    /// `Full` variant.
+    pub fn to_owned_vec(&self) -> SortResults<(T, Set<T>)>
+    where
+        T: Clone,
+    {
+        SortResults::new(
+            self.iter()
+                .flatten()
+                .map(|(node, depends)| (node.clone(), depends.clone()))
+                .collect(),
+            self.node_depends.len(),
+        )
     }
 
     /// Sort and return a vector (with borrowed nodes) of the results. If a cycle is detected,
+    /// partial results will be inside the `Partial` variant, otherwise full results will be in the
+    /// `Full` variant.
+    pub fn to_vec_nodes(&self) -> SortResults<&T> {
+        SortResults::new(self.nodes().flatten().collect(), self.node_depends.len())
+    }
+
+    /// Sort and return a vector (with owned/consumed nodes) of the results. If a cycle is detected,
+    /// partial results will be inside the `Partial` variant, otherwise full results will be in the
+    /// `Full` variant.
+    pub fn into_vec_nodes(self) -> SortResults<T> {
+        let len = self.node_depends.len();
+        let nodes: Vec<_> = self.into_nodes().flatten().collect();
+        SortResults::new(nodes, len)
+    }
+
+    /// Sort and return a vector (with owned/cloned nodes) of the results. If a cycle is detected,
+    /// partial results will be inside the `Partial` variant, otherwise full results will be in the
+    /// `Full` variant.
+    pub fn to_owned_vec_nodes(&self) -> SortResults<T>
+    where
+        T: Clone,
+    {
+        SortResults::new(
+            self.nodes().flatten().cloned().collect(),
+            self.node_depends.len(),
+        )
+    }
+
+    // # try Vec #
+
+    /// Sort and return a vector (with borrowed nodes/dependencies) of the results. If a cycle is detected,
+    /// an error is returned instead (see `try_vec_detailed` to find out which nodes). The order is cached until
+    /// the next mutation, so repeated calls don't sort again.
+    pub fn try_vec(&self) -> Result<Vec<(&T, &Set<T>)>, CycleError> {
+        let order = self.cached_order()?;
+        let entries: Vec<_> = self.node_depends.iter().collect();
+        Ok(order.iter().map(|&idx| entries[idx as usize]).collect())
+    }
+
+    /// Sort and return a vector (with borrowed nodes/dependencies) of the results. Unlike `try_vec`, if a cycle
+    /// is detected the error includes the nodes that could not be sorted and the path of one cycle among them.
+    pub fn try_vec_detailed(&self) -> Result<Vec<(&T, &Set<T>)>, DetailedCycleError<&T>> {
+        self.try_vec().map_err(|_| self.detailed_cycle_error())
+    }
+
+    /// Sort and return a vector (with owned/cloned nodes/dependencies) of the results. Unlike `try_owned_vec`,
+    /// if a cycle is detected the error includes the nodes that could not be sorted and the path of one cycle
+    /// among them.
+    pub fn try_owned_vec_detailed(&self) -> Result<Vec<(T, Set<T>)>, DetailedCycleError<T>>
+    where
+        T: Clone,
+    {
+        match self.try_vec() {
+            Ok(entries) => Ok(entries
+                .into_iter()
+                .map(|(node, depends)| (node.clone(), depends.clone()))
+                .collect()),
+            Err(_) => {
+                let err = self.detailed_cycle_error();
+                Err(DetailedCycleError {
+                    stuck: err.stuck.into_iter().cloned().collect(),
+                    cycle: err.cycle.into_iter().cloned().collect(),
+                })
+            }
+        }
+    }
+
+    // Sort until stuck and build the detailed error. Only call this once a cycle is known to exist
+    fn detailed_cycle_error(&self) -> DetailedCycleError<&T> {
+        match self.nodes_detailed().find_map(Result::err) {
+            Some(err) => err,
+            None => unreachable!("no cycle found in a graph that failed to sort"),
+        }
+    }
+
+    /// Sort and return a vector (with owned/consumed nodes/dependencies) of the results. If a cycle is detected,
     /// an error is returned instead
     #[inline]
+    pub fn try_into_vec(self) -> Result<Vec<(T, Set<T>)>, CycleError> {
+        self.into_iter().collect()
+    }
+
+    /// Sort and return a vector (with owned/cloned nodes/dependencies) of the results. If a cycle is detected,
+    /// an error is returned instead (`try_owned_vec_detailed` also reports the nodes involved)
+    pub fn try_owned_vec(&self) -> Result<Vec<(T, Set<T>)>, CycleError>
+    where
+        T: Clone,
+    {
+        // Collecting into a `Result` loses the size hint, so reserve up front to avoid reallocation
+        let mut nodes = Vec::with_capacity(self.node_depends.len());
+        for result in self.iter() {
+            let (node, depends) = result?;
+            nodes.push((node.clone(), depends.clone()));
+        }
+        Ok(nodes)
+    }
+
+    /// Sort and return a vector (with borrowed nodes) of the results. If a cycle is detected,
+    /// an error is returned instead. Like `try_vec`, the order is cached until the next mutation.
+    ///
+    /// NOTE: `CycleError` doesn't say which nodes are involved. Use `find_cycle` for the path of a cycle.
     pub fn try_vec_nodes(&self) -> Result<Vec<&T>, CycleError> {
-        self.nodes().collect()
+        let order = self.cached_order()?;
+        let keys: Vec<_> = self.node_depends.keys().collect();
+        Ok(order.iter().map(|&idx| keys[idx as usize]).collect())
+    }
+
+    /// Sort and return a vector (with borrowed nodes) of the results. This is a convenience for prototyping and
+    /// tests where handling a `Result` isn't worth it.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a cycle is detected, listing the nodes that could not be sorted
+    pub fn sorted_or_panic(&self) -> Vec<&T>
+    where
+        T: fmt::Debug,
+    {
+        match self.nodes_detailed().collect() {
+            Ok(nodes) => nodes,
+            Err(err) => panic!("{}: {:?}", err, err.stuck()),
+        }
     }
 
     /// Sort and return a vector (with owned/consumed nodes) of the results. If a cycle is detected,
@@ -358,15 +1479,54 @@ where
         self.into_nodes().collect()
     }
 
+    /// Sort and return a vector of the nodes, moved out of this struct (never cloned), discarding their
+    /// dependency sets. This is the consuming counterpart of `try_owned_vec_nodes` and is equivalent to
+    /// `try_into_vec_nodes`. If a cycle is detected, an error is returned instead
+    #[inline]
+    pub fn into_sorted_vec(self) -> Result<Vec<T>, CycleError> {
+        self.try_into_vec_nodes()
+    }
+
     /// Sort and return a vector (with owned/cloned nodes) of the results. If a cycle is detected,
     /// an error is returned instead
     pub fn try_owned_vec_nodes(&self) -> Result<Vec<T>, CycleError>
     where
         T: Clone,
     {
-        self.nodes()
-            .map(|result| result.map(|node| node.clone()))
-            .collect()
+        let mut nodes = Vec::with_capacity(self.node_depends.len());
+        for result in self.nodes() {
+            nodes.push(result?.clone());
+        }
+        Ok(nodes)
+    }
+
+    // # Channels #
+
+    /// Sort and send each node (borrowed) to the channel as soon as it is emitted, so a consumer can start work
+    /// immediately. If a cycle is detected, an error is returned and none of the nodes that could not be sorted are
+    /// sent. If the receiver hangs up, sorting stops early.
+    pub fn sort_to_channel<'d>(&'d self, tx: Sender<&'d T>) -> Result<(), CycleError> {
+        for node in self.nodes() {
+            if tx.send(node?).is_err() {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    /// Sort and send each node (owned/cloned) to the channel as soon as it is emitted, so a consumer can start
+    /// work immediately. If a cycle is detected, an error is returned and none of the nodes that could not be
+    /// sorted are sent. If the receiver hangs up, sorting stops early.
+    pub fn sort_owned_to_channel(&self, tx: Sender<T>) -> Result<(), CycleError>
+    where
+        T: Clone,
+    {
+        for node in self.nodes() {
+            if tx.send(node?.clone()).is_err() {
+                break;
+            }
+        }
+        Ok(())
     }
 
     // # Misc #
@@ -388,11 +1548,108 @@ where
         self.node_depends.len()
     }
 
+    /// Returns the number of nodes the collection can hold without reallocating
+    #[inline]
+    pub fn capacity(&self) -> usize {
+        self.node_depends.capacity()
+    }
+
+    /// Shrink the capacity of the collection (but not the dependency sets) as much as possible
+    #[inline]
+    pub fn shrink_to_fit(&mut self) {
+        self.node_depends_mut().shrink_to_fit();
+    }
+
+    /// Shrink the capacity of the collection and every dependency set as much as possible. This is useful to
+    /// reclaim memory in long lived collections after heavy edge churn.
+    pub fn shrink_dependency_sets(&mut self) {
+        for depends in self.node_depends.values_mut() {
+            depends.shrink_to_fit();
+        }
+        self.shrink_to_fit();
+    }
+
     /// Returns the dependency set of a node (as inserted), if found, else None
     #[inline]
     pub fn get(&self, node: &T) -> Option<&Set<T>> {
         self.node_depends.get(node)
     }
+
+    /// Validate the consistency of the adjacency the iterators sort on: the offsets must be well formed and every
+    /// effective dependency (a node other than its dependent) must appear exactly once as a dependent edge, with
+    /// the in degree of each node matching. This is intended for property tests and `debug_assert` style checks, as
+    /// it visits every edge, and is only available in debug builds.
+    ///
+    /// # Panics
+    ///
+    /// Panics with a description of the first violation found
+    #[cfg(any(test, debug_assertions))]
+    pub fn debug_check_invariants(&self) {
+        let entries: Vec<_> = self.node_depends.iter().collect();
+        Csr::new(&entries).check_invariants(&entries);
+    }
+
+    /// Returns a diagnostic view of a node's dependencies (split into present and missing), if found, else None
+    pub fn inspect(&self, node: &T) -> Option<NodeInspection<'_, T>> {
+        let (node, depends) = self.node_depends.get_key_value(node)?;
+        let mut inspection = NodeInspection {
+            present_deps: Set::new(),
+            missing_deps: Set::new(),
+            self_loop: false,
+        };
+
+        for dependency in depends {
+            if dependency == node {
+                inspection.self_loop = true;
+            } else if let Some((dependency, _)) = self.node_depends.get_key_value(dependency) {
+                inspection.present_deps.insert(dependency);
+            } else {
+                inspection.missing_deps.insert(dependency);
+            }
+        }
+
+        Some(inspection)
+    }
+
+    /// Returns the direct dependencies of a node that the sort actually honors (skipping self references and
+    /// dependencies that aren't nodes), if found, else None
+    #[inline]
+    pub fn effective_dependencies(&self, node: &T) -> Option<Set<&T>> {
+        let (node, _) = self.node_depends.get_key_value(node)?;
+        Some(self.effective_depends(node).collect())
+    }
+}
+
+impl<T> Clone for TopoSort<T>
+where
+    T: Clone + Eq + Hash,
+{
+    #[inline]
+    fn clone(&self) -> Self {
+        TopoSort {
+            node_depends: self.node_depends.clone(),
+            comparator: self.comparator.clone(),
+            max_degree: self.max_degree,
+            priorities: self.priorities.clone(),
+            cache: OnceLock::new(),
+            edge_order: self.edge_order.clone(),
+        }
+    }
+
+    /// Overwrite this struct with a copy of `source`, reusing the allocation of the node map
+    fn clone_from(&mut self, source: &Self) {
+        self.node_depends_mut().clear();
+        self.node_depends.extend(
+            source
+                .node_depends
+                .iter()
+                .map(|(node, depends)| (node.clone(), depends.clone())),
+        );
+        self.comparator.clone_from(&source.comparator);
+        self.max_degree = source.max_degree;
+        self.priorities.clone_from(&source.priorities);
+        self.edge_order.clone_from(&source.edge_order);
+    }
 }
 
 impl<T> Index<&T> for TopoSort<T>
@@ -407,16 +1664,59 @@ where
     }
 }
 
-impl<T> IntoIterator for TopoSort<T>
+/// Insert every node and its dependencies (see `insert` - dependencies of nodes already inserted are overwritten)
+///
+/// # Panics
+///
+/// Panics if a maximum degree was set (see `TopoSort::with_max_degree`) and a node's dependencies exceed it. All
+/// nodes are checked first, so nothing is inserted in that case.
+impl<T, I> Extend<(T, I)> for TopoSort<T>
 where
     T: Eq + Hash,
+    I: IntoIterator<Item = T>,
 {
-    type Item = Result<(T, Set<T>), CycleError>;
-    type IntoIter = IntoTopoSortIter<T>;
+    fn extend<It: IntoIterator<Item = (T, I)>>(&mut self, iter: It) {
+        let entries: Vec<(T, Set<T>)> = iter
+            .into_iter()
+            .map(|(node, depends)| (node, depends.into_iter().collect()))
+            .collect();
+        for (_, depends) in &entries {
+            if let Err(err) = self.check_degree(depends.len()) {
+                panic!("{}", err);
+            }
+        }
+        self.node_depends_mut().reserve(entries.len());
+        for (node, depends) in entries {
+            self.replace_depends(node, depends);
+        }
+    }
+}
+
+/// Collect nodes and their dependencies into a new struct (see `insert` - later dependencies of the same node
+/// overwrite earlier ones)
+impl<T, I> FromIterator<(T, I)> for TopoSort<T>
+where
+    T: Eq + Hash,
+    I: IntoIterator<Item = T>,
+{
+    #[inline]
+    fn from_iter<It: IntoIterator<Item = (T, I)>>(iter: It) -> Self {
+        let mut topo_sort = TopoSort::new();
+        topo_sort.extend(iter);
+        topo_sort
+    }
+}
+
+impl<T> IntoIterator for TopoSort<T>
+where
+    T: Eq + Hash,
+{
+    type Item = Result<(T, Set<T>), CycleError>;
+    type IntoIter = IntoTopoSortIter<T>;
 
     #[inline]
     fn into_iter(self) -> Self::IntoIter {
-        IntoTopoSortIter::new(self.node_depends)
+        IntoTopoSortIter::new(self.node_depends, self.comparator)
     }
 }
 
@@ -433,116 +1733,212 @@ where
     }
 }
 
-// *** InnerIter ***
+// *** Functions ***
+
+/// Sort a list of `(dependent, dependency)` edges and return the owned nodes in sorted order. Every node that
+/// appears on either side of an edge is included in the results. If a cycle is detected, an error is returned instead.
+pub fn sort_edges<T, I>(edges: I) -> Result<Vec<T>, CycleError>
+where
+    T: Clone + Eq + Hash,
+    I: IntoIterator<Item = (T, T)>,
+{
+    TopoSort::from_edges(edges).try_into_vec_nodes()
+}
+
+/// Sort a vector of arbitrary items by their dependencies and return them in sorted order. `key` returns the
+/// key of an item and `deps` returns the keys of the items it depends on. Dependencies on keys that don't belong to
+/// any item are ignored, and if more than one item has the same key, dependencies on that key refer to the last
+/// of them. If a cycle is detected, an error is returned instead.
+pub fn sort_by_key<Item, K, FK, FD, I>(
+    items: Vec<Item>,
+    key: FK,
+    deps: FD,
+) -> Result<Vec<Item>, CycleError>
+where
+    K: Eq + Hash,
+    FK: Fn(&Item) -> K,
+    FD: Fn(&Item) -> I,
+    I: IntoIterator<Item = K>,
+{
+    let keys: Map<K, usize> = items
+        .iter()
+        .enumerate()
+        .map(|(idx, item)| (key(item), idx))
+        .collect();
+
+    let mut topo_sort = TopoSort::with_capacity(items.len());
+    for (idx, item) in items.iter().enumerate() {
+        let depends = deps(item)
+            .into_iter()
+            .filter_map(|dep| keys.get(&dep).copied());
+        topo_sort.insert(idx, depends);
+    }
+    let order = topo_sort.try_into_vec_nodes()?;
+
+    let mut items: Vec<_> = items.into_iter().map(Some).collect();
+    Ok(order
+        .into_iter()
+        .map(|idx| items[idx].take().expect("item taken twice"))
+        .collect())
+}
 
-// Dependency -> (Dependents, Edge Count)
-type Nodes<T> = Map<*const T, (Set<*const T>, u32)>;
+// *** InnerIter ***
 
+// Every node is assigned a dense index up front (its position in `node_depends` iteration order) and the sort
+// operates on indices only, so no references into `node_depends` need to be held
 struct InnerIter<T> {
-    nodes: Nodes<T>,
-    no_edges: Vec<*const T>,
+    // Dependency -> Dependents
+    csr: Arc<Csr>,
+    // Remaining (unemitted) dependencies of each node
+    edges: Vec<u32>,
+    no_edges: NoEdges,
+    emitted: Vec<bool>,
+    remaining: usize,
+    comparator: Option<Comparator<T>>,
+}
+
+// Nodes that are ready to be emitted
+enum NoEdges {
+    // Popped last in first out, or scanned with the comparator
+    Stack(Vec<u32>),
+    // Deterministic: the node inserted first (which has the lowest index) wins
+    #[cfg(any(feature = "indexmap", feature = "indexmap-serde"))]
+    Heap(BinaryHeap<Reverse<u32>>),
+}
+
+impl NoEdges {
+    #[inline]
+    fn push(&mut self, node: u32) {
+        match self {
+            NoEdges::Stack(no_edges) => no_edges.push(node),
+            #[cfg(any(feature = "indexmap", feature = "indexmap-serde"))]
+            NoEdges::Heap(no_edges) => no_edges.push(Reverse(node)),
+        }
+    }
 }
 
 impl<T> InnerIter<T>
 where
     T: Eq + Hash,
 {
-    fn new(node_depends: &Map<T, Set<T>>) -> Self {
-        let nodes = Self::make_nodes(node_depends);
-        let no_edges = Self::make_no_edges(&nodes);
-        InnerIter { nodes, no_edges }
-    }
-
-    fn make_nodes(node_depends: &Map<T, Set<T>>) -> Nodes<T> {
-        let mut nodes: Nodes<T> = Map::with_capacity(node_depends.len());
-        // Assume no dependents for now (TODO: How to pick a good # here to minimize reallocation but doesn't go crazy?)
-        let new_entry_fn = || (Set::new(), 0);
-
-        // We need to ensure that every `*const T` is based off `&T` from the key in `node_depends`
-        // NOTE: This looks odd but remember that `Eq` and `Hash` are off the value of `T`, not it's address
-        // so we need to lookup the address even though it looks like an identity op... it isn't
-        let lookup: Map<_, _> = node_depends.keys().map(|key| (key, key)).collect();
-
-        for (dependent, dependencies) in node_depends {
-            // Don't overwrite if we have it already (from a dependency below), but otherwise ensure every node is added
-            nodes.entry(dependent).or_insert_with(new_entry_fn);
-
-            for dependency in dependencies {
-                // Filter any self references
-                if dependent != dependency {
-                    // We need to swap to the `&T` based on `dependent` before going further
-                    // `dependency` must be in `node_depends` to qualify for continued processing
-                    if let Some(&dependency) = lookup.get(dependency) {
-                        // Each dependent tracks the # of dependencies
-                        // Safe: It was just added above
-                        let dependent_entry = nodes
-                            .get_mut(&(dependent as *const T))
-                            .expect("dependent not found in `nodes`");
-                        dependent_entry.1 += 1;
-
-                        // Each dependency tracks all it's dependents
-                        let dependency_entry = nodes.entry(dependency).or_insert_with(new_entry_fn);
-                        dependency_entry.0.insert(dependent);
-                    }
-                }
-            }
-        }
+    #[inline]
+    fn new(entries: &[(&T, &Set<T>)], comparator: Option<Comparator<T>>) -> Self {
+        Self::from_csr(Arc::new(Csr::new(entries)), comparator)
+    }
 
-        nodes
+    #[inline]
+    fn from_csr(csr: Arc<Csr>, comparator: Option<Comparator<T>>) -> Self {
+        Self::from_csr_in(csr, comparator, &mut SortWorkspace::new())
     }
 
-    fn make_no_edges(nodes: &Nodes<T>) -> Vec<*const T> {
-        // Find first batch of ready nodes (TODO: move into loop so we can set capacity? What capacity to set?)
-        nodes
-            .iter()
-            .filter(|(_, (_, edges))| *edges == 0)
-            .map(|(&node, _)| node)
-            .collect()
+    // Builds the sort state in the buffers of `workspace` (see `recycle`)
+    fn from_csr_in(
+        csr: Arc<Csr>,
+        comparator: Option<Comparator<T>>,
+        workspace: &mut SortWorkspace,
+    ) -> Self {
+        let len = csr.in_degree.len();
+        let mut edges = mem::take(&mut workspace.edges);
+        edges.clone_from(&csr.in_degree);
+        let mut no_edges = mem::take(&mut workspace.no_edges);
+        no_edges.clear();
+        no_edges.extend((0..len as u32).filter(|&idx| edges[idx as usize] == 0));
+        let no_edges = match comparator {
+            #[cfg(any(feature = "indexmap", feature = "indexmap-serde"))]
+            None => NoEdges::Heap(no_edges.into_iter().map(Reverse).collect()),
+            _ => NoEdges::Stack(no_edges),
+        };
+
+        InnerIter {
+            csr,
+            edges,
+            no_edges,
+            emitted: reuse(&mut workspace.emitted, len),
+            remaining: len,
+            comparator,
+        }
     }
 
-    fn next(&mut self) -> Option<Result<*const T, CycleError>> {
-        match self.no_edges.pop() {
-            Some(node) => {
-                // NOTE: Unwrap() should be safe - we know it was in there since it came from there
-                // We are done with this node - remove entirely
-                let (dependents, _) = &self
-                    .nodes
-                    .remove(&node)
-                    .expect("node not in `nodes` on remove");
+    fn pop_no_edges<'a>(&mut self, node: impl Fn(u32) -> &'a T) -> Option<u32>
+    where
+        T: 'a,
+    {
+        match (&mut self.no_edges, &self.comparator) {
+            (NoEdges::Stack(no_edges), Some(comparator)) => {
+                let (idx, _) = no_edges
+                    .iter()
+                    .enumerate()
+                    .min_by(|(_, &a), (_, &b)| comparator(node(a), node(b)))?;
+                Some(no_edges.swap_remove(idx))
+            }
+            (NoEdges::Stack(no_edges), None) => no_edges.pop(),
+            #[cfg(any(feature = "indexmap", feature = "indexmap-serde"))]
+            (NoEdges::Heap(no_edges), _) => no_edges.pop().map(|Reverse(node)| node),
+        }
+    }
+
+    // `node` maps an index back to its node (only called for nodes that are ready and not yet emitted)
+    fn next<'a>(&mut self, node: impl Fn(u32) -> &'a T) -> Option<Result<usize, CycleError>>
+    where
+        T: 'a,
+    {
+        match self.pop_no_edges(node) {
+            Some(idx) => {
+                let idx = idx as usize;
+                self.emitted[idx] = true;
+                self.remaining -= 1;
 
                 // Decrement the edge count of all nodes that depend on this one and add them
                 // to no_edges when they hit zero
-                for &dependent in dependents {
-                    // NOTE: Unwrap() should be safe - we know it was in there from init
-                    let (_, edges) = self
-                        .nodes
-                        .get_mut(&dependent)
-                        .expect("dependent not found in `nodes`");
+                for &dependent in self.csr.dependents(idx) {
+                    let edges = &mut self.edges[dependent as usize];
                     *edges -= 1;
                     if *edges == 0 {
                         self.no_edges.push(dependent);
                     }
                 }
 
-                Some(Ok(node))
+                Some(Ok(idx))
             }
-            None if self.nodes.is_empty() => None,
+            None if self.remaining == 0 => None,
             None => {
-                self.nodes.clear();
+                // Nothing further can be emitted - whatever wasn't emitted is stuck
+                self.remaining = 0;
                 Some(Err(CycleError))
             }
         }
     }
 
+    // Indices of the nodes that have not been emitted yet
+    fn unemitted(&self) -> impl Iterator<Item = usize> + '_ {
+        self.emitted
+            .iter()
+            .enumerate()
+            .filter(|(_, &emitted)| !emitted)
+            .map(|(idx, _)| idx)
+    }
+
     #[inline]
     fn size_hint(&self) -> (usize, Option<usize>) {
-        let len = self.nodes.len();
-        (len, Some(len))
+        (self.remaining, Some(self.remaining))
     }
 }
 
-// This should be safe
-unsafe impl<T> Send for InnerIter<T> {}
+impl<T> InnerIter<T> {
+    // Hands the buffers back to `workspace` so the next sort can reuse them
+    fn recycle(&mut self, workspace: &mut SortWorkspace) {
+        workspace.edges = mem::take(&mut self.edges);
+        match mem::replace(&mut self.no_edges, NoEdges::Stack(Vec::new())) {
+            NoEdges::Stack(no_edges) => workspace.no_edges = no_edges,
+            #[cfg(any(feature = "indexmap", feature = "indexmap-serde"))]
+            NoEdges::Heap(_) => {}
+        }
+        workspace.emitted = mem::take(&mut self.emitted);
+        if let Some(csr) = Arc::get_mut(&mut self.csr) {
+            csr.recycle(workspace);
+        }
+    }
+}
 
 // *** IntoTopoSortIter ***
 
@@ -550,8 +1946,8 @@ unsafe impl<T> Send for InnerIter<T> {}
 pub struct IntoTopoSortIter<T> {
     inner: InnerIter<T>,
 
-    // Dependent -> Dependencies
-    node_depends: Map<T, Set<T>>,
+    // Index -> (Dependent, Dependencies) - taken as each node is emitted
+    entries: Vec<Option<(T, Set<T>)>>,
 }
 
 impl<T> IntoTopoSortIter<T>
@@ -559,10 +1955,17 @@ where
     T: Eq + Hash,
 {
     #[inline]
-    fn new(node_depends: Map<T, Set<T>>) -> Self {
+    fn new(node_depends: Map<T, Set<T>>, comparator: Option<Comparator<T>>) -> Self {
+        // Indices are assigned from this one list, so they can't disagree with the order of `entries`
+        let entries: Vec<_> = node_depends.into_iter().collect();
+        let refs: Vec<_> = entries
+            .iter()
+            .map(|(node, depends)| (node, depends))
+            .collect();
+        let inner = InnerIter::new(&refs, comparator);
         IntoTopoSortIter {
-            inner: InnerIter::new(&node_depends),
-            node_depends,
+            inner,
+            entries: entries.into_iter().map(Some).collect(),
         }
     }
 }
@@ -574,15 +1977,91 @@ where
     type Item = Result<(T, Set<T>), CycleError>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        self.inner.next().map(|result| {
-            result.map(|node| unsafe {
-                // NOTE: This depends on the HashMap NOT shrinking on remove - if this ever changes this
-                // will likely break as the addresses of the keys will change
-                self.node_depends
-                    .remove_entry(&*node)
-                    .expect("node not in `node_depends` on remove")
-            })
-        })
+        let entries = &self.entries;
+        let result = self.inner.next(|idx| {
+            &entries[idx as usize]
+                .as_ref()
+                .expect("ready node was already emitted")
+                .0
+        })?;
+        Some(result.map(|idx| self.entries[idx].take().expect("node was already emitted")))
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+// *** IntoTopoSortNodeIter ***
+
+/// Consuming/owning Iterator over the final node only of the topological sort
+pub struct IntoTopoSortNodeIter<T>(IntoTopoSortIter<T>);
+
+impl<T> IntoTopoSortNodeIter<T>
+where
+    T: Eq + Hash,
+{
+    #[inline]
+    fn new(node_depends: Map<T, Set<T>>, comparator: Option<Comparator<T>>) -> Self {
+        IntoTopoSortNodeIter(IntoTopoSortIter::new(node_depends, comparator))
+    }
+}
+
+impl<T> Iterator for IntoTopoSortNodeIter<T>
+where
+    T: Eq + Hash,
+{
+    type Item = Result<T, CycleError>;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next().map(|result| result.map(|(node, _)| node))
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+}
+
+// *** TopoSortIter ***
+
+/// Iterator over the final node and dependent set of the topological sort
+pub struct TopoSortIter<'d, T> {
+    inner: InnerIter<T>,
+
+    // Index -> (Dependent, Dependencies)
+    entries: Vec<(&'d T, &'d Set<T>)>,
+    // Dependent -> Dependencies
+    node_depends: &'d Map<T, Set<T>>,
+}
+
+impl<'d, T> TopoSortIter<'d, T>
+where
+    T: Eq + Hash,
+{
+    #[inline]
+    fn new(node_depends: &'d Map<T, Set<T>>, comparator: Option<Comparator<T>>) -> Self {
+        let entries: Vec<_> = node_depends.iter().collect();
+        TopoSortIter {
+            inner: InnerIter::new(&entries, comparator),
+            entries,
+            node_depends,
+        }
+    }
+}
+
+impl<'d, T> Iterator for TopoSortIter<'d, T>
+where
+    T: Eq + Hash,
+{
+    type Item = Result<(&'d T, &'d Set<T>), CycleError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let entries = &self.entries;
+        let result = self.inner.next(|idx| entries[idx as usize].0)?;
+        Some(result.map(|idx| entries[idx]))
     }
 
     #[inline]
@@ -591,210 +2070,1624 @@ where
     }
 }
 
-// *** IntoTopoSortNodeIter ***
+impl<'d, T> TopoSortIter<'d, T>
+where
+    T: Eq + Hash,
+{
+    /// Returns the nodes that have not been yielded yet. Once a `CycleError` has been returned, these are the
+    /// nodes that could not be sorted because they are either part of a cycle or depend on one.
+    pub fn remaining(&self) -> Set<&'d T> {
+        self.inner
+            .unemitted()
+            .map(|idx| self.entries[idx].0)
+            .collect()
+    }
+}
+
+// *** TopoSortNodeIter ***
+
+/// Iterator over the final node only of the topological sort
+pub struct TopoSortNodeIter<'d, T>(TopoSortIter<'d, T>);
+
+impl<'d, T> TopoSortNodeIter<'d, T>
+where
+    T: Eq + Hash,
+{
+    #[inline]
+    fn new(node_depends: &'d Map<T, Set<T>>, comparator: Option<Comparator<T>>) -> Self {
+        TopoSortNodeIter(TopoSortIter::new(node_depends, comparator))
+    }
+}
+
+impl<'d, T> TopoSortNodeIter<'d, T>
+where
+    T: Eq + Hash,
+{
+    /// Returns the nodes that have not been yielded yet (see `TopoSortIter::remaining`)
+    #[inline]
+    pub fn remaining(&self) -> Set<&'d T> {
+        self.0.remaining()
+    }
+
+    /// Only yield the nodes for which `f` returns true, while still preserving their dependency order.
+    /// Nodes that are filtered out are still sorted, so their dependents are yielded as normal (if they match).
+    /// A `CycleError` is always yielded.
+    #[inline]
+    pub fn filter_nodes<F: Fn(&T) -> bool>(self, f: F) -> FilterNodes<'d, T, F> {
+        FilterNodes { iter: self, f }
+    }
+}
+
+impl<'d, T> Iterator for TopoSortNodeIter<'d, T>
+where
+    T: Eq + Hash,
+{
+    type Item = Result<&'d T, CycleError>;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next().map(|result| result.map(|(node, _)| node))
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+}
+
+// *** TopoSortDetailedNodeIter ***
+
+/// Iterator over the final node only of the topological sort, which includes the nodes that could not be
+/// sorted in the error when a cycle is detected
+pub struct TopoSortDetailedNodeIter<'d, T>(TopoSortIter<'d, T>);
+
+impl<'d, T> Iterator for TopoSortDetailedNodeIter<'d, T>
+where
+    T: Eq + Hash,
+{
+    type Item = Result<&'d T, DetailedCycleError<&'d T>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next().map(|result| match result {
+            Ok((node, _)) => Ok(node),
+            Err(CycleError) => {
+                let stuck = self.0.remaining();
+                let graph = Graph::new(self.0.node_depends);
+                let sorted: Vec<_> = graph
+                    .nodes
+                    .iter()
+                    .map(|node| !stuck.contains(node))
+                    .collect();
+                let cycle = graph.to_nodes(graph.cycle_within(&sorted));
+                Err(DetailedCycleError { stuck, cycle })
+            }
+        })
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+}
+
+// *** TopoSortLenientIter ***
+
+/// Iterator over the final node only of the topological sort that ends (instead of returning an error) when a
+/// cycle is detected, keeping the nodes that could not be sorted
+pub struct TopoSortLenientIter<'d, T> {
+    iter: TopoSortIter<'d, T>,
+    stuck: Set<&'d T>,
+}
+
+impl<'d, T> TopoSortLenientIter<'d, T>
+where
+    T: Eq + Hash,
+{
+    /// Returns the nodes that could not be sorted because they are either part of a cycle or depend on one. This
+    /// is only complete once the iterator has been exhausted (and is empty if there was no cycle).
+    #[inline]
+    pub fn stuck(&self) -> &Set<&'d T> {
+        &self.stuck
+    }
+}
+
+impl<'d, T> Iterator for TopoSortLenientIter<'d, T>
+where
+    T: Eq + Hash,
+{
+    type Item = &'d T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.iter.next()? {
+            Ok((node, _)) => Some(node),
+            Err(CycleError) => {
+                self.stuck = self.iter.remaining();
+                None
+            }
+        }
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, self.iter.size_hint().1)
+    }
+}
+
+// *** FilterNodes ***
+
+/// Iterator over the final node only of the topological sort, skipping nodes that don't match a predicate
+pub struct FilterNodes<'d, T, F> {
+    iter: TopoSortNodeIter<'d, T>,
+    f: F,
+}
+
+impl<'d, T, F> Iterator for FilterNodes<'d, T, F>
+where
+    T: Eq + Hash,
+    F: Fn(&T) -> bool,
+{
+    type Item = Result<&'d T, CycleError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let f = &self.f;
+        self.iter.find(|result| match result {
+            Ok(node) => f(node),
+            Err(_) => true,
+        })
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, self.iter.size_hint().1)
+    }
+}
+
+// *** TopoStepper ***
+
+/// Manually driven sort that emits a single node per step. Unlike the iterators, the set of nodes
+/// that are ready to be emitted can be inspected between steps and the caller can choose which
+/// one is emitted next.
+pub struct TopoStepper<'d, T> {
+    graph: Graph<'d, T>,
+    // Remaining (unemitted) dependencies of each node
+    edges: Vec<usize>,
+    ready: Vec<&'d T>,
+    ready_idx: Vec<usize>,
+    remaining: usize,
+    comparator: Option<Comparator<T>>,
+}
+
+impl<'d, T> TopoStepper<'d, T>
+where
+    T: Eq + Hash,
+{
+    fn new(node_depends: &'d Map<T, Set<T>>, comparator: Option<Comparator<T>>) -> Self {
+        let graph = Graph::new(node_depends);
+        let edges = graph.edge_counts();
+        let ready_idx = graph.no_edges();
+        let ready = ready_idx.iter().map(|&idx| graph.nodes[idx]).collect();
+        let remaining = graph.len();
+
+        TopoStepper {
+            graph,
+            edges,
+            ready,
+            ready_idx,
+            remaining,
+            comparator,
+        }
+    }
+
+    /// Returns the nodes whose dependencies have all been emitted and are ready to be emitted next
+    #[inline]
+    pub fn ready(&self) -> &[&'d T] {
+        &self.ready
+    }
+
+    /// Returns the number of nodes that have not yet been emitted
+    #[inline]
+    pub fn remaining(&self) -> usize {
+        self.remaining
+    }
+
+    /// Emit the next node using the same selection as iteration. Returns `None` when all nodes have
+    /// been emitted or after a cycle was reported.
+    #[inline]
+    pub fn step(&mut self) -> Option<Result<&'d T, CycleError>> {
+        let index = match (&self.comparator, self.ready.len()) {
+            (_, 0) => 0,
+            (Some(comparator), _) => {
+                let (index, _) = self
+                    .ready
+                    .iter()
+                    .enumerate()
+                    .min_by(|(_, a), (_, b)| comparator(a, b))?;
+                index
+            }
+            // Like `InnerIter`, ties go to the first inserted node with `indexmap`, else the last one to become ready
+            #[cfg(any(feature = "indexmap", feature = "indexmap-serde"))]
+            (None, _) => {
+                let (index, _) = self
+                    .ready_idx
+                    .iter()
+                    .enumerate()
+                    .min_by_key(|&(_, &idx)| idx)?;
+                index
+            }
+            #[cfg(not(any(feature = "indexmap", feature = "indexmap-serde")))]
+            (None, len) => len - 1,
+        };
+        self.step_at(index)
+    }
+
+    /// Emit the node at position `index` of the ready set (see `ready`). If the ready set is empty,
+    /// this behaves like `step`, returning either `None` or a `CycleError`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the ready set is not empty and `index` is out of bounds
+    pub fn step_at(&mut self, index: usize) -> Option<Result<&'d T, CycleError>> {
+        if self.ready.is_empty() {
+            return match self.remaining {
+                0 => None,
+                _ => {
+                    self.remaining = 0;
+                    Some(Err(CycleError))
+                }
+            };
+        }
+
+        let node = self.ready.swap_remove(index);
+        let idx = self.ready_idx.swap_remove(index);
+        self.remaining -= 1;
+
+        // Decrement the edge count of all nodes that depend on this one and add them
+        // to the ready set when they hit zero
+        for &dependent in &self.graph.dependents[idx] {
+            self.edges[dependent] -= 1;
+            if self.edges[dependent] == 0 {
+                self.ready.push(self.graph.nodes[dependent]);
+                self.ready_idx.push(dependent);
+            }
+        }
+
+        Some(Ok(node))
+    }
+}
+
+// *** Tests ***
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+    use std::sync::mpsc;
+    use std::thread;
+
+    use rand_core::{Rng, SeedableRng};
+    use rand_xorshift::XorShiftRng;
+
+    use crate::{
+        sort_by_key, sort_edges, AddDependencyError, CycleError, DegreeExceeded, Edit, Map,
+        QueuePolicy, Set, SortResults, TopoSort,
+    };
+
+    #[test]
+    fn test_termination() {
+        let mut topo_sort = TopoSort::with_capacity(4);
+        topo_sort.insert(1, vec![2]);
+        topo_sort.insert(2, vec![1]); // cycle
+        topo_sort.insert(3, vec![4]);
+        topo_sort.insert(4, vec![]);
+
+        let v: Vec<Result<_, _>> = topo_sort.nodes().collect();
+        assert_eq!(vec![Ok(&4), Ok(&3), Err(CycleError)], v);
+    }
+
+    #[test]
+    fn test_nodes_detailed() {
+        let mut topo_sort = TopoSort::with_capacity(4);
+        topo_sort.insert(1, vec![2]);
+        topo_sort.insert(2, vec![1]); // cycle
+        topo_sort.insert(3, vec![4]);
+        topo_sort.insert(4, vec![]);
+
+        let mut iter = topo_sort.nodes_detailed();
+        assert_eq!(Some(Ok(&4)), iter.next());
+        assert_eq!(Some(Ok(&3)), iter.next());
+        let err = iter.next().unwrap().unwrap_err();
+        assert_eq!(&Set::from_iter(vec![&1, &2]), err.stuck());
+        assert!(err.cycle() == [&1, &2] || err.cycle() == [&2, &1]);
+        let msg = err.to_string();
+        assert!(
+            msg == "cycle detected: 1 -> 2 -> 1 (2 node(s) could not be sorted)"
+                || msg == "cycle detected: 2 -> 1 -> 2 (2 node(s) could not be sorted)"
+        );
+        assert_eq!(CycleError, CycleError::from(err));
+        assert_eq!(None, iter.next());
+
+        topo_sort.insert(2, vec![]);
+        let nodes: Result<Vec<_>, _> = topo_sort.nodes_detailed().collect();
+        assert_eq!(4, nodes.unwrap().len());
+    }
+
+    #[test]
+    fn test_try_vec_detailed() {
+        let mut topo_sort = TopoSort::with_capacity(4);
+        topo_sort.insert(1, vec![2]);
+        topo_sort.insert(2, vec![3]);
+        topo_sort.insert(3, vec![1]); // cycle
+        topo_sort.insert(4, vec![1]);
+
+        let err = topo_sort.try_vec_detailed().unwrap_err();
+        assert_eq!(&Set::from_iter(vec![&1, &2, &3, &4]), err.stuck());
+        assert_eq!(3, err.cycle().len());
+        assert!(!err.cycle().contains(&&4));
+
+        let err = topo_sort.try_owned_vec_detailed().unwrap_err();
+        assert_eq!(&Set::from_iter(vec![1, 2, 3, 4]), err.stuck());
+        assert_eq!(3, err.cycle().len());
+
+        topo_sort.insert(3, vec![]);
+        let expected = vec![
+            (3, Set::new()),
+            (2, Set::from_iter(vec![3])),
+            (1, Set::from_iter(vec![2])),
+        ];
+        assert_eq!(
+            Ok(expected),
+            topo_sort.try_owned_vec_detailed().map(|v| v[..3].to_vec())
+        );
+        assert_eq!(4, topo_sort.try_vec_detailed().unwrap().len());
+    }
+
+    #[cfg(any(feature = "indexmap", feature = "indexmap-serde"))]
+    #[test]
+    fn test_insertion_order_ties() {
+        let mut topo_sort = TopoSort::with_capacity(6);
+        topo_sort.insert("E", vec!["A"]);
+        topo_sort.insert("C", vec![]);
+        topo_sort.insert("D", vec!["A"]);
+        topo_sort.insert("A", vec![]);
+        topo_sort.insert("B", vec![]);
+        topo_sort.insert("F", vec![]);
+        assert_eq!(
+            vec!["C", "A", "E", "D", "B", "F"],
+            topo_sort.try_owned_vec_nodes().unwrap()
+        );
+
+        // Replacing keeps the position, while removing shifts the rest up
+        topo_sort.insert("C", vec!["D"]);
+        topo_sort.take(&"B");
+        assert_eq!(
+            vec!["A", "E", "D", "C", "F"],
+            topo_sort.try_owned_vec_nodes().unwrap()
+        );
+
+        // ...except for `remove_unchecked`, which moves the last node into the gap
+        topo_sort.remove_unchecked(&"E");
+        assert_eq!(
+            vec!["F", "A", "D", "C"],
+            topo_sort.try_into_vec_nodes().unwrap()
+        );
+    }
+
+    #[cfg(any(feature = "indexmap", feature = "indexmap-serde"))]
+    #[test]
+    fn test_stepper_insertion_order_ties() {
+        let mut topo_sort = TopoSort::with_capacity(6);
+        topo_sort.insert("E", vec!["A"]);
+        topo_sort.insert("C", vec![]);
+        topo_sort.insert("D", vec!["A"]);
+        topo_sort.insert("A", vec![]);
+        topo_sort.insert("B", vec![]);
+        topo_sort.insert("F", vec![]);
+
+        let mut stepper = topo_sort.stepper();
+        let stepped: Result<Vec<_>, _> = std::iter::from_fn(|| stepper.step()).collect();
+        assert_eq!(topo_sort.try_vec_nodes().unwrap(), stepped.unwrap());
+    }
+
+    #[test]
+    fn test_iter_by() {
+        let mut topo_sort = TopoSort::with_comparator(|a: &u32, b: &u32| a.cmp(b));
+        topo_sort.insert(1, vec![]);
+        topo_sort.insert(2, vec![]);
+        topo_sort.insert(3, vec![1]);
+        topo_sort.insert(4, vec![2]);
+        topo_sort.insert(5, vec![3, 4]);
+
+        // Most "expensive" (largest) first
+        let nodes: Vec<_> = topo_sort
+            .nodes_by(|a, b| b.cmp(a))
+            .map(Result::unwrap)
+            .collect();
+        assert_eq!(vec![&2, &4, &1, &3, &5], nodes);
+        let nodes: Vec<_> = topo_sort
+            .iter_by(|a, b| b.cmp(a))
+            .map(|result| *result.unwrap().0)
+            .collect();
+        assert_eq!(vec![2, 4, 1, 3, 5], nodes);
+
+        // The stored comparator is unaffected
+        assert_eq!(vec![&1, &2, &3, &4, &5], topo_sort.try_vec_nodes().unwrap());
+
+        topo_sort.insert(1, vec![5]); // cycle
+        let results: Vec<_> = topo_sort.nodes_by(|a, b| b.cmp(a)).collect();
+        assert_eq!(vec![Ok(&2), Ok(&4), Err(CycleError)], results);
+    }
+
+    #[test]
+    fn test_sorted_nodes() {
+        let mut topo_sort = TopoSort::with_capacity(6);
+        topo_sort.insert("d", vec!["b"]);
+        topo_sort.insert("c", vec![]);
+        topo_sort.insert("f", vec![]);
+        topo_sort.insert("b", vec!["f"]);
+        topo_sort.insert("a", vec!["e"]);
+        topo_sort.insert("e", vec![]);
+
+        let nodes: Result<Vec<_>, _> = topo_sort.sorted_nodes().collect();
+        assert_eq!(vec![&"c", &"e", &"a", &"f", &"b", &"d"], nodes.unwrap());
+
+        topo_sort.insert("f", vec!["d"]); // cycle
+        let results: Vec<_> = topo_sort.sorted_nodes().collect();
+        assert_eq!(vec![Ok(&"c"), Ok(&"e"), Ok(&"a"), Err(CycleError)], results);
+    }
+
+    #[test]
+    fn test_nodes_with_policy() {
+        let mut topo_sort = TopoSort::with_capacity(5);
+        topo_sort.insert(1, vec![]);
+        topo_sort.insert(2, vec![1]);
+        topo_sort.insert(3, vec![1]);
+        topo_sort.insert(4, vec![2]);
+        topo_sort.insert(5, vec![3]);
+
+        let sort = |policy| -> Vec<_> {
+            topo_sort
+                .nodes_with_policy(policy)
+                .map(Result::unwrap)
+                .collect()
+        };
+        // 2 and 3 become ready together, and then depth vs. breadth decides what comes next
+        let nodes = sort(QueuePolicy::Lifo);
+        assert!(nodes == [&1, &2, &4, &3, &5] || nodes == [&1, &3, &5, &2, &4]);
+        let nodes = sort(QueuePolicy::Fifo);
+        assert!(nodes == [&1, &2, &3, &4, &5] || nodes == [&1, &3, &2, &5, &4]);
+        let nodes: Vec<_> = topo_sort
+            .nodes_with_policy_by_key(QueuePolicy::MinHeap, |&node| node)
+            .map(Result::unwrap)
+            .collect();
+        assert_eq!(vec![&1, &2, &3, &4, &5], nodes);
+        let nodes: Vec<_> = topo_sort
+            .nodes_with_policy_by_key(QueuePolicy::MinHeap, |&node| -node)
+            .map(Result::unwrap)
+            .collect();
+        assert_eq!(vec![&1, &3, &5, &2, &4], nodes);
+        assert_eq!(topo_sort.len(), sort(QueuePolicy::MinHeap).len());
+        assert_eq!(QueuePolicy::Lifo, QueuePolicy::default());
+
+        topo_sort.insert(1, vec![4]); // cycle
+        let results: Vec<_> = topo_sort.nodes_with_policy(QueuePolicy::Fifo).collect();
+        assert_eq!(vec![Err(CycleError)], results);
+    }
+
+    #[test]
+    fn test_rev_nodes() {
+        let mut topo_sort = TopoSort::with_capacity(5);
+        topo_sort.insert(1, vec![]);
+        topo_sort.insert(2, vec![1]);
+        topo_sort.insert(3, vec![2, 1]);
+        topo_sort.insert(4, vec![3, 6]); // Missing dependency is ignored
+        topo_sort.insert(5, vec![1, 5]); // Self reference is ignored
+
+        let nodes: Vec<_> = topo_sort.rev_nodes().map(Result::unwrap).collect();
+        assert_eq!(5, nodes.len());
+        let pos = |node| nodes.iter().position(|&&n| n == node).unwrap();
+        assert!(pos(4) < pos(3) && pos(3) < pos(2) && pos(2) < pos(1));
+        assert!(pos(5) < pos(1));
+
+        topo_sort.insert(1, vec![3]); // cycle
+        let mut results: Vec<_> = topo_sort.rev_nodes().collect();
+        assert_eq!(Some(Err(CycleError)), results.pop());
+        let mut nodes: Vec<_> = results.into_iter().map(Result::unwrap).collect();
+        nodes.sort_unstable();
+        assert_eq!(vec![&4, &5], nodes);
+    }
+
+    #[test]
+    fn test_remaining() {
+        let mut topo_sort = TopoSort::with_capacity(5);
+        topo_sort.insert(1, vec![2]);
+        topo_sort.insert(2, vec![1]); // cycle
+        topo_sort.insert(3, vec![4]);
+        topo_sort.insert(4, vec![]);
+        topo_sort.insert(5, vec![1]);
+
+        let mut iter = topo_sort.nodes();
+        assert_eq!(5, iter.remaining().len());
+        assert_eq!(Some(Ok(&4)), iter.next());
+        assert_eq!(Some(Ok(&3)), iter.next());
+        assert_eq!(Some(Err(CycleError)), iter.next());
+        assert_eq!(Set::from_iter(vec![&1, &2, &5]), iter.remaining());
+        assert_eq!(None, iter.next());
+
+        topo_sort.insert(2, vec![]);
+        let mut iter = topo_sort.iter();
+        assert_eq!(5, iter.by_ref().count());
+        assert!(iter.remaining().is_empty());
+    }
+
+    #[test]
+    fn test_iter_lenient() {
+        let mut topo_sort = TopoSort::with_capacity(6);
+        topo_sort.insert(1, vec![2]);
+        topo_sort.insert(2, vec![1]); // cycle
+        topo_sort.insert(3, vec![4]);
+        topo_sort.insert(4, vec![]);
+        topo_sort.insert(5, vec![1, 4]); // Depends on the cycle
+        topo_sort.insert(6, vec![3]);
+
+        let mut iter = topo_sort.iter_lenient();
+        let nodes: Vec<_> = iter.by_ref().collect();
+        assert_eq!(vec![&4, &3, &6], nodes);
+        assert_eq!(&Set::from_iter(vec![&1, &2, &5]), iter.stuck());
+        assert_eq!(None, iter.next());
+
+        topo_sort.insert(2, vec![]);
+        let mut iter = topo_sort.iter_lenient();
+        assert_eq!(6, iter.by_ref().count());
+        assert!(iter.stuck().is_empty());
+    }
+
+    #[test]
+    fn test_direct_cycle() {
+        let mut topo_sort = TopoSort::with_capacity(2);
+        topo_sort.insert(1, vec![2]);
+        assert!(!topo_sort.cycle_detected());
+        assert!(!topo_sort.to_owned_vec_nodes().cycle_detected());
+        assert!(topo_sort.try_vec_nodes().is_ok());
+
+        topo_sort.insert(2, vec![1]); // cycle
+        assert!(topo_sort.cycle_detected());
+        assert!(topo_sort.to_owned_vec_nodes().cycle_detected());
+        assert!(topo_sort.try_into_vec_nodes().is_err());
+    }
+
+    #[test]
+    fn test_indirect_cycle() {
+        let mut topo_sort = TopoSort::with_capacity(3);
+        topo_sort.insert(1, vec![2, 3]);
+        topo_sort.insert(2, vec![3]);
+        assert!(!topo_sort.cycle_detected());
+        assert!(!topo_sort.to_owned_vec_nodes().cycle_detected());
+        assert!(topo_sort.try_vec_nodes().is_ok());
+
+        topo_sort.insert(3, vec![1]); // cycle
+        assert!(topo_sort.cycle_detected());
+        assert!(topo_sort.to_owned_vec_nodes().cycle_detected());
+        assert!(topo_sort.try_into_vec_nodes().is_err());
+    }
+
+    #[test]
+    fn test_is_cyclic() {
+        let mut topo_sort = TopoSort::with_capacity(4);
+        assert!(topo_sort.is_acyclic());
+
+        topo_sort.insert(1, vec![2, 3]);
+        topo_sort.insert(2, vec![2, 3]); // Self reference is ignored
+        topo_sort.insert(3, vec![5]); // Missing node is ignored
+        assert!(topo_sort.is_acyclic());
+        assert!(!topo_sort.is_cyclic());
+
+        topo_sort.insert(4, vec![1]);
+        topo_sort.insert(3, vec![4]); // cycle
+        assert!(topo_sort.is_cyclic());
+        assert!(!topo_sort.is_acyclic());
+        assert_eq!(topo_sort.cycle_detected(), topo_sort.is_cyclic());
+    }
+
+    #[test]
+    fn test_check_edits() {
+        let mut topo_sort = TopoSort::with_capacity(3);
+        topo_sort.insert(1, vec![2]);
+        topo_sort.insert(2, vec![3]);
+        topo_sort.insert(3, vec![]);
+
+        assert_eq!(
+            Ok(()),
+            topo_sort.check_edits(vec![Edit::AddNode(4), Edit::AddEdge(4, 1)])
+        );
+        assert_eq!(
+            Err(CycleError),
+            topo_sort.check_edits(vec![Edit::AddNode(4), Edit::AddEdge(3, 1)])
+        );
+        // The cycle is broken again by a later edit in the same batch
+        assert_eq!(
+            Ok(()),
+            topo_sort.check_edits(vec![Edit::AddEdge(3, 1), Edit::RemoveEdge(2, 3)])
+        );
+        assert_eq!(
+            Ok(()),
+            topo_sort.check_edits(vec![Edit::AddEdge(3, 1), Edit::RemoveNode(2)])
+        );
+
+        // Unchanged
+        assert_eq!(3, topo_sort.len());
+        assert_eq!(Set::new(), topo_sort[&3]);
+        assert_eq!(Set::from_iter(vec![3]), topo_sort[&2]);
+    }
+
+    #[test]
+    fn test_typical() {
+        let mut topo_sort = TopoSort::with_capacity(5);
+        topo_sort.insert("C", vec!["A", "B"]);
+        topo_sort.insert("E", vec!["B", "C"]);
+        topo_sort.insert("A", vec![]);
+        topo_sort.insert("D", vec!["A", "C", "E"]);
+        topo_sort.insert("B", vec!["A"]);
+
+        match topo_sort.into_vec_nodes() {
+            SortResults::Full(nodes) => assert_eq!(vec!["A", "B", "C", "E", "D"], nodes),
+            SortResults::Partial(_) => panic!("unexpected cycle!"),
+        }
+    }
+
+    #[test]
+    fn test_sort_edges() {
+        let edges = vec![
+            ("C", "A"),
+            ("C", "B"),
+            ("E", "B"),
+            ("E", "C"),
+            ("D", "A"),
+            ("D", "C"),
+            ("D", "E"),
+            ("B", "A"),
+        ];
+        assert_eq!(vec!["A", "B", "C", "E", "D"], sort_edges(edges).unwrap());
+
+        assert_eq!(Err(CycleError), sort_edges(vec![(1, 2), (2, 3), (3, 1)]));
+        assert_eq!(Ok(Vec::<u32>::new()), sort_edges(vec![]));
+    }
+
+    #[test]
+    fn test_extend_from_edges() {
+        let mut topo_sort = TopoSort::from_edges(vec![("C", "A"), ("C", "B"), ("B", "A")]);
+        assert_eq!(3, topo_sort.len());
+
+        assert_eq!(
+            Ok(()),
+            topo_sort.extend_from_edges(vec![("E", "B"), ("E", "C"), ("D", "E"), ("D", "A")])
+        );
+        assert_eq!(5, topo_sort.len());
+        assert_eq!(Set::from_iter(vec!["A", "B"]), topo_sort[&"C"]);
+        assert_eq!(
+            vec!["A", "B", "C", "E", "D"],
+            topo_sort.try_owned_vec_nodes().unwrap()
+        );
+
+        assert_eq!(Ok(()), topo_sort.extend_from_edges(vec![("A", "D")])); // cycle
+        assert!(topo_sort.try_vec_nodes().is_err());
+    }
+
+    #[test]
+    fn test_from_pairs_grouped() {
+        let rows = vec![
+            ("app", "lib"),
+            ("lib", "core"),
+            ("app", "log"),
+            ("app", "lib"), // Duplicate row
+            ("log", "core"),
+            ("app", "core"),
+        ];
+        let topo_sort = TopoSort::from_pairs_grouped(rows);
+
+        assert_eq!(4, topo_sort.len());
+        assert_eq!(
+            Set::from_iter(vec!["lib", "log", "core"]),
+            topo_sort[&"app"]
+        );
+        assert!(topo_sort[&"core"].is_empty());
+        let nodes = topo_sort.try_vec_nodes().unwrap();
+        assert_eq!((&"core", &"app"), (nodes[0], nodes[3]));
+    }
+
+    #[test]
+    fn test_merge_with() {
+        let mut base = TopoSort::with_capacity(4);
+        base.insert("app", vec!["lib", "log"]);
+        base.insert("lib", vec![]);
+        base.insert("log", vec![]);
+
+        let mut other = TopoSort::with_capacity(3);
+        other.insert("app", vec!["lib", "tls"]);
+        other.insert("tls", vec!["lib"]);
+
+        let mut union = base.clone();
+        assert_eq!(
+            Ok(()),
+            union.merge_with(other.clone(), |_, a, b| a.union(b).cloned().collect())
+        );
+        assert_eq!(4, union.len());
+        assert_eq!(Set::from_iter(vec!["lib", "log", "tls"]), union[&"app"]);
+        assert_eq!(Set::from_iter(vec!["lib"]), union[&"tls"]);
+
+        let mut intersection = base;
+        assert_eq!(
+            Ok(()),
+            intersection.merge_with(other, |_, a, b| a.intersection(b).cloned().collect())
+        );
+        assert_eq!(4, intersection.len());
+        assert_eq!(Set::from_iter(vec!["lib"]), intersection[&"app"]);
+        assert!(intersection[&"log"].is_empty());
+    }
+
+    #[test]
+    fn test_sort_by_key() {
+        #[derive(Debug, PartialEq)]
+        struct Crate {
+            name: &'static str,
+            deps: Vec<&'static str>,
+        }
+
+        let krate = |name, deps| Crate { name, deps };
+        let crates = vec![
+            krate("app", vec!["serde", "log"]),
+            krate("serde", vec!["serde_derive"]),
+            krate("log", vec!["cfg-if"]), // There is no "cfg-if"
+            krate("serde_derive", vec![]),
+        ];
+
+        let sorted = sort_by_key(crates, |c| c.name, |c| c.deps.clone()).unwrap();
+        let names: Vec<_> = sorted.iter().map(|c| c.name).collect();
+        let pos = |name| names.iter().position(|&n| n == name).unwrap();
+        assert_eq!(4, names.len());
+        assert_eq!("app", names[3]);
+        assert!(pos("serde_derive") < pos("serde"));
+
+        let cycle = vec![krate("a", vec!["b"]), krate("b", vec!["a"])];
+        assert_eq!(
+            Err(CycleError),
+            sort_by_key(cycle, |c| c.name, |c| c.deps.clone())
+        );
+    }
+
+    #[test]
+    fn test_empty() {
+        let topo_sort: TopoSort<u32> = TopoSort::new();
+        assert_eq!(
+            Vec::new() as Vec<(u32, Set<u32>)>,
+            topo_sort.try_owned_vec().unwrap()
+        );
+    }
+
+    #[test]
+    fn test_cached_order() {
+        let mut topo_sort = TopoSort::with_capacity(3);
+        topo_sort.insert(1, vec![2]);
+        topo_sort.insert(2, vec![]);
+        assert_eq!(vec![&2, &1], topo_sort.try_vec_nodes().unwrap());
+        assert_eq!(vec![&2, &1], topo_sort.try_vec_nodes().unwrap());
+
+        // Every mutation invalidates the cached order
+        topo_sort.insert(3, vec![1]);
+        assert_eq!(vec![&2, &1, &3], topo_sort.try_vec_nodes().unwrap());
+        topo_sort.add_dependency(2, 3); // cycle
+        assert_eq!(Err(CycleError), topo_sort.try_vec());
+        assert!(topo_sort.remove_dependency(&2, &3));
+        let (nodes, depends): (Vec<&i32>, Vec<&Set<i32>>) =
+            topo_sort.try_vec().unwrap().into_iter().unzip();
+        assert_eq!(vec![&2, &1, &3], nodes);
+        assert_eq!(&Set::from_iter(vec![2]), depends[1]);
+        topo_sort.remove(&1);
+        let mut nodes = topo_sort.try_vec_nodes().unwrap();
+        nodes.sort();
+        assert_eq!(vec![&2, &3], nodes);
+        *topo_sort.entry(2).or_default() = Set::from_iter(vec![3]);
+        assert_eq!(vec![&3, &2], topo_sort.try_vec_nodes().unwrap());
+    }
+
+    #[test]
+    fn test_owned_vec_capacity() {
+        let mut topo_sort = TopoSort::with_capacity(100);
+        topo_sort.insert(0, vec![]);
+        for node in 1..100 {
+            topo_sort.insert(node, vec![node - 1]);
+        }
+
+        // Output is allocated once at its final size
+        let nodes = topo_sort.try_owned_vec().unwrap();
+        assert_eq!(100, nodes.len());
+        assert_eq!(nodes.len(), nodes.capacity());
+
+        let nodes = topo_sort.try_owned_vec_nodes().unwrap();
+        assert_eq!((0..100).collect::<Vec<_>>(), nodes);
+        assert_eq!(nodes.len(), nodes.capacity());
+    }
+
+    #[test]
+    fn test_with_no_depends() {
+        let mut topo_sort = TopoSort::with_capacity(1);
+        topo_sort.insert("C", vec![]);
+
+        assert_eq!(vec!["C"], topo_sort.try_owned_vec_nodes().unwrap());
+    }
+
+    #[test]
+    fn test_with_excess_depends() {
+        let mut topo_sort = TopoSort::with_capacity(5);
+        topo_sort.insert("C", vec!["F", "A", "B", "F"]); // There is no 'F' - two of them
+        topo_sort.insert("E", vec!["C", "B", "C"]); // Double "C" dependency
+        topo_sort.insert("A", vec!["A", "G"]); // Self dependency + there is no 'G'
+        topo_sort.insert("D", vec!["A", "C", "E"]);
+        topo_sort.insert("B", vec!["B", "A"]); // Self dependency
+
+        assert_eq!(
+            vec!["A", "B", "C", "E", "D"],
+            topo_sort.try_owned_vec_nodes().unwrap()
+        );
+    }
+
+    #[test]
+    fn test_effective_dependencies() {
+        let mut topo_sort = TopoSort::with_capacity(5);
+        topo_sort.insert("C", vec!["F", "A", "B", "F"]); // There is no 'F' - two of them
+        topo_sort.insert("E", vec!["C", "B", "C"]); // Double "C" dependency
+        topo_sort.insert("A", vec!["A", "G"]); // Self dependency + there is no 'G'
+        topo_sort.insert("D", vec!["A", "C", "E"]);
+        topo_sort.insert("B", vec!["B", "A"]); // Self dependency
+
+        assert_eq!(
+            Some(Set::from_iter(vec![&"A", &"B"])),
+            topo_sort.effective_dependencies(&"C")
+        );
+        assert_eq!(
+            Some(Set::from_iter(vec![&"A"])),
+            topo_sort.effective_dependencies(&"B")
+        );
+        assert_eq!(Some(Set::new()), topo_sort.effective_dependencies(&"A"));
+        assert_eq!(None, topo_sort.effective_dependencies(&"F"));
+    }
+
+    #[test]
+    fn test_inspect() {
+        let mut topo_sort = TopoSort::with_capacity(5);
+        topo_sort.insert("C", vec!["F", "A", "B", "F"]); // There is no 'F' - two of them
+        topo_sort.insert("E", vec!["C", "B", "C"]); // Double "C" dependency
+        topo_sort.insert("A", vec!["A", "G"]); // Self dependency + there is no 'G'
+        topo_sort.insert("D", vec!["A", "C", "E"]);
+        topo_sort.insert("B", vec!["B", "A"]); // Self dependency
+
+        let inspection = topo_sort.inspect(&"C").unwrap();
+        assert_eq!(Set::from_iter(vec![&"A", &"B"]), inspection.present_deps);
+        assert_eq!(Set::from_iter(vec![&"F"]), inspection.missing_deps);
+        assert!(!inspection.self_loop);
+
+        let inspection = topo_sort.inspect(&"A").unwrap();
+        assert!(inspection.present_deps.is_empty());
+        assert_eq!(Set::from_iter(vec![&"G"]), inspection.missing_deps);
+        assert!(inspection.self_loop);
+
+        assert_eq!(None, topo_sort.inspect(&"F"));
+    }
+
+    #[test]
+    fn test_from_map_cleaned() {
+        let mut map = Map::with_capacity(4);
+        map.insert("A", Set::from_iter(vec!["A", "G"])); // Self dependency + there is no 'G'
+        map.insert("B", Set::from_iter(vec!["A"]));
+        map.insert("C", Set::from_iter(vec!["A", "B", "F"])); // There is no 'F'
+        map.insert("D", Set::from_iter(vec!["D"])); // Self dependency
+
+        let (topo_sort, mut report) = TopoSort::from_map_cleaned(map);
+        report.self_loops.sort_unstable();
+        report.dangling.sort_unstable();
+        assert!(!report.is_clean());
+        assert_eq!(vec!["A", "D"], report.self_loops);
+        assert_eq!(vec![("A", "G"), ("C", "F")], report.dangling);
+
+        assert_eq!(4, topo_sort.len());
+        assert_eq!(Set::new(), topo_sort[&"A"]);
+        assert_eq!(Set::from_iter(vec!["A", "B"]), topo_sort[&"C"]);
+        assert_eq!(Set::new(), topo_sort[&"D"]);
+
+        let (_, report) = TopoSort::from_map_cleaned(topo_sort.into_inner());
+        assert!(report.is_clean());
+    }
+
+    #[test]
+    fn test_iter() {
+        let mut topo_sort = TopoSort::with_capacity(5);
+        topo_sort.insert("C", vec!["A", "B"]);
+        topo_sort.insert("E", vec!["B", "C"]);
+        topo_sort.insert("A", vec![]);
+        topo_sort.insert("D", vec!["A", "C", "E"]);
+        topo_sort.insert("B", vec!["A"]);
+
+        let mut nodes = Vec::with_capacity(5);
+        for node in &topo_sort {
+            match node {
+                Ok(node) => nodes.push(node),
+                Err(_) => panic!("Unexpected cycle!"),
+            }
+        }
+
+        assert_eq!(
+            vec![
+                (&"A", &Set::new()),
+                (&"B", &Set::from_iter(vec!["A"])),
+                (&"C", &Set::from_iter(vec!["A", "B"])),
+                (&"E", &Set::from_iter(vec!["B", "C"])),
+                (&"D", &Set::from_iter(vec!["A", "C", "E"])),
+            ],
+            nodes
+        );
+    }
+
+    #[test]
+    fn test_into_iter() {
+        let mut topo_sort = TopoSort::with_capacity(5);
+        topo_sort.insert("C", vec!["A", "B"]);
+        topo_sort.insert("E", vec!["B", "C"]);
+        topo_sort.insert("A", vec![]);
+        topo_sort.insert("D", vec!["A", "C", "E"]);
+        topo_sort.insert("B", vec!["A"]);
+
+        let mut nodes = Vec::with_capacity(5);
+        for node in topo_sort {
+            match node {
+                Ok(node) => nodes.push(node),
+                Err(_) => panic!("Unexpected cycle!"),
+            }
+        }
+
+        assert_eq!(
+            vec![
+                ("A", Set::new()),
+                ("B", Set::from_iter(vec!["A"])),
+                ("C", Set::from_iter(vec!["A", "B"])),
+                ("E", Set::from_iter(vec!["B", "C"])),
+                ("D", Set::from_iter(vec!["A", "C", "E"])),
+            ],
+            nodes
+        );
+    }
+
+    #[test]
+    fn test_into_iter_cycle() {
+        let mut topo_sort = TopoSort::with_capacity(4);
+        topo_sort.insert(String::from("A"), vec![]);
+        topo_sort.insert(
+            String::from("B"),
+            vec![String::from("A"), String::from("C")],
+        );
+        topo_sort.insert(String::from("C"), vec![String::from("B")]);
+        topo_sort.insert(String::from("D"), vec![String::from("A")]);
+
+        let mut iter = topo_sort.into_iter();
+        assert_eq!((4, Some(4)), iter.size_hint());
+        assert_eq!("A", iter.next().unwrap().unwrap().0);
+        assert_eq!("D", iter.next().unwrap().unwrap().0);
+        assert_eq!((2, Some(2)), iter.size_hint());
+        assert!(iter.next().unwrap().is_err());
+        assert!(iter.next().is_none());
+    }
+
+    #[test]
+    fn test_edges_sorted() {
+        let mut topo_sort = TopoSort::with_capacity(5);
+        topo_sort.insert("C", vec!["A", "B", "F"]); // There is no 'F'
+        topo_sort.insert("E", vec!["B", "C"]);
+        topo_sort.insert("A", vec!["A"]); // Self dependency
+        topo_sort.insert("D", vec!["A", "C", "E"]);
+        topo_sort.insert("B", vec!["A"]);
+
+        let edges: Vec<_> = topo_sort.edges_sorted().collect::<Result<_, _>>().unwrap();
+        assert_eq!(8, edges.len());
+
+        let order = topo_sort.try_vec_nodes().unwrap();
+        let position = |node| order.iter().position(|&n| n == node).unwrap();
+        // Edges are grouped by their dependent in sorted order
+        assert!(edges
+            .windows(2)
+            .all(|pair| position(pair[0].0) <= position(pair[1].0)));
+        assert!(edges
+            .iter()
+            .all(|(node, dep)| position(dep) < position(node)));
+
+        topo_sort.insert("A", vec!["D"]); // cycle
+        assert_eq!(
+            Some(Err(CycleError)),
+            topo_sort.edges_sorted().find(|result| result.is_err())
+        );
+    }
+
+    #[test]
+    fn test_filter_nodes() {
+        let mut topo_sort = TopoSort::with_capacity(6);
+        topo_sort.insert(1, vec![]);
+        topo_sort.insert(2, vec![1]);
+        topo_sort.insert(3, vec![2]);
+        topo_sort.insert(4, vec![3]);
+        topo_sort.insert(5, vec![4]);
+        topo_sort.insert(6, vec![5]);
+
+        let nodes: Result<Vec<_>, _> = topo_sort
+            .nodes()
+            .filter_nodes(|node| node % 2 == 0)
+            .collect();
+        assert_eq!(vec![&2, &4, &6], nodes.unwrap());
+
+        topo_sort.insert(1, vec![6]); // cycle
+        let nodes: Vec<_> = topo_sort.nodes().filter_nodes(|_| false).collect();
+        assert_eq!(vec![Err(CycleError)], nodes);
+    }
+
+    #[test]
+    fn test_with_comparator() {
+        let mut topo_sort = TopoSort::with_comparator(|a: &u32, b: &u32| b.cmp(a));
+        topo_sort.insert(1, vec![]);
+        topo_sort.insert(2, vec![]);
+        topo_sort.insert(3, vec![1]);
+        topo_sort.insert(4, vec![2]);
+        topo_sort.insert(5, vec![3, 4]);
+
+        // Largest ready node first
+        let expected = vec![2, 4, 1, 3, 5];
+        let nodes: Vec<_> = topo_sort.nodes().flatten().copied().collect();
+        assert_eq!(expected, nodes);
+        let nodes: Vec<_> = topo_sort.iter().flatten().map(|(&node, _)| node).collect();
+        assert_eq!(expected, nodes);
+        let nodes: Vec<_> = topo_sort
+            .try_vec()
+            .unwrap()
+            .into_iter()
+            .map(|(&node, _)| node)
+            .collect();
+        assert_eq!(expected, nodes);
+        assert_eq!(expected, topo_sort.try_owned_vec_nodes().unwrap());
+
+        let mut stepper = topo_sort.stepper();
+        let mut nodes = Vec::with_capacity(5);
+        while let Some(result) = stepper.step() {
+            nodes.push(*result.unwrap());
+        }
+        assert_eq!(expected, nodes);
+
+        match topo_sort.clone().into_vec_nodes() {
+            SortResults::Full(nodes) => assert_eq!(expected, nodes),
+            SortResults::Partial(_) => panic!("unexpected cycle!"),
+        }
+        assert_eq!(expected, topo_sort.try_into_vec_nodes().unwrap());
+    }
+
+    #[test]
+    fn test_for_each_mut() {
+        let mut topo_sort = TopoSort::with_capacity(4);
+        topo_sort.insert("A", vec![]);
+        topo_sort.insert("B", vec!["A"]);
+        topo_sort.insert("C", vec!["B"]);
+        topo_sort.insert("D", vec!["C", "A"]);
+
+        // Replace each dependency set with the full transitive set, which relies on dependencies going first
+        let mut closures: Map<&str, Set<&str>> = Map::new();
+        topo_sort
+            .for_each_mut(|node, depends| {
+                let mut closure = depends.clone();
+                for dep in depends.iter() {
+                    closure.extend(closures[dep].iter().copied());
+                }
+                *depends = closure.clone();
+                closures.insert(node, closure);
+            })
+            .unwrap();
+
+        assert_eq!(Set::from_iter(vec!["A", "B", "C"]), topo_sort[&"D"]);
+        assert_eq!(Set::from_iter(vec!["A", "B"]), topo_sort[&"C"]);
+        assert_eq!(4, closures.len());
+
+        topo_sort.insert("A", vec!["D"]); // cycle
+        let mut called = false;
+        assert_eq!(
+            Err(CycleError),
+            topo_sort.for_each_mut(|_, _| called = true)
+        );
+        assert!(!called);
+    }
+
+    #[test]
+    fn test_stepper() {
+        let mut topo_sort = TopoSort::with_capacity(5);
+        topo_sort.insert("C", vec!["A", "B"]);
+        topo_sort.insert("E", vec!["B", "C"]);
+        topo_sort.insert("A", vec![]);
+        topo_sort.insert("D", vec!["A", "C", "E"]);
+        topo_sort.insert("B", vec!["A"]);
+
+        let mut stepper = topo_sort.stepper();
+        assert_eq!(&[&"A"], stepper.ready());
+        assert_eq!(5, stepper.remaining());
+
+        let mut nodes = Vec::with_capacity(5);
+        while let Some(result) = stepper.step() {
+            nodes.push(result.unwrap());
+        }
+
+        assert!(stepper.ready().is_empty());
+        assert_eq!(topo_sort.try_vec_nodes().unwrap(), nodes);
+    }
+
+    #[test]
+    fn test_stepper_choose() {
+        let mut topo_sort = TopoSort::with_capacity(3);
+        topo_sort.insert(1, vec![]);
+        topo_sort.insert(2, vec![]);
+        topo_sort.insert(3, vec![1, 2]);
+
+        let mut stepper = topo_sort.stepper();
+        let mut nodes = Vec::with_capacity(3);
+        while !stepper.ready().is_empty() {
+            // Always pick the largest ready node
+            let (index, _) = stepper
+                .ready()
+                .iter()
+                .enumerate()
+                .max_by_key(|(_, &&node)| node)
+                .unwrap();
+            nodes.push(*stepper.step_at(index).unwrap().unwrap());
+        }
+
+        assert_eq!(vec![2, 1, 3], nodes);
+        assert!(stepper.step().is_none());
+    }
+
+    #[test]
+    fn test_stepper_cycle() {
+        let mut topo_sort = TopoSort::with_capacity(3);
+        topo_sort.insert(1, vec![2]);
+        topo_sort.insert(2, vec![1]); // cycle
+        topo_sort.insert(3, vec![]);
+
+        let mut stepper = topo_sort.stepper();
+        assert_eq!(Some(Ok(&3)), stepper.step());
+        assert_eq!(Some(Err(CycleError)), stepper.step());
+        assert_eq!(None, stepper.step());
+    }
 
-/// Consuming/owning Iterator over the final node only of the topological sort
-pub struct IntoTopoSortNodeIter<T>(IntoTopoSortIter<T>);
+    #[test]
+    fn test_set_dependencies() {
+        let mut topo_sort = TopoSort::with_capacity(2);
+        topo_sort.set_dependencies("A", vec!["B"]);
+        topo_sort.set_dependencies("A", vec!["C"]);
+        assert_eq!(Set::from_iter(vec!["C"]), topo_sort[&"A"]);
 
-impl<'d, T> IntoTopoSortNodeIter<T>
-where
-    T: Eq + Hash,
-{
-    #[inline]
-    fn new(node_depends: Map<T, Set<T>>) -> Self {
-        IntoTopoSortNodeIter(IntoTopoSortIter::new(node_depends))
+        topo_sort.insert("A", vec!["D"]);
+        assert_eq!(Set::from_iter(vec!["D"]), topo_sort[&"A"]);
     }
-}
 
-impl<T> Iterator for IntoTopoSortNodeIter<T>
-where
-    T: Eq + Hash,
-{
-    type Item = Result<T, CycleError>;
+    #[test]
+    fn test_add_dependencies() {
+        let mut topo_sort = TopoSort::with_capacity(2);
+        topo_sort.add_dependencies("A", vec!["B"]);
+        assert_eq!(Set::from_iter(vec!["B"]), topo_sort[&"A"]);
 
-    #[inline]
-    fn next(&mut self) -> Option<Self::Item> {
-        self.0.next().map(|result| result.map(|(node, _)| node))
+        topo_sort.add_dependencies("A", vec!["C", "B"]);
+        topo_sort.add_dependencies("A", vec![]);
+        assert_eq!(Set::from_iter(vec!["B", "C"]), topo_sort[&"A"]);
+
+        topo_sort.set_dependencies("A", vec!["D"]);
+        assert_eq!(Set::from_iter(vec!["D"]), topo_sort[&"A"]);
     }
 
-    #[inline]
-    fn size_hint(&self) -> (usize, Option<usize>) {
-        self.0.size_hint()
+    #[test]
+    fn test_add_dependency() {
+        let mut topo_sort = TopoSort::with_capacity(3);
+        topo_sort.insert("A", vec![]);
+        topo_sort.add_dependency("B", "A");
+        topo_sort.add_dependency("C", "A");
+        topo_sort.add_dependency("C", "B");
+        topo_sort.add_dependency("C", "B");
+
+        assert_eq!(Set::from_iter(vec!["A"]), topo_sort[&"B"]);
+        assert_eq!(Set::from_iter(vec!["A", "B"]), topo_sort[&"C"]);
+        assert_eq!(vec![&"A", &"B", &"C"], topo_sort.try_vec_nodes().unwrap());
     }
-}
 
-// *** TopoSortIter ***
+    #[test]
+    fn test_try_add_dependency() {
+        let mut topo_sort = TopoSort::with_capacity(4);
+        assert_eq!(Ok(()), topo_sort.try_add_dependency("B", "A"));
+        assert_eq!(Ok(()), topo_sort.try_add_dependency("C", "B"));
+        assert_eq!(Ok(()), topo_sort.try_add_dependency("C", "C")); // Self reference is ignored
+        topo_sort.insert("A", vec![]);
 
-/// Iterator over the final node and dependent set of the topological sort
-pub struct TopoSortIter<'d, T> {
-    inner: InnerIter<T>,
+        assert_eq!(
+            Err(AddDependencyError::Cycle(CycleError)),
+            topo_sort.try_add_dependency("A", "C")
+        );
+        assert!(topo_sort[&"A"].is_empty());
+        assert_eq!(Ok(()), topo_sort.try_add_dependency("C", "A"));
 
-    // Dependent -> Dependencies
-    node_depends: &'d Map<T, Set<T>>,
-}
+        // "D" isn't a node yet, but "A" depending on it would close a cycle once it is
+        topo_sort.insert("A", vec!["D"]);
+        assert_eq!(
+            Err(AddDependencyError::Cycle(CycleError)),
+            topo_sort.try_add_dependency("D", "C")
+        );
+        assert_eq!(None, topo_sort.get(&"D"));
+        assert_eq!(Ok(()), topo_sort.try_add_dependency("D", "E"));
+        assert!(topo_sort.is_acyclic());
 
-impl<'d, T> TopoSortIter<'d, T>
-where
-    T: Eq + Hash,
-{
-    #[inline]
-    fn new(node_depends: &'d Map<T, Set<T>>) -> Self {
-        TopoSortIter {
-            inner: InnerIter::new(node_depends),
-            node_depends,
-        }
+        // The degree is checked instead of panicking, and re-adding an existing dependency doesn't count twice
+        let mut topo_sort = TopoSort::with_max_degree(1);
+        assert_eq!(Ok(()), topo_sort.try_add_dependency("B", "A"));
+        assert_eq!(Ok(()), topo_sort.try_add_dependency("B", "A"));
+        assert_eq!(
+            Err(AddDependencyError::DegreeExceeded(DegreeExceeded {
+                degree: 2,
+                max_degree: 1
+            })),
+            topo_sort.try_add_dependency("B", "C")
+        );
+        assert_eq!(Set::from_iter(vec!["A"]), topo_sort[&"B"]);
     }
-}
 
-impl<'d, T> Iterator for TopoSortIter<'d, T>
-where
-    T: Eq + Hash,
-{
-    type Item = Result<(&'d T, &'d Set<T>), CycleError>;
+    #[test]
+    fn test_max_degree() {
+        let mut topo_sort = TopoSort::with_max_degree(2);
+        assert_eq!(Ok(()), topo_sort.try_insert("A", vec!["B", "C"]));
+        assert_eq!(
+            Err(DegreeExceeded {
+                degree: 3,
+                max_degree: 2
+            }),
+            topo_sort.try_insert("B", vec!["C", "D", "E"])
+        );
+        assert_eq!(None, topo_sort.get(&"B"));
 
-    fn next(&mut self) -> Option<Self::Item> {
-        self.inner.next().map(|result| {
-            result.map(|node| {
-                // Safe: We ensure every node is always added first thing in the loop in 'new'
-                unsafe { (&*node, &self.node_depends[&*node]) }
-            })
-        })
-    }
+        assert_eq!(
+            Err(DegreeExceeded {
+                degree: 3,
+                max_degree: 2
+            }),
+            topo_sort.try_add_dependencies("A", vec!["C", "D"])
+        );
+        assert_eq!(Set::from_iter(vec!["B", "C"]), topo_sort[&"A"]);
 
-    #[inline]
-    fn size_hint(&self) -> (usize, Option<usize>) {
-        self.inner.size_hint()
+        // Already present dependencies don't count twice
+        assert_eq!(Ok(()), topo_sort.try_add_dependencies("A", vec!["C"]));
+        assert_eq!(1, topo_sort.len());
     }
-}
 
-// *** TopoSortNodeIter ***
+    #[test]
+    #[should_panic(expected = "maximum is 1")]
+    fn test_max_degree_panic() {
+        let mut topo_sort = TopoSort::with_max_degree(1);
+        topo_sort.insert("A", vec!["B", "C"]);
+    }
 
-/// Iterator over the final node only of the topological sort
-pub struct TopoSortNodeIter<'d, T>(TopoSortIter<'d, T>);
+    #[test]
+    fn test_max_degree_merge() {
+        let mut topo_sort = TopoSort::with_max_degree(2);
+        topo_sort.insert("A", vec!["B"]);
+        let before = topo_sort.clone();
+
+        // Every edge and node is checked before anything is inserted
+        let exceeded = Err(DegreeExceeded {
+            degree: 3,
+            max_degree: 2,
+        });
+        assert_eq!(
+            exceeded,
+            topo_sort.extend_from_edges(vec![("Z", "A"), ("A", "B"), ("A", "C"), ("A", "D")])
+        );
+        assert_eq!(before.node_depends, topo_sort.node_depends);
+        assert_eq!(
+            Ok(()),
+            topo_sort.extend_from_edges(vec![("A", "C"), ("D", "A")])
+        );
+        assert_eq!(3, topo_sort.len());
+
+        let mut other = TopoSort::new();
+        other.insert_with_priority("E", vec![], 5);
+        other.insert("A", vec!["E"]);
+        let before = topo_sort.clone();
+        let union = |_: &_, a: &Set<_>, b: &Set<_>| a.union(b).cloned().collect();
+        assert_eq!(exceeded, topo_sort.merge_with(other.clone(), union));
+        assert_eq!(before.node_depends, topo_sort.node_depends);
+        assert_eq!(0, topo_sort.priority(&"E"));
+        assert_eq!(Ok(()), topo_sort.merge_with(other, |_, a, _| a.clone()));
+        assert_eq!(5, topo_sort.priority(&"E"));
+    }
 
-impl<'d, T> TopoSortNodeIter<'d, T>
-where
-    T: Eq + Hash,
-{
-    #[inline]
-    fn new(node_depends: &'d Map<T, Set<T>>) -> Self {
-        TopoSortNodeIter(TopoSortIter::new(node_depends))
+    #[test]
+    #[should_panic(expected = "`retain` is not available with a maximum degree")]
+    fn test_max_degree_retain_panic() {
+        let mut topo_sort = TopoSort::with_max_degree(1);
+        topo_sort.insert("A", vec!["B"]);
+        topo_sort.retain(|_, depends| depends.insert("C"));
     }
-}
 
-impl<'d, T> Iterator for TopoSortNodeIter<'d, T>
-where
-    T: Eq + Hash,
-{
-    type Item = Result<&'d T, CycleError>;
+    #[test]
+    fn test_max_degree_mut_access() {
+        let mut topo_sort = TopoSort::with_max_degree(1);
+        topo_sort.insert("A", vec![]);
+        topo_sort.insert("B", vec!["A"]);
+        let before = topo_sort.clone();
 
-    #[inline]
-    fn next(&mut self) -> Option<Self::Item> {
-        self.0.next().map(|result| result.map(|(node, _)| node))
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            topo_sort.for_each_mut(|_, depends| {
+                depends.insert("C");
+            })
+        }));
+        assert!(result.is_err());
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            topo_sort.entry("B").or_default().insert("C");
+        }));
+        assert!(result.is_err());
+        // Nothing was changed
+        assert_eq!(before.node_depends, topo_sort.node_depends);
+
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            topo_sort.extend(vec![("C", vec![]), ("D", vec!["A", "B"])]);
+        }));
+        assert!(result.is_err());
+        assert_eq!(before.node_depends, topo_sort.node_depends);
     }
 
-    #[inline]
-    fn size_hint(&self) -> (usize, Option<usize>) {
-        self.0.size_hint()
+    // Random number below `bound` (seeded, so random graphs are reproducible)
+    fn below(rng: &mut XorShiftRng, bound: u64) -> u64 {
+        rng.next_u64() % bound
     }
-}
 
-// *** Tests ***
+    // Reference cycle check using a depth first search (self references and missing nodes are ignored)
+    fn has_cycle(topo_sort: &TopoSort<u64>) -> bool {
+        fn visit(topo_sort: &TopoSort<u64>, node: u64, state: &mut Map<u64, bool>) -> bool {
+            match state.get(&node) {
+                Some(&done) => return !done,
+                None => state.insert(node, false),
+            };
+            for &dep in &topo_sort[&node] {
+                if dep != node && topo_sort.get(&dep).is_some() && visit(topo_sort, dep, state) {
+                    return true;
+                }
+            }
+            state.insert(node, true);
+            false
+        }
 
-#[cfg(test)]
-mod tests {
-    use crate::{CycleError, Map, Set, SortResults, TopoSort};
+        let mut state = Map::new();
+        topo_sort
+            .node_depends
+            .keys()
+            .any(|&node| visit(topo_sort, node, &mut state))
+    }
 
     #[test]
-    fn test_termination() {
-        let mut topo_sort = TopoSort::with_capacity(4);
-        topo_sort.insert(1, vec![2]);
-        topo_sort.insert(2, vec![1]); // cycle
-        topo_sort.insert(3, vec![4]);
-        topo_sort.insert(4, vec![]);
+    fn test_random_graphs() {
+        let mut rng = XorShiftRng::seed_from_u64(0x2545_f491_4f6c_dd1d);
+        let (mut acyclic, mut cyclic) = (0, 0);
+
+        for _ in 0..500 {
+            let len = below(&mut rng, 30);
+            let mut topo_sort = TopoSort::with_capacity(len as usize);
+            for node in 0..len {
+                // Mostly edges to lower nodes (acyclic), with the odd back edge, self reference or missing node
+                let depends: Vec<_> = (0..below(&mut rng, 4))
+                    .map(|_| match below(&mut rng, 20) {
+                        0 => len + below(&mut rng, 5),
+                        1 => node,
+                        2 => below(&mut rng, len),
+                        _ => below(&mut rng, node.max(1)),
+                    })
+                    .collect();
+                topo_sort.insert(node, depends);
+            }
+            topo_sort.debug_check_invariants();
+
+            match topo_sort.try_vec_nodes() {
+                Ok(nodes) => {
+                    acyclic += 1;
+                    assert!(!has_cycle(&topo_sort));
+                    assert_eq!(topo_sort.len(), nodes.len());
+
+                    let position: Map<_, _> = nodes
+                        .iter()
+                        .enumerate()
+                        .map(|(pos, &node)| (node, pos))
+                        .collect();
+                    for (node, depends) in topo_sort.iter().map(Result::unwrap) {
+                        for dep in depends.iter().filter(|&dep| dep != node) {
+                            if let Some(dep_pos) = position.get(dep) {
+                                assert!(dep_pos < &position[node]);
+                            }
+                        }
+                    }
+                }
+                Err(CycleError) => {
+                    cyclic += 1;
+                    assert!(has_cycle(&topo_sort));
+                }
+            }
+        }
+        assert!(acyclic > 0 && cyclic > 0);
+    }
 
-        let v: Vec<Result<_, _>> = topo_sort.nodes().collect();
-        assert_eq!(vec![Ok(&4), Ok(&3), Err(CycleError)], v);
+    #[test]
+    fn test_into_sorted_vec() {
+        thread_local! {
+            static CLONES: Cell<usize> = const { Cell::new(0) };
+        }
+
+        #[derive(Debug, PartialEq, Eq, Hash)]
+        struct Counted(&'static str);
+
+        impl Clone for Counted {
+            fn clone(&self) -> Self {
+                CLONES.with(|clones| clones.set(clones.get() + 1));
+                Counted(self.0)
+            }
+        }
+
+        let mut topo_sort = TopoSort::with_capacity(3);
+        topo_sort.insert(Counted("C"), vec![Counted("B")]);
+        topo_sort.insert(Counted("B"), vec![Counted("A")]);
+        topo_sort.insert(Counted("A"), vec![]);
+        let owned = topo_sort.try_owned_vec_nodes().unwrap();
+        assert!(CLONES.with(Cell::get) > 0);
+
+        CLONES.with(|clones| clones.set(0));
+        assert_eq!(owned, topo_sort.into_sorted_vec().unwrap());
+        assert_eq!(0, CLONES.with(Cell::get));
     }
 
     #[test]
-    fn test_direct_cycle() {
+    fn test_sorted_or_panic() {
         let mut topo_sort = TopoSort::with_capacity(2);
-        topo_sort.insert(1, vec![2]);
-        assert!(!topo_sort.cycle_detected());
-        assert!(!topo_sort.to_owned_vec_nodes().cycle_detected());
-        assert!(!topo_sort.try_vec_nodes().is_err());
-
-        topo_sort.insert(2, vec![1]); // cycle
-        assert!(topo_sort.cycle_detected());
-        assert!(topo_sort.to_owned_vec_nodes().cycle_detected());
-        assert!(topo_sort.try_into_vec_nodes().is_err());
+        topo_sort.insert("B", vec!["A"]);
+        topo_sort.insert("A", vec![]);
+        assert_eq!(vec![&"A", &"B"], topo_sort.sorted_or_panic());
     }
 
     #[test]
-    fn test_indirect_cycle() {
+    #[should_panic(expected = "\"cyclic\"")]
+    fn test_sorted_or_panic_cycle() {
         let mut topo_sort = TopoSort::with_capacity(3);
-        topo_sort.insert(1, vec![2, 3]);
-        topo_sort.insert(2, vec![3]);
-        assert!(!topo_sort.cycle_detected());
-        assert!(!topo_sort.to_owned_vec_nodes().cycle_detected());
-        assert!(!topo_sort.try_vec_nodes().is_err());
-
-        topo_sort.insert(3, vec![1]); // cycle
-        assert!(topo_sort.cycle_detected());
-        assert!(topo_sort.to_owned_vec_nodes().cycle_detected());
-        assert!(topo_sort.try_into_vec_nodes().is_err());
+        topo_sort.insert("A", vec![]);
+        topo_sort.insert("B", vec!["cyclic"]);
+        topo_sort.insert("cyclic", vec!["B"]); // cycle
+        topo_sort.sorted_or_panic();
     }
 
     #[test]
-    fn test_typical() {
-        let mut topo_sort = TopoSort::with_capacity(5);
-        topo_sort.insert("C", vec!["A", "B"]);
-        topo_sort.insert("E", vec!["B", "C"]);
+    fn test_take() {
+        let mut topo_sort = TopoSort::with_capacity(3);
         topo_sort.insert("A", vec![]);
-        topo_sort.insert("D", vec!["A", "C", "E"]);
         topo_sort.insert("B", vec!["A"]);
+        topo_sort.insert("C", vec!["A", "B"]);
 
-        match topo_sort.into_vec_nodes() {
-            SortResults::Full(nodes) => assert_eq!(vec!["A", "B", "C", "E", "D"], nodes),
-            SortResults::Partial(_) => panic!("unexpected cycle!"),
-        }
+        let (node, depends) = topo_sort.take(&"B").unwrap();
+        assert_eq!("B", node);
+        assert_eq!(Set::from_iter(vec!["A"]), depends);
+        assert_eq!(None, topo_sort.take(&"B"));
+        assert_eq!(Set::from_iter(vec!["A"]), topo_sort[&"C"]);
+
+        let mut topo_sort2 = TopoSort::with_capacity(2);
+        topo_sort2.insert("A", vec![]);
+        topo_sort2.insert_from_set(node, depends);
+        assert_eq!(vec!["A", "B"], topo_sort2.try_owned_vec_nodes().unwrap());
     }
 
     #[test]
-    fn test_empty() {
-        let topo_sort: TopoSort<u32> = TopoSort::new();
+    fn test_remove() {
+        let mut topo_sort = TopoSort::with_capacity(3);
+        topo_sort.insert("A", vec![]);
+        topo_sort.insert("B", vec!["A"]);
+        topo_sort.insert("C", vec!["A", "B"]);
+
+        assert_eq!(Some(Set::from_iter(vec!["A"])), topo_sort.remove(&"B"));
+        assert_eq!(None, topo_sort.remove(&"B"));
+        assert_eq!(2, topo_sort.len());
+        assert_eq!(Set::from_iter(vec!["A"]), topo_sort[&"C"]);
+
+        // Re-inserting doesn't bring back the old edge
+        topo_sort.insert("B", vec!["C"]);
         assert_eq!(
-            Vec::new() as Vec<(u32, Set<u32>)>,
-            topo_sort.try_owned_vec().unwrap()
+            vec!["A", "C", "B"],
+            topo_sort.try_owned_vec_nodes().unwrap()
         );
     }
 
     #[test]
-    fn test_with_no_depends() {
-        let mut topo_sort = TopoSort::with_capacity(1);
-        topo_sort.insert("C", vec![]);
+    fn test_extend() {
+        let mut topo_sort = TopoSort::with_capacity(4);
+        topo_sort.insert("A", vec!["D"]);
+        topo_sort.extend(vec![("B", vec!["A"]), ("C", vec!["A", "B"])]);
+        topo_sort.extend(Map::from_iter(vec![("A", Set::new()), ("D", Set::new())]));
+
+        assert_eq!(4, topo_sort.len());
+        assert!(topo_sort[&"A"].is_empty());
+        assert_eq!(Set::from_iter(vec!["A", "B"]), topo_sort[&"C"]);
+        assert!(topo_sort.is_acyclic());
+    }
 
-        assert_eq!(vec!["C"], topo_sort.try_owned_vec_nodes().unwrap());
+    #[test]
+    fn test_from_iter() {
+        let topo_sort: TopoSort<_> = vec![("B", vec!["A"]), ("C", vec!["B"]), ("A", vec![])]
+            .into_iter()
+            .collect();
+        assert_eq!(vec![&"A", &"B", &"C"], topo_sort.try_vec_nodes().unwrap());
+
+        let topo_sort: TopoSort<_> = (1..=3)
+            .map(|node| (node, Set::from_iter(1..node)))
+            .collect();
+        assert_eq!(Set::from_iter(vec![1, 2]), topo_sort[&3]);
+        assert_eq!(vec![1, 2, 3], topo_sort.try_into_vec_nodes().unwrap());
     }
 
     #[test]
-    fn test_with_excess_depends() {
+    fn test_retain() {
         let mut topo_sort = TopoSort::with_capacity(5);
-        topo_sort.insert("C", vec!["F", "A", "B", "F"]); // There is no 'F' - two of them
-        topo_sort.insert("E", vec!["C", "B", "C"]); // Double "C" dependency
-        topo_sort.insert("A", vec!["A", "G"]); // Self dependency + there is no 'G'
-        topo_sort.insert("D", vec!["A", "C", "E"]);
-        topo_sort.insert("B", vec!["B", "A"]); // Self dependency
+        topo_sort.insert("app", vec!["lib", "test-util", "missing"]);
+        topo_sort.insert("lib", vec!["core"]);
+        topo_sort.insert("test-util", vec!["core"]);
+        topo_sort.insert("test-app", vec!["app", "test-util"]);
+        topo_sort.insert("core", vec![]);
+
+        topo_sort.retain(|node, depends| {
+            depends.remove(&"core");
+            !node.starts_with("test")
+        });
+
+        assert_eq!(3, topo_sort.len());
+        // Dangling dependencies that weren't removed by `retain` are left alone
+        assert_eq!(Set::from_iter(vec!["lib", "missing"]), topo_sort[&"app"]);
+        assert!(topo_sort[&"lib"].is_empty());
+        assert_eq!(None, topo_sort.get(&"test-util"));
+        let nodes = topo_sort.try_vec_nodes().unwrap();
+        let pos = |node| nodes.iter().position(|&&n| n == node).unwrap();
+        assert!(pos("lib") < pos("app"));
+    }
+
+    #[test]
+    fn test_remove_dependency() {
+        let mut topo_sort = TopoSort::with_capacity(3);
+        topo_sort.insert("A", vec![]);
+        topo_sort.insert("B", vec!["A", "C"]);
+        topo_sort.insert("C", vec!["B"]); // cycle
+        assert!(topo_sort.try_vec_nodes().is_err());
+
+        assert!(topo_sort.remove_dependency(&"B", &"C"));
+        assert!(!topo_sort.remove_dependency(&"B", &"C"));
+        assert!(!topo_sort.remove_dependency(&"D", &"A"));
+        assert_eq!(3, topo_sort.len());
+        assert_eq!(Set::from_iter(vec!["A"]), topo_sort[&"B"]);
+        assert_eq!(vec![&"A", &"B", &"C"], topo_sort.try_vec_nodes().unwrap());
+    }
+
+    #[test]
+    fn test_remove_unchecked() {
+        let mut topo_sort = TopoSort::with_capacity(3);
+        topo_sort.insert("A", vec![]);
+        topo_sort.insert("B", vec!["A"]);
+        topo_sort.insert("leaf", vec!["B"]);
 
         assert_eq!(
-            vec!["A", "B", "C", "E", "D"],
-            topo_sort.try_owned_vec_nodes().unwrap()
+            Some(Set::from_iter(vec!["B"])),
+            topo_sort.remove_unchecked(&"leaf")
         );
+        assert_eq!(None, topo_sort.remove_unchecked(&"leaf"));
+        assert_eq!(vec![&"A", &"B"], topo_sort.try_vec_nodes().unwrap());
+
+        // Removing a node others depend on leaves their edge in place (no scrub)
+        assert_eq!(Some(Set::new()), topo_sort.remove_unchecked(&"A"));
+        assert_eq!(Set::from_iter(vec!["A"]), topo_sort[&"B"]);
+        assert_eq!(vec![&"B"], topo_sort.try_vec_nodes().unwrap());
     }
 
     #[test]
-    fn test_iter() {
+    fn test_remove_tracked() {
         let mut topo_sort = TopoSort::with_capacity(5);
         topo_sort.insert("C", vec!["A", "B"]);
         topo_sort.insert("E", vec!["B", "C"]);
@@ -802,28 +3695,60 @@ mod tests {
         topo_sort.insert("D", vec!["A", "C", "E"]);
         topo_sort.insert("B", vec!["A"]);
 
-        let mut nodes = Vec::with_capacity(5);
-        for node in &topo_sort {
-            match node {
-                Ok(node) => nodes.push(node),
-                Err(_) => panic!("Unexpected cycle!"),
-            }
-        }
+        let (depends, mut dependents) = topo_sort.remove_tracked(&"C").unwrap();
+        dependents.sort_unstable();
+        assert_eq!(Set::from_iter(vec!["A", "B"]), depends);
+        assert_eq!(vec!["D", "E"], dependents);
 
+        assert_eq!(4, topo_sort.len());
+        assert_eq!(Set::from_iter(vec!["B"]), topo_sort[&"E"]);
+        assert_eq!(Set::from_iter(vec!["A", "E"]), topo_sort[&"D"]);
+        assert_eq!(None, topo_sort.remove_tracked(&"C"));
         assert_eq!(
-            vec![
-                (&"A", &Set::new()),
-                (&"B", &Set::from_iter(vec!["A"])),
-                (&"C", &Set::from_iter(vec!["A", "B"])),
-                (&"E", &Set::from_iter(vec!["B", "C"])),
-                (&"D", &Set::from_iter(vec!["A", "C", "E"])),
-            ],
-            nodes
+            vec!["A", "B", "E", "D"],
+            topo_sort.try_owned_vec_nodes().unwrap()
         );
     }
 
     #[test]
-    fn test_into_iter() {
+    fn test_shrink_dependency_sets() {
+        let mut topo_sort = TopoSort::with_capacity(100);
+        topo_sort.add_dependencies(0, 1..100);
+        for node in 1..100 {
+            topo_sort.insert(node, vec![]);
+        }
+        let set_capacity = topo_sort[&0].capacity();
+
+        // Churn: every remaining node ends up with an oversized dependency set
+        for node in 0..10 {
+            topo_sort.insert_from_set(node, Set::with_capacity(set_capacity));
+        }
+        topo_sort.add_dependencies(0, vec![1, 2]);
+        topo_sort.add_dependencies(3, vec![1]);
+        for node in 10..100 {
+            topo_sort.remove_tracked(&node);
+        }
+        let capacities: Map<_, _> = (0..10)
+            .map(|node| (node, topo_sort[&node].capacity()))
+            .collect();
+        assert!(capacities
+            .values()
+            .all(|&capacity| capacity >= set_capacity));
+
+        topo_sort.shrink_dependency_sets();
+        for (node, capacity) in capacities {
+            let depends = &topo_sort[&node];
+            assert!(depends.capacity() < capacity);
+            assert!(depends.capacity() >= depends.len());
+        }
+        assert!(topo_sort.capacity() < 100);
+        assert_eq!(Set::from_iter(vec![1, 2]), topo_sort[&0]);
+        assert_eq!(Set::from_iter(vec![1]), topo_sort[&3]);
+        assert_eq!(10, topo_sort.len());
+    }
+
+    #[test]
+    fn test_sort_to_channel() {
         let mut topo_sort = TopoSort::with_capacity(5);
         topo_sort.insert("C", vec!["A", "B"]);
         topo_sort.insert("E", vec!["B", "C"]);
@@ -831,23 +3756,45 @@ mod tests {
         topo_sort.insert("D", vec!["A", "C", "E"]);
         topo_sort.insert("B", vec!["A"]);
 
-        let mut nodes = Vec::with_capacity(5);
-        for node in topo_sort {
-            match node {
-                Ok(node) => nodes.push(node),
-                Err(_) => panic!("Unexpected cycle!"),
-            }
-        }
+        let (tx, rx) = mpsc::channel();
+        let nodes = thread::scope(|scope| {
+            let consumer = scope.spawn(move || rx.iter().collect::<Vec<_>>());
+            assert_eq!(Ok(()), topo_sort.sort_to_channel(tx));
+            consumer.join().unwrap()
+        });
+        assert_eq!(vec![&"A", &"B", &"C", &"E", &"D"], nodes);
+
+        let (tx, rx) = mpsc::channel();
+        assert_eq!(Ok(()), topo_sort.sort_owned_to_channel(tx));
+        assert_eq!(vec!["A", "B", "C", "E", "D"], rx.iter().collect::<Vec<_>>());
+
+        topo_sort.insert("A", vec!["D"]); // cycle
+        let (tx, rx) = mpsc::channel();
+        assert_eq!(Err(CycleError), topo_sort.sort_to_channel(tx));
+        assert_eq!(None, rx.iter().next());
+    }
 
+    #[test]
+    fn test_clone_from() {
+        let mut source = TopoSort::with_capacity(3);
+        source.insert("C", vec!["A", "B"]);
+        source.insert("B", vec!["A"]);
+        source.insert("A", vec![]);
+
+        let mut dest = TopoSort::with_capacity(100);
+        dest.insert("X", vec!["Y"]);
+        let capacity = dest.capacity();
+
+        dest.clone_from(&source);
+        assert_eq!(source.len(), dest.len());
+        for (node, depends) in source.iter().map(Result::unwrap) {
+            assert_eq!(depends, &dest[node]);
+        }
+        assert_eq!(None, dest.get(&"X"));
+        assert!(dest.capacity() >= capacity);
         assert_eq!(
-            vec![
-                ("A", Set::new()),
-                ("B", Set::from_iter(vec!["A"])),
-                ("C", Set::from_iter(vec!["A", "B"])),
-                ("E", Set::from_iter(vec!["B", "C"])),
-                ("D", Set::from_iter(vec!["A", "C", "E"])),
-            ],
-            nodes
+            source.try_vec_nodes().unwrap(),
+            dest.try_vec_nodes().unwrap()
         );
     }
 
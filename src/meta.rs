@@ -0,0 +1,143 @@
+use std::hash::Hash;
+
+use crate::graph::Graph;
+use crate::{CycleError, Map, Set};
+
+// *** TopoSortWith ***
+
+/// A variant of `TopoSort` that stores metadata of type `M` with every node, avoiding the need for a parallel map
+/// from nodes to their data. Metadata is ignored by the sort, but is returned alongside each sorted node.
+#[derive(Clone)]
+pub struct TopoSortWith<T, M>
+where
+    T: Eq + Hash,
+{
+    // Dependent -> (Dependencies, Metadata)
+    node_depends: Map<T, (Set<T>, M)>,
+}
+
+impl<T, M> TopoSortWith<T, M>
+where
+    T: Eq + Hash,
+{
+    /// Initialize a new struct with zero capacity. It will not allocate until the first insertion
+    #[inline]
+    pub fn new() -> Self {
+        TopoSortWith {
+            node_depends: Map::new(),
+        }
+    }
+
+    /// Initialize an empty struct with a given capacity
+    #[inline]
+    pub fn with_capacity(capacity: usize) -> Self {
+        TopoSortWith {
+            node_depends: Map::with_capacity(capacity),
+        }
+    }
+
+    /// Insert into this struct with the given node, an iterator of its dependencies and its metadata. If the node
+    /// was already inserted, both its dependencies and metadata are overwritten
+    #[inline]
+    pub fn insert_with_meta<I: IntoIterator<Item = T>>(&mut self, node: T, i: I, meta: M) {
+        self.node_depends
+            .insert(node, (i.into_iter().collect(), meta));
+    }
+
+    /// Returns the metadata of a node, if found, else None
+    #[inline]
+    pub fn meta(&self, node: &T) -> Option<&M> {
+        self.node_depends.get(node).map(|(_, meta)| meta)
+    }
+
+    /// Returns a mutable reference to the metadata of a node, if found, else None
+    #[inline]
+    pub fn meta_mut(&mut self, node: &T) -> Option<&mut M> {
+        self.node_depends.get_mut(node).map(|(_, meta)| meta)
+    }
+
+    /// Returns the dependency set of a node (as inserted), if found, else None
+    #[inline]
+    pub fn get(&self, node: &T) -> Option<&Set<T>> {
+        self.node_depends.get(node).map(|(depends, _)| depends)
+    }
+
+    /// Start the sort process and return an iterator of the results and their metadata. If a cycle is detected,
+    /// the nodes sorted before it are yielded, followed by an error.
+    pub fn iter(&self) -> impl Iterator<Item = Result<(&T, &M), CycleError>> + '_ {
+        let graph = self.graph();
+        let order = graph.partial_sort_by_key(|node| node);
+        let err = (order.len() < graph.len()).then_some(Err(CycleError));
+
+        let metas: Vec<_> = self.node_depends.values().map(|(_, meta)| meta).collect();
+        order
+            .into_iter()
+            .map(move |node| Ok((graph.nodes[node], metas[node])))
+            .chain(err)
+    }
+
+    /// Sort and return a vector (with borrowed nodes) of the results and their metadata. If a cycle is detected,
+    /// an error is returned instead
+    #[inline]
+    pub fn try_vec(&self) -> Result<Vec<(&T, &M)>, CycleError> {
+        self.iter().collect()
+    }
+
+    /// Returns true if there aren't any nodes added otherwise false
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.node_depends.is_empty()
+    }
+
+    /// Returns the number of nodes added to the collection
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.node_depends.len()
+    }
+
+    fn graph(&self) -> Graph<'_, T> {
+        let depends: Vec<_> = self
+            .node_depends
+            .values()
+            .map(|(depends, _)| depends)
+            .collect();
+        Graph::from_fn(self.node_depends.keys().collect(), |node| depends[node])
+    }
+}
+
+impl<T, M> Default for TopoSortWith<T, M>
+where
+    T: Eq + Hash,
+{
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// *** Tests ***
+
+#[cfg(test)]
+mod tests {
+    use crate::{CycleError, TopoSortWith};
+
+    #[test]
+    fn test_topo_sort_with() {
+        let mut topo_sort = TopoSortWith::with_capacity(3);
+        topo_sort.insert_with_meta("app", vec!["lib"], "bin");
+        topo_sort.insert_with_meta("lib", vec!["core"], "rlib");
+        topo_sort.insert_with_meta("core", vec![], "rlib");
+
+        assert_eq!(Some(&"bin"), topo_sort.meta(&"app"));
+        assert_eq!(None, topo_sort.meta(&"docs"));
+        *topo_sort.meta_mut(&"core").unwrap() = "no_std";
+
+        assert_eq!(
+            vec![(&"core", &"no_std"), (&"lib", &"rlib"), (&"app", &"bin")],
+            topo_sort.try_vec().unwrap()
+        );
+
+        topo_sort.insert_with_meta("core", vec!["app"], "cycle"); // cycle
+        assert_eq!(Err(CycleError), topo_sort.try_vec());
+    }
+}
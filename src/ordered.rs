@@ -0,0 +1,195 @@
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, BTreeSet, BinaryHeap};
+
+use crate::CycleError;
+
+// *** OrdTopoSort ***
+
+/// A variant of `TopoSort` backed by a `BTreeMap` that only requires `Ord` (no `Hash`) on nodes. Iteration is
+/// fully deterministic: whenever more than one node is ready, the smallest is emitted first, which yields the
+/// lexicographically smallest topological order.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct OrdTopoSort<T>
+where
+    T: Ord,
+{
+    // Dependent -> Dependencies
+    node_depends: BTreeMap<T, BTreeSet<T>>,
+}
+
+impl<T> OrdTopoSort<T>
+where
+    T: Ord,
+{
+    /// Initialize a new struct. It will not allocate until the first insertion
+    #[inline]
+    pub fn new() -> Self {
+        OrdTopoSort {
+            node_depends: BTreeMap::new(),
+        }
+    }
+
+    /// Initialize a new struct from a map. The key represents the node to be sorted and the set is its dependencies
+    #[inline]
+    pub fn from_map(nodes: BTreeMap<T, BTreeSet<T>>) -> Self {
+        OrdTopoSort {
+            node_depends: nodes,
+        }
+    }
+
+    /// Insert into this struct with the given node and an iterator of its dependencies. If the node was
+    /// already inserted, its dependencies are overwritten (use `add_dependencies` to merge them instead)
+    #[inline]
+    pub fn insert<I: IntoIterator<Item = T>>(&mut self, node: T, i: I) {
+        self.node_depends.insert(node, i.into_iter().collect());
+    }
+
+    /// Add the given dependencies to those already present for the node, inserting the node if it doesn't exist
+    #[inline]
+    pub fn add_dependencies<I: IntoIterator<Item = T>>(&mut self, node: T, i: I) {
+        self.node_depends.entry(node).or_default().extend(i);
+    }
+
+    /// Remove a node, returning the owned node and its dependency set (as inserted), if found, else None. The
+    /// removed node is also scrubbed from the dependency sets of all remaining nodes.
+    pub fn take(&mut self, node: &T) -> Option<(T, BTreeSet<T>)> {
+        let entry = self.node_depends.remove_entry(node)?;
+        for depends in self.node_depends.values_mut() {
+            depends.remove(node);
+        }
+        Some(entry)
+    }
+
+    /// Returns the dependency set of a node (as inserted), if found, else None
+    #[inline]
+    pub fn get(&self, node: &T) -> Option<&BTreeSet<T>> {
+        self.node_depends.get(node)
+    }
+
+    /// Start the sort process and return an iterator of the results, emitting the smallest ready node first. If a
+    /// cycle is detected, an error is yielded last.
+    pub fn nodes(&self) -> impl Iterator<Item = Result<&T, CycleError>> + '_ {
+        // Keys are sorted, so a binary search maps a node to its index (and index order is node order)
+        let nodes: Vec<_> = self.node_depends.keys().collect();
+        let mut dependents = vec![Vec::new(); nodes.len()];
+        let mut edges = vec![0; nodes.len()];
+
+        for (dependent, depends) in self.node_depends.values().enumerate() {
+            for dependency in depends {
+                // Filter any self references and dependencies that aren't nodes themselves
+                match nodes.binary_search(&dependency) {
+                    Ok(dependency) if dependency != dependent => {
+                        dependents[dependency].push(dependent);
+                        edges[dependent] += 1;
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        let mut ready: BinaryHeap<_> = (0..nodes.len())
+            .filter(|&node| edges[node] == 0)
+            .map(Reverse)
+            .collect();
+        let mut remaining = nodes.len();
+
+        std::iter::from_fn(move || match ready.pop() {
+            Some(Reverse(node)) => {
+                remaining -= 1;
+                for &dependent in &dependents[node] {
+                    edges[dependent] -= 1;
+                    if edges[dependent] == 0 {
+                        ready.push(Reverse(dependent));
+                    }
+                }
+                Some(Ok(nodes[node]))
+            }
+            None if remaining == 0 => None,
+            None => {
+                remaining = 0;
+                Some(Err(CycleError))
+            }
+        })
+    }
+
+    /// Sort and return a vector (with borrowed nodes) of the results. If a cycle is detected, an error is
+    /// returned instead
+    #[inline]
+    pub fn try_vec_nodes(&self) -> Result<Vec<&T>, CycleError> {
+        self.nodes().collect()
+    }
+
+    /// Reclaim ownership of the inner map
+    #[inline]
+    pub fn into_inner(self) -> BTreeMap<T, BTreeSet<T>> {
+        self.node_depends
+    }
+
+    /// Returns true if there aren't any nodes added otherwise false
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.node_depends.is_empty()
+    }
+
+    /// Returns the number of nodes added to the collection
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.node_depends.len()
+    }
+}
+
+// *** Tests ***
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+
+    use crate::{CycleError, OrdTopoSort};
+
+    // Only implements `Ord`, not `Hash`
+    #[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+    struct Version(u32, u32);
+
+    #[test]
+    fn test_ord_topo_sort() {
+        let mut topo_sort = OrdTopoSort::new();
+        topo_sort.insert(Version(2, 0), vec![Version(1, 1)]);
+        topo_sort.insert(Version(1, 1), vec![Version(1, 0), Version(1, 1)]); // Self reference is ignored
+        topo_sort.insert(Version(1, 0), vec![]);
+        topo_sort.insert(Version(0, 9), vec![Version(0, 1)]); // Missing node is ignored
+        topo_sort.add_dependencies(Version(1, 0), vec![Version(0, 9)]);
+
+        assert_eq!(4, topo_sort.len());
+        assert_eq!(
+            vec![
+                &Version(0, 9),
+                &Version(1, 0),
+                &Version(1, 1),
+                &Version(2, 0)
+            ],
+            topo_sort.try_vec_nodes().unwrap()
+        );
+
+        topo_sort.insert(Version(0, 9), vec![Version(2, 0)]); // cycle
+        assert_eq!(Err(CycleError), topo_sort.try_vec_nodes());
+
+        let (node, depends) = topo_sort.take(&Version(2, 0)).unwrap();
+        assert_eq!(Version(2, 0), node);
+        assert_eq!(BTreeSet::from([Version(1, 1)]), depends);
+        assert!(topo_sort.get(&Version(0, 9)).unwrap().is_empty());
+        assert_eq!(3, topo_sort.try_vec_nodes().unwrap().len());
+    }
+
+    #[test]
+    fn test_ord_topo_sort_smallest_first() {
+        let mut topo_sort = OrdTopoSort::new();
+        topo_sort.insert("d", vec!["b"]);
+        topo_sort.insert("c", vec![]);
+        topo_sort.insert("b", vec![]);
+        topo_sort.insert("a", vec!["c"]);
+
+        let nodes: Vec<_> = topo_sort.nodes().map(Result::unwrap).collect();
+        assert_eq!(vec![&"b", &"c", &"a", &"d"], nodes);
+        assert_eq!(nodes, topo_sort.clone().try_vec_nodes().unwrap());
+    }
+}
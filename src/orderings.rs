@@ -0,0 +1,410 @@
+use std::hash::Hash;
+
+use crate::graph::Graph;
+#[cfg(feature = "rand")]
+use crate::Set;
+use crate::{CycleError, Map, TopoSort};
+
+// The most steps `random_order` takes, so that large graphs still finish quickly
+#[cfg(feature = "rand")]
+const RANDOM_ORDER_MAX_STEPS: usize = 10_000_000;
+
+// *** Orderings ***
+
+impl<T> TopoSort<T>
+where
+    T: Eq + Hash,
+{
+    /// Count the number of distinct valid topological orderings of the graph. If a cycle is detected, an error
+    /// is returned instead.
+    ///
+    /// NOTE: This is exponential in the worst case (it is a dynamic program over every possible set of already
+    /// emitted nodes), so it is only practical for small or narrow graphs. The count saturates at `u128::MAX` if the
+    /// real count doesn't fit (see `order_count` to tell the two apart).
+    #[inline]
+    pub fn count_orderings(&self) -> Result<u128, CycleError> {
+        // Every partial count only ever grows, so an overflow anywhere means the total doesn't fit either
+        Ok(self.order_count()?.unwrap_or(u128::MAX))
+    }
+
+    /// Count the number of distinct valid topological orderings of the graph, which measures how constrained it
+    /// is (a single ordering means every node is fully constrained). Unlike `count_orderings`, the count is exact
+    /// or None, which is only returned if it doesn't fit in a `u128`. If a cycle is detected, an error is returned
+    /// instead.
+    ///
+    /// NOTE: This has the same exponential worst case cost as `count_orderings`
+    pub fn order_count(&self) -> Result<Option<u128>, CycleError> {
+        let graph = Graph::new(&self.node_depends);
+        if !graph.is_acyclic() {
+            return Err(CycleError);
+        }
+
+        // Each set of nodes is a bitset with one bit per node
+        let words = graph.len().div_ceil(64);
+        let depends: Vec<Vec<u64>> = graph
+            .depends
+            .iter()
+            .map(|depends| {
+                let mut mask = vec![0; words];
+                for &node in depends {
+                    mask[node / 64] |= 1 << (node % 64);
+                }
+                mask
+            })
+            .collect();
+
+        // Emitted set -> # of orderings that emit exactly that set first
+        let mut emitted: Map<Vec<u64>, u128> = Map::with_capacity(1);
+        emitted.insert(vec![0; words], 1);
+
+        for _ in 0..graph.len() {
+            let mut next = Map::with_capacity(emitted.len());
+            for (mask, &count) in &emitted {
+                for (node, depends) in depends.iter().enumerate() {
+                    let (word, bit) = (node / 64, 1 << (node % 64));
+                    let ready = depends
+                        .iter()
+                        .zip(mask)
+                        .all(|(&depends, &mask)| depends & !mask == 0);
+                    if mask[word] & bit == 0 && ready {
+                        let mut mask = mask.clone();
+                        mask[word] |= bit;
+                        let total = next.entry(mask).or_insert(0u128);
+                        *total = match total.checked_add(count) {
+                            Some(total) => total,
+                            None => return Ok(None),
+                        };
+                    }
+                }
+            }
+            emitted = next;
+        }
+
+        Ok(emitted
+            .values()
+            .try_fold(0u128, |total, &count| total.checked_add(count)))
+    }
+
+    /// Returns a lazy iterator over every distinct valid topological ordering of the graph, in lexicographic
+    /// order of node positions (as iterated). If a cycle is detected, an error is returned instead.
+    ///
+    /// NOTE: The number of orderings grows factorially with the number of independent nodes (see
+    /// `count_orderings`), so this is only practical for exhaustively testing small graphs. Each ordering is found
+    /// by backtracking, so the time between orderings is bounded regardless.
+    pub fn all_orders(&self) -> Result<impl Iterator<Item = Vec<&T>> + '_, CycleError> {
+        let graph = Graph::new(&self.node_depends);
+        if !graph.is_acyclic() {
+            return Err(CycleError);
+        }
+
+        Ok(AllOrders {
+            edges: graph.edge_counts(),
+            emitted: vec![false; graph.len()],
+            path: Vec::with_capacity(graph.len()),
+            cursor: vec![0; graph.len() + 1],
+            started: false,
+            graph,
+        })
+    }
+}
+
+// *** Random Orders ***
+
+#[cfg(feature = "rand")]
+impl<T> TopoSort<T>
+where
+    T: Eq + Hash,
+{
+    /// Sample an approximately uniform random valid topological ordering of the graph. If a cycle is detected,
+    /// an error is returned instead. This is useful for fuzzing code that should not depend on the order of
+    /// independent nodes.
+    ///
+    /// NOTE: Exact uniform sampling is as hard as counting the orderings, so this runs the Markov chain of Bubley
+    /// and Dyer instead: starting from any ordering, randomly swap adjacent independent nodes. It takes roughly
+    /// `n^3 log n` steps for `n` nodes to become (very close to) uniform, but this is capped at 10 million steps,
+    /// so above roughly 150 nodes the result is increasingly biased towards the deterministic starting order. Use
+    /// `random_order_with_steps` to choose the number of steps instead.
+    pub fn random_order<R: rand::Rng + ?Sized>(&self, rng: &mut R) -> Result<Vec<&T>, CycleError> {
+        let len = self.node_depends.len();
+        let steps = len
+            .saturating_pow(3)
+            .saturating_mul(len.checked_ilog2().unwrap_or(0) as usize + 1)
+            .min(RANDOM_ORDER_MAX_STEPS);
+        self.random_order_with_steps(rng, steps)
+    }
+
+    /// Sample a random valid topological ordering of the graph by running exactly `steps` steps of the Markov
+    /// chain used by `random_order`. If a cycle is detected, an error is returned instead.
+    ///
+    /// NOTE: The result is only close to uniform once `steps` is in the order of `n^3 log n` for `n` nodes. Fewer
+    /// steps trade accuracy for time, and zero steps returns the same ordering every time.
+    pub fn random_order_with_steps<R: rand::Rng + ?Sized>(
+        &self,
+        rng: &mut R,
+        steps: usize,
+    ) -> Result<Vec<&T>, CycleError> {
+        use rand::RngExt;
+
+        let graph = Graph::new(&self.node_depends);
+        let mut order = graph.sort_by_key(|node| node)?;
+        let len = order.len();
+        if len < 2 {
+            return Ok(graph.to_nodes(order));
+        }
+
+        let edges: Set<_> = graph
+            .depends
+            .iter()
+            .enumerate()
+            .flat_map(|(node, depends)| depends.iter().map(move |&dep| (node, dep)))
+            .collect();
+
+        for _ in 0..steps {
+            // Lazy chain: only attempt a swap half of the time. Since adjacent nodes have nothing in between, they
+            // are independent unless there is a direct edge.
+            let pos = rng.random_range(0..len - 1);
+            if rng.random_bool(0.5) && !edges.contains(&(order[pos + 1], order[pos])) {
+                order.swap(pos, pos + 1);
+            }
+        }
+        Ok(graph.to_nodes(order))
+    }
+}
+
+// *** AllOrders ***
+
+// Backtracking enumeration of all topological orderings
+struct AllOrders<'d, T> {
+    graph: Graph<'d, T>,
+    // Remaining dependencies of each node
+    edges: Vec<usize>,
+    emitted: Vec<bool>,
+    path: Vec<usize>,
+    // Depth -> Next node to try at that depth
+    cursor: Vec<usize>,
+    started: bool,
+}
+
+impl<'d, T> Iterator for AllOrders<'d, T>
+where
+    T: Eq + Hash,
+{
+    type Item = Vec<&'d T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        // The empty graph has exactly one (empty) ordering
+        if !self.started {
+            self.started = true;
+            if self.graph.len() == 0 {
+                return Some(Vec::new());
+            }
+        }
+
+        loop {
+            let depth = self.path.len();
+            let next = (self.cursor[depth]..self.graph.len())
+                .find(|&node| !self.emitted[node] && self.edges[node] == 0);
+
+            match next {
+                Some(node) => {
+                    self.cursor[depth] = node + 1;
+                    self.cursor[depth + 1] = 0;
+                    self.emitted[node] = true;
+                    for &dependent in &self.graph.dependents[node] {
+                        self.edges[dependent] -= 1;
+                    }
+                    self.path.push(node);
+
+                    if self.path.len() == self.graph.len() {
+                        return Some(
+                            self.path
+                                .iter()
+                                .map(|&node| self.graph.nodes[node])
+                                .collect(),
+                        );
+                    }
+                }
+                None => {
+                    // Backtrack (the cursor of the previous depth already points past this node)
+                    let node = self.path.pop()?;
+                    self.emitted[node] = false;
+                    for &dependent in &self.graph.dependents[node] {
+                        self.edges[dependent] += 1;
+                    }
+                }
+            }
+        }
+    }
+}
+
+// *** Tests ***
+
+#[cfg(test)]
+mod tests {
+    #[cfg(feature = "rand")]
+    use crate::Map;
+    use crate::{CycleError, Set, TopoSort};
+
+    #[test]
+    fn test_all_orders() {
+        let mut topo_sort = TopoSort::with_capacity(4);
+        topo_sort.insert("A", vec![]);
+        topo_sort.insert("B", vec!["A"]);
+        topo_sort.insert("C", vec!["A"]);
+        topo_sort.insert("D", vec!["B", "C"]);
+
+        let mut orders: Vec<_> = topo_sort.all_orders().unwrap().collect();
+        orders.sort_unstable();
+        assert_eq!(
+            vec![vec![&"A", &"B", &"C", &"D"], vec![&"A", &"C", &"B", &"D"]],
+            orders
+        );
+
+        // Matches the count, and every ordering is distinct
+        topo_sort.insert("E", vec![]);
+        topo_sort.insert("F", vec!["E"]);
+        let orders: Set<_> = topo_sort.all_orders().unwrap().collect();
+        assert_eq!(topo_sort.count_orderings().unwrap(), orders.len() as u128);
+
+        assert_eq!(
+            vec![Vec::<&i32>::new()],
+            TopoSort::<i32>::new()
+                .all_orders()
+                .unwrap()
+                .collect::<Vec<_>>()
+        );
+
+        topo_sort.insert("A", vec!["D"]); // cycle
+        assert!(matches!(topo_sort.all_orders(), Err(CycleError)));
+    }
+
+    #[cfg(feature = "rand")]
+    #[test]
+    fn test_random_order() {
+        use rand::SeedableRng;
+
+        let mut topo_sort = TopoSort::with_capacity(4);
+        topo_sort.insert("A", vec![]);
+        topo_sort.insert("B", vec!["A"]);
+        topo_sort.insert("C", vec!["A"]);
+        topo_sort.insert("D", vec![]);
+
+        let mut rng = rand_xorshift::XorShiftRng::seed_from_u64(7);
+        let all: Set<_> = topo_sort.all_orders().unwrap().collect();
+        let mut seen = Map::new();
+        for _ in 0..2000 {
+            let order = topo_sort.random_order(&mut rng).unwrap();
+            assert!(all.contains(&order));
+            *seen.entry(order).or_insert(0) += 1;
+        }
+
+        // Every ordering is sampled, and roughly uniformly (each is expected 250 times)
+        assert_eq!(all.len(), seen.len());
+        assert!(seen.values().all(|&count| (150..350).contains(&count)));
+
+        topo_sort.insert("A", vec!["B"]); // cycle
+        assert_eq!(Err(CycleError), topo_sort.random_order(&mut rng));
+    }
+
+    #[cfg(feature = "rand")]
+    #[test]
+    fn test_random_order_with_steps() {
+        use rand::SeedableRng;
+
+        let mut topo_sort = TopoSort::with_capacity(4);
+        topo_sort.insert("A", vec![]);
+        topo_sort.insert("B", vec!["A"]);
+        topo_sort.insert("C", vec!["A"]);
+        topo_sort.insert("D", vec![]);
+
+        // No steps leaves the starting order untouched
+        let mut rng = rand_xorshift::XorShiftRng::seed_from_u64(7);
+        let first = topo_sort.random_order_with_steps(&mut rng, 0).unwrap();
+        for _ in 0..10 {
+            assert_eq!(
+                first,
+                topo_sort.random_order_with_steps(&mut rng, 0).unwrap()
+            );
+        }
+        let all: Set<_> = topo_sort.all_orders().unwrap().collect();
+        assert!(all.contains(&topo_sort.random_order_with_steps(&mut rng, 50).unwrap()));
+
+        // Large graphs are capped rather than running n^3 log n steps
+        let mut topo_sort = TopoSort::with_capacity(2000);
+        for node in 0..2000 {
+            topo_sort.insert(node, vec![]);
+        }
+        assert_eq!(2000, topo_sort.random_order(&mut rng).unwrap().len());
+    }
+
+    #[test]
+    fn test_order_count() {
+        let mut topo_sort = TopoSort::with_capacity(4);
+        topo_sort.insert("A", vec![]);
+        topo_sort.insert("B", vec!["A"]);
+        topo_sort.insert("C", vec!["A"]);
+        topo_sort.insert("D", vec![]);
+        assert_eq!(Ok(Some(8)), topo_sort.order_count());
+
+        let mut topo_sort = TopoSort::with_capacity(12);
+        for node in 0..12 {
+            topo_sort.insert(node, vec![]);
+        }
+        assert_eq!(
+            Ok(Some((1..=12).product::<u128>())),
+            topo_sort.order_count()
+        );
+
+        // Three independent chains of 40 nodes have 120! / (40!)^3 (over 10^54) orderings
+        let mut topo_sort = TopoSort::with_capacity(120);
+        for node in 0..120 {
+            let depends = if node % 40 == 0 { None } else { Some(node - 1) };
+            topo_sort.insert(node, depends);
+        }
+        assert_eq!(Ok(None), topo_sort.order_count());
+        assert_eq!(Ok(u128::MAX), topo_sort.count_orderings());
+
+        // Over 128 nodes is fine as long as the count fits
+        let mut topo_sort = TopoSort::with_capacity(129);
+        for node in 0..129_u32 {
+            topo_sort.insert(node, node.checked_sub(1));
+        }
+        assert_eq!(Ok(1), topo_sort.count_orderings());
+        for node in 129..200 {
+            topo_sort.insert(node, Some(node - 1));
+        }
+        assert_eq!(Ok(Some(1)), topo_sort.order_count());
+
+        topo_sort.insert(0, vec![1]);
+        topo_sort.insert(1, vec![0]); // cycle
+        assert_eq!(Err(CycleError), topo_sort.order_count());
+    }
+
+    #[test]
+    fn test_count_orderings() {
+        // Diamond
+        let mut topo_sort = TopoSort::with_capacity(4);
+        topo_sort.insert("A", vec![]);
+        topo_sort.insert("B", vec!["A"]);
+        topo_sort.insert("C", vec!["A"]);
+        topo_sort.insert("D", vec!["B", "C"]);
+        assert_eq!(Ok(2), topo_sort.count_orderings());
+
+        // Chain
+        topo_sort.insert("C", vec!["B"]);
+        assert_eq!(Ok(1), topo_sort.count_orderings());
+
+        // Fully independent
+        let mut topo_sort = TopoSort::with_capacity(5);
+        for node in 0..5 {
+            topo_sort.insert(node, vec![]);
+        }
+        assert_eq!(Ok(120), topo_sort.count_orderings());
+
+        assert_eq!(Ok(1), TopoSort::<u32>::new().count_orderings());
+
+        topo_sort.insert(0, vec![4]);
+        topo_sort.insert(4, vec![0]); // cycle
+        assert_eq!(Err(CycleError), topo_sort.count_orderings());
+    }
+}
@@ -0,0 +1,81 @@
+use std::hash::Hash;
+
+use rayon::prelude::*;
+
+use crate::{CycleError, TopoSort};
+
+// *** Parallel ***
+
+impl<T> TopoSort<T>
+where
+    T: Eq + Hash + Sync,
+{
+    /// Sort and return an iterator of batches, where each batch is a parallel iterator over nodes whose
+    /// dependencies were all yielded in earlier batches (see `layers`). Nodes within a batch can be processed
+    /// in parallel, while dependency order is still respected between batches. If a cycle is detected, an
+    /// error is yielded last.
+    pub fn par_iter(
+        &self,
+    ) -> impl Iterator<Item = Result<rayon::vec::IntoIter<&T>, CycleError>> + '_ {
+        self.layers()
+            .map(|layer| layer.map(IntoParallelIterator::into_par_iter))
+    }
+
+    /// Sort and call `f` with every node, calling it in parallel for nodes within the same batch (see
+    /// `par_iter`). Every call for a batch returns before the next batch is started. If a cycle is detected, `f`
+    /// has been called for all nodes sorted before it and an error is returned.
+    pub fn par_for_each<F>(&self, f: F) -> Result<(), CycleError>
+    where
+        F: Fn(&T) + Sync + Send,
+    {
+        for batch in self.par_iter() {
+            batch?.for_each(&f);
+        }
+        Ok(())
+    }
+}
+
+// *** Tests ***
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use rayon::prelude::*;
+
+    use crate::{CycleError, TopoSort};
+
+    #[test]
+    fn test_par_iter() {
+        let mut topo_sort = TopoSort::with_capacity(5);
+        topo_sort.insert("C", vec!["A", "B"]);
+        topo_sort.insert("E", vec!["B", "C"]);
+        topo_sort.insert("A", vec![]);
+        topo_sort.insert("D", vec!["A", "C"]);
+        topo_sort.insert("B", vec!["A"]);
+
+        let batches: Vec<Vec<_>> = topo_sort
+            .par_iter()
+            .map(|batch| {
+                let mut batch: Vec<_> = batch.unwrap().copied().collect();
+                batch.sort_unstable();
+                batch
+            })
+            .collect();
+        assert_eq!(
+            vec![vec!["A"], vec!["B"], vec!["C"], vec!["D", "E"]],
+            batches
+        );
+
+        let seen = Mutex::new(Vec::new());
+        topo_sort
+            .par_for_each(|&node| seen.lock().unwrap().push(node))
+            .unwrap();
+        let seen = seen.into_inner().unwrap();
+        assert_eq!(5, seen.len());
+        assert_eq!(vec!["A", "B", "C"], seen[..3]);
+
+        topo_sort.insert("A", vec!["E"]); // cycle
+        assert_eq!(Err(CycleError), topo_sort.par_for_each(|_| {}));
+    }
+}
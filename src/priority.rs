@@ -0,0 +1,107 @@
+use std::hash::Hash;
+
+use crate::graph::{Graph, Kahn};
+use crate::{CycleError, TopoSort};
+
+// *** Priorities ***
+
+impl<T> TopoSort<T>
+where
+    T: Eq + Hash,
+{
+    /// Insert into this struct with the given node, an iterator of its dependencies and a priority (the default
+    /// priority is zero). If the node was already inserted, its dependencies and priority are overwritten. Only
+    /// `nodes_by_priority` uses priorities.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a maximum degree was set (see `with_max_degree`) and the dependencies exceed it
+    pub fn insert_with_priority<I: IntoIterator<Item = T>>(&mut self, node: T, i: I, priority: i64)
+    where
+        T: Clone,
+    {
+        // Insert first, so nothing is changed if the dependencies exceed the maximum degree
+        self.insert(node.clone(), i);
+        self.set_priority(node, priority);
+    }
+
+    /// Set the priority of a node, whether or not it was inserted yet (the default priority is zero)
+    pub fn set_priority(&mut self, node: T, priority: i64) {
+        match priority {
+            0 => {
+                self.priorities.remove(&node);
+            }
+            _ => {
+                self.priorities.insert(node, priority);
+            }
+        }
+    }
+
+    /// Returns the priority of a node (zero if it was never set)
+    #[inline]
+    pub fn priority(&self, node: &T) -> i64 {
+        self.priorities.get(node).copied().unwrap_or_default()
+    }
+
+    /// Start the sort process and return an iterator of the results, always emitting the ready node with the
+    /// highest priority next (see `insert_with_priority`). Dependencies are still respected, so a low priority
+    /// node is emitted early if a high priority node depends on it. If a cycle is detected, an error is yielded
+    /// last.
+    pub fn nodes_by_priority(&self) -> impl Iterator<Item = Result<&T, CycleError>> + '_ {
+        let graph = Graph::new(&self.node_depends);
+        let mut order: Vec<_> = (0..graph.len()).collect();
+        order.sort_by_key(|&node| std::cmp::Reverse(self.priority(graph.nodes[node])));
+        let mut rank = vec![0; graph.len()];
+        for (pos, node) in order.into_iter().enumerate() {
+            rank[node] = pos;
+        }
+
+        let mut kahn = Kahn::new_ranked(graph, rank);
+        std::iter::from_fn(move || {
+            let result = kahn.next()?;
+            Some(result.map(|node| kahn.graph.nodes[node]))
+        })
+    }
+}
+
+// *** Tests ***
+
+#[cfg(test)]
+mod tests {
+    use crate::{CycleError, TopoSort};
+
+    #[test]
+    fn test_nodes_by_priority() {
+        let mut topo_sort = TopoSort::with_capacity(5);
+        topo_sort.insert("setup", vec![]);
+        topo_sort.insert_with_priority("lint", vec!["setup"], -1);
+        topo_sort.insert("test", vec!["setup"]);
+        topo_sort.insert_with_priority("bench", vec!["compile"], 10);
+        topo_sort.insert("compile", vec!["setup"]);
+
+        assert_eq!(10, topo_sort.priority(&"bench"));
+        assert_eq!(0, topo_sort.priority(&"test"));
+        let nodes: Result<Vec<_>, _> = topo_sort.nodes_by_priority().collect();
+        let nodes = nodes.unwrap();
+        assert_eq!(&"setup", nodes[0]);
+        assert_eq!(&"lint", nodes[4]);
+        // "bench" is emitted as soon as its dependency is
+        let compile = nodes.iter().position(|&&node| node == "compile").unwrap();
+        assert_eq!(&"bench", nodes[compile + 1]);
+
+        topo_sort.set_priority("lint", 20);
+        assert_eq!(
+            Some(&&"lint"),
+            topo_sort.nodes_by_priority().nth(1).unwrap().as_ref().ok()
+        );
+
+        // Removing a node forgets its priority
+        topo_sort.take(&"lint");
+        topo_sort.insert("lint", vec![]);
+        assert_eq!(0, topo_sort.priority(&"lint"));
+
+        topo_sort.insert("setup", vec!["bench"]); // cycle
+        let results: Vec<_> = topo_sort.nodes_by_priority().collect();
+        assert_eq!(vec![Ok(&"lint"), Err(CycleError)], results);
+    }
+}
@@ -0,0 +1,622 @@
+use std::hash::Hash;
+use std::sync::OnceLock;
+
+use crate::graph::Graph;
+use crate::{Map, Set, TopoSort};
+
+// *** Queries ***
+
+// NOTE: All queries follow "effective" edges only - self references and dependencies that were never
+// inserted as nodes are ignored, exactly as they are during the sort
+
+impl<T> TopoSort<T>
+where
+    T: Eq + Hash,
+{
+    /// Returns the effective dependencies of a node (keys of `node_depends`), skipping self references and
+    /// dependencies that aren't nodes
+    pub(crate) fn effective_depends<'d>(&'d self, node: &'d T) -> impl Iterator<Item = &'d T> + 'd {
+        self.node_depends
+            .get(node)
+            .into_iter()
+            .flatten()
+            .filter(move |&dependency| dependency != node)
+            .filter_map(move |dependency| {
+                self.node_depends
+                    .get_key_value(dependency)
+                    .map(|(dependency, _)| dependency)
+            })
+    }
+
+    // # Transitive #
+
+    /// Returns true if `node` depends on `dependency` either directly or transitively, else false
+    #[inline]
+    pub fn depends_on(&self, node: &T, dependency: &T) -> bool {
+        self.depends_on_with(node, dependency, &mut Set::new())
+    }
+
+    /// Same as `depends_on`, but uses (and clears first) the given set to track visited nodes. This
+    /// allows a single allocation to be reused across many queries.
+    pub fn depends_on_with<'d>(
+        &'d self,
+        node: &T,
+        dependency: &T,
+        visited: &mut Set<&'d T>,
+    ) -> bool {
+        visited.clear();
+
+        let mut stack = match self.node_depends.get_key_value(node) {
+            Some((node, _)) => vec![node],
+            None => return false,
+        };
+
+        while let Some(current) = stack.pop() {
+            for dep in self.effective_depends(current) {
+                if dep == dependency {
+                    return true;
+                }
+                if visited.insert(dep) {
+                    stack.push(dep);
+                }
+            }
+        }
+
+        false
+    }
+
+    /// Returns all nodes that `node` depends on either directly or transitively. The node itself is only
+    /// included if it is part of a cycle.
+    pub fn transitive_closure(&self, node: &T) -> Set<&T> {
+        let mut closure = Set::new();
+        let mut stack = match self.node_depends.get_key_value(node) {
+            Some((node, _)) => vec![node],
+            None => return closure,
+        };
+
+        while let Some(current) = stack.pop() {
+            for dep in self.effective_depends(current) {
+                if closure.insert(dep) {
+                    stack.push(dep);
+                }
+            }
+        }
+
+        closure
+    }
+
+    /// Returns the dependencies of `node` that are exactly `n` steps away via their shortest path. Unlike the
+    /// transitive closure, nearer nodes are excluded, so increasing `n` expands the dependencies ring by ring. A
+    /// distance of zero returns just the node itself (if found).
+    pub fn frontier_at(&self, node: &T, n: usize) -> Set<&T> {
+        let mut frontier = Set::new();
+        let node = match self.node_depends.get_key_value(node) {
+            Some((node, _)) => node,
+            None => return frontier,
+        };
+        frontier.insert(node);
+        let mut visited = frontier.clone();
+
+        for _ in 0..n {
+            let mut next = Set::new();
+            for current in frontier {
+                for dep in self.effective_depends(current) {
+                    if visited.insert(dep) {
+                        next.insert(dep);
+                    }
+                }
+            }
+            if next.is_empty() {
+                return next;
+            }
+            frontier = next;
+        }
+
+        frontier
+    }
+
+    /// Returns the nodes that every one of the given nodes depends on, either directly or transitively (the
+    /// intersection of their transitive closures). Returns an empty set if no nodes are given.
+    pub fn common_dependencies<'a, I>(&self, nodes: I) -> Set<&T>
+    where
+        T: 'a,
+        I: IntoIterator<Item = &'a T>,
+    {
+        let mut nodes = nodes.into_iter();
+        let mut common = match nodes.next() {
+            Some(node) => self.transitive_closure(node),
+            None => return Set::new(),
+        };
+
+        for node in nodes {
+            if common.is_empty() {
+                break;
+            }
+            let closure = self.transitive_closure(node);
+            common.retain(|dependency| closure.contains(dependency));
+        }
+
+        common
+    }
+
+    /// Returns the nodes that `a` depends on, either directly or transitively, that `b` does not (the difference
+    /// of their transitive closures)
+    pub fn dependency_difference(&self, a: &T, b: &T) -> Set<&T> {
+        let mut difference = self.transitive_closure(a);
+        let closure = self.transitive_closure(b);
+        difference.retain(|dependency| !closure.contains(dependency));
+        difference
+    }
+
+    /// Precompute the full transitive closure of every node as a bit matrix, after which `depends_on` style
+    /// queries can be answered in constant time via `Reachability::reaches`.
+    ///
+    /// NOTE: The matrix takes O(V²/64) words of memory, so this is only suitable for moderately sized graphs
+    pub fn reachability_matrix(&self) -> Reachability<'_, T> {
+        let graph = Graph::new(&self.node_depends);
+        let words = graph.len().div_ceil(64);
+        let mut bits = vec![0u64; graph.len() * words];
+        let mut done = vec![false; graph.len()];
+
+        // In sorted order, every dependency is complete before its dependents, so just merge their rows
+        for node in graph.partial_sort_by_key(|node| node) {
+            let mut row = vec![0u64; words];
+            for &dep in &graph.depends[node] {
+                row[dep / 64] |= 1 << (dep % 64);
+                for (word, &dep_word) in row.iter_mut().zip(&bits[dep * words..(dep + 1) * words]) {
+                    *word |= dep_word;
+                }
+            }
+            bits[node * words..(node + 1) * words].copy_from_slice(&row);
+            done[node] = true;
+        }
+
+        // Nodes in (or depending on) a cycle - search until reaching completed nodes
+        for node in (0..graph.len()).filter(|&node| !done[node]) {
+            let mut row = vec![0u64; words];
+            let mut stack = vec![node];
+
+            while let Some(current) = stack.pop() {
+                for &dep in &graph.depends[current] {
+                    let (word, bit) = (dep / 64, 1 << (dep % 64));
+                    if row[word] & bit != 0 {
+                        continue;
+                    }
+
+                    row[word] |= bit;
+                    if done[dep] {
+                        for (word, &dep_word) in
+                            row.iter_mut().zip(&bits[dep * words..(dep + 1) * words])
+                        {
+                            *word |= dep_word;
+                        }
+                    } else {
+                        stack.push(dep);
+                    }
+                }
+            }
+            bits[node * words..(node + 1) * words].copy_from_slice(&row);
+        }
+
+        Reachability {
+            index: graph.index,
+            words,
+            bits,
+        }
+    }
+
+    /// Returns the neighbors of every node when edge direction is ignored - the union of its effective
+    /// dependencies and dependents. If `a` is a neighbor of `b`, then `b` is always a neighbor of `a`.
+    pub fn undirected_adjacency(&self) -> Map<&T, Set<&T>> {
+        let graph = Graph::new(&self.node_depends);
+        graph
+            .undirected()
+            .into_iter()
+            .enumerate()
+            .map(|(node, neighbors)| {
+                let neighbors = neighbors
+                    .into_iter()
+                    .map(|node| graph.nodes[node])
+                    .collect();
+                (graph.nodes[node], neighbors)
+            })
+            .collect()
+    }
+
+    /// Returns true if the graph splits into more than one independent part (weakly connected component) with no
+    /// edges between them, else false. Such parts can be processed concurrently.
+    #[inline]
+    pub fn is_partitioned(&self) -> bool {
+        Graph::new(&self.node_depends).weak_components().len() > 1
+    }
+
+    /// Split the graph into its independent parts (weakly connected components), each as its own `TopoSort` with
+    /// the same tie-break comparator, maximum degree and node priorities. Dependency sets are copied as inserted.
+    pub fn partitions(&self) -> Vec<TopoSort<T>>
+    where
+        T: Clone,
+    {
+        let graph = Graph::new(&self.node_depends);
+        graph
+            .weak_components()
+            .into_iter()
+            .map(|component| TopoSort {
+                priorities: component
+                    .iter()
+                    .filter_map(|&node| self.priorities.get_key_value(graph.nodes[node]))
+                    .map(|(node, &priority)| (node.clone(), priority))
+                    .collect(),
+                node_depends: component
+                    .into_iter()
+                    .map(|node| {
+                        let node = graph.nodes[node];
+                        (node.clone(), self.node_depends[node].clone())
+                    })
+                    .collect(),
+                comparator: self.comparator.clone(),
+                max_degree: self.max_degree,
+                cache: OnceLock::new(),
+                edge_order: self.edge_order.clone(),
+            })
+            .collect()
+    }
+
+    /// Returns the nodes whose removal would split the graph into more (weakly connected) parts - single points of
+    /// failure where every path between some other pair of nodes passes through them.
+    ///
+    /// These are found as the articulation points of the undirected view of the graph (edge direction is ignored)
+    /// using a single depth first search (Hopcroft-Tarjan): a node is an articulation point if it is the root of the
+    /// search with more than one child, or if it has a child whose subtree has no edge back above the node.
+    pub fn bottlenecks(&self) -> Vec<&T> {
+        let graph = Graph::new(&self.node_depends);
+        let adjacent = graph.undirected();
+
+        let mut discovered = vec![usize::MAX; graph.len()];
+        let mut low = vec![0; graph.len()];
+        let mut is_bottleneck = vec![false; graph.len()];
+        let mut time = 0;
+
+        for root in 0..graph.len() {
+            if discovered[root] != usize::MAX {
+                continue;
+            }
+            discovered[root] = time;
+            low[root] = time;
+            time += 1;
+
+            let mut root_children = 0;
+            // Node plus the index of the next neighbor to visit
+            let mut stack = vec![(root, 0)];
+
+            while let Some((node, next)) = stack.last_mut() {
+                let node = *node;
+                if let Some(&neighbor) = adjacent[node].get(*next) {
+                    *next += 1;
+                    if discovered[neighbor] == usize::MAX {
+                        discovered[neighbor] = time;
+                        low[neighbor] = time;
+                        time += 1;
+                        stack.push((neighbor, 0));
+                    } else {
+                        low[node] = low[node].min(discovered[neighbor]);
+                    }
+                    continue;
+                }
+
+                stack.pop();
+                if let Some(&(parent, _)) = stack.last() {
+                    low[parent] = low[parent].min(low[node]);
+                    if parent == root {
+                        root_children += 1;
+                    } else if low[node] >= discovered[parent] {
+                        is_bottleneck[parent] = true;
+                    }
+                }
+            }
+            is_bottleneck[root] = root_children > 1;
+        }
+
+        let bottlenecks = (0..graph.len())
+            .filter(|&node| is_bottleneck[node])
+            .collect();
+        graph.to_nodes(bottlenecks)
+    }
+
+    /// Returns all nodes that `target` does not depend on, either directly or transitively (excluding `target`
+    /// itself). These are the nodes that are irrelevant to producing `target`.
+    pub fn unrelated_to(&self, target: &T) -> Vec<&T> {
+        let closure = self.transitive_closure(target);
+        self.node_depends
+            .keys()
+            .filter(|&node| node != target && !closure.contains(node))
+            .collect()
+    }
+}
+
+// *** Reachability ***
+
+/// Precomputed transitive closure of every node of a `TopoSort` (see `TopoSort::reachability_matrix`)
+pub struct Reachability<'d, T> {
+    index: Map<&'d T, usize>,
+    // Words per row
+    words: usize,
+    // Row major - row `n` has bit `m` set if node `n` depends on node `m`
+    bits: Vec<u64>,
+}
+
+impl<'d, T> Reachability<'d, T>
+where
+    T: Eq + Hash,
+{
+    /// Returns true if `node` depends on `dependency` either directly or transitively, else false. This gives
+    /// the same answer as `TopoSort::depends_on` as of when the matrix was computed.
+    pub fn reaches(&self, node: &T, dependency: &T) -> bool {
+        match (self.index.get(node), self.index.get(dependency)) {
+            (Some(&node), Some(&dep)) => {
+                self.bits[node * self.words + dep / 64] & (1 << (dep % 64)) != 0
+            }
+            _ => false,
+        }
+    }
+}
+
+// *** Tests ***
+
+#[cfg(test)]
+mod tests {
+    use crate::{Set, TopoSort};
+
+    #[test]
+    fn test_depends_on() {
+        let mut topo_sort = TopoSort::with_capacity(5);
+        topo_sort.insert("C", vec!["A", "B", "F"]); // There is no 'F'
+        topo_sort.insert("E", vec!["B", "C"]);
+        topo_sort.insert("A", vec!["A"]); // Self dependency
+        topo_sort.insert("D", vec!["C"]);
+        topo_sort.insert("B", vec!["A"]);
+
+        assert!(topo_sort.depends_on(&"D", &"A"));
+        assert!(topo_sort.depends_on(&"E", &"C"));
+        assert!(!topo_sort.depends_on(&"A", &"A"));
+        assert!(!topo_sort.depends_on(&"C", &"F"));
+        assert!(!topo_sort.depends_on(&"D", &"E"));
+        assert!(!topo_sort.depends_on(&"G", &"A"));
+    }
+
+    #[test]
+    fn test_depends_on_with() {
+        let mut topo_sort = TopoSort::with_capacity(5);
+        topo_sort.insert(1, vec![2]);
+        topo_sort.insert(2, vec![3]);
+        topo_sort.insert(3, vec![]);
+        topo_sort.insert(4, vec![5]);
+        topo_sort.insert(5, vec![4]); // cycle
+
+        let mut visited = Set::new();
+        let queries = [
+            ((1, 3), true),
+            ((3, 1), false),
+            ((1, 4), false),
+            ((4, 4), true),
+            ((2, 3), true),
+            ((4, 1), false),
+        ];
+        for ((node, dependency), expected) in queries {
+            assert_eq!(
+                expected,
+                topo_sort.depends_on_with(&node, &dependency, &mut visited)
+            );
+            assert_eq!(expected, topo_sort.depends_on(&node, &dependency));
+        }
+    }
+
+    #[test]
+    fn test_transitive_closure() {
+        let mut topo_sort = TopoSort::with_capacity(5);
+        topo_sort.insert("C", vec!["A", "B", "F"]); // There is no 'F'
+        topo_sort.insert("E", vec!["B", "C"]);
+        topo_sort.insert("A", vec!["A"]); // Self dependency
+        topo_sort.insert("D", vec!["C"]);
+        topo_sort.insert("B", vec!["A"]);
+
+        assert_eq!(
+            Set::from_iter(vec![&"A", &"B", &"C"]),
+            topo_sort.transitive_closure(&"D")
+        );
+        assert!(topo_sort.transitive_closure(&"A").is_empty());
+        assert!(topo_sort.transitive_closure(&"F").is_empty());
+
+        topo_sort.insert("A", vec!["D"]); // cycle
+        assert!(topo_sort.transitive_closure(&"D").contains(&"D"));
+    }
+
+    #[test]
+    fn test_frontier_at() {
+        let mut topo_sort = TopoSort::with_capacity(6);
+        topo_sort.insert("app", vec!["lib", "log"]);
+        topo_sort.insert("lib", vec!["core", "log"]);
+        topo_sort.insert("log", vec!["core"]);
+        topo_sort.insert("core", vec!["app"]); // cycle back to the start
+        topo_sort.insert("docs", vec![]);
+
+        assert_eq!(
+            Set::from_iter(vec![&"app"]),
+            topo_sort.frontier_at(&"app", 0)
+        );
+        assert_eq!(
+            Set::from_iter(vec![&"lib", &"log"]),
+            topo_sort.frontier_at(&"app", 1)
+        );
+        // "log" is also two steps away via "lib", but is excluded as it is only one step away directly
+        assert_eq!(
+            Set::from_iter(vec![&"core"]),
+            topo_sort.frontier_at(&"app", 2)
+        );
+        assert!(topo_sort.frontier_at(&"app", 3).is_empty());
+        assert!(topo_sort.frontier_at(&"docs", 1).is_empty());
+        assert!(topo_sort.frontier_at(&"missing", 0).is_empty());
+    }
+
+    #[test]
+    fn test_common_dependencies() {
+        let mut topo_sort = TopoSort::with_capacity(6);
+        topo_sort.insert("app", vec!["lib", "log"]);
+        topo_sort.insert("cli", vec!["lib", "args"]);
+        topo_sort.insert("lib", vec!["core"]);
+        topo_sort.insert("log", vec![]);
+        topo_sort.insert("args", vec!["core"]);
+        topo_sort.insert("core", vec![]);
+
+        assert_eq!(
+            Set::from_iter(vec![&"lib", &"core"]),
+            topo_sort.common_dependencies(&["app", "cli"])
+        );
+        assert_eq!(
+            Set::from_iter(vec![&"core"]),
+            topo_sort.common_dependencies(vec![&"app", &"cli", &"args"])
+        );
+        assert!(topo_sort.common_dependencies(&["app", "log"]).is_empty());
+        assert!(topo_sort.common_dependencies(&[]).is_empty());
+    }
+
+    #[test]
+    fn test_dependency_difference() {
+        let mut topo_sort = TopoSort::with_capacity(6);
+        topo_sort.insert("app", vec!["lib", "tls"]);
+        topo_sort.insert("app-lite", vec!["lib"]);
+        topo_sort.insert("lib", vec!["core"]);
+        topo_sort.insert("tls", vec!["core"]);
+        topo_sort.insert("core", vec![]);
+
+        assert_eq!(
+            Set::from_iter(vec![&"tls"]),
+            topo_sort.dependency_difference(&"app", &"app-lite")
+        );
+        assert!(topo_sort
+            .dependency_difference(&"app-lite", &"app")
+            .is_empty());
+        assert_eq!(
+            Set::from_iter(vec![&"lib", &"tls", &"core"]),
+            topo_sort.dependency_difference(&"app", &"missing")
+        );
+    }
+
+    #[test]
+    fn test_reachability_matrix() {
+        let mut topo_sort = TopoSort::with_capacity(150);
+        // Long enough to span several words per row
+        for node in 0u32..100 {
+            let depends: Vec<_> = [node / 2, node / 3, node.saturating_sub(7)]
+                .into_iter()
+                .filter(|&dep| dep != node)
+                .collect();
+            topo_sort.insert(node, depends);
+        }
+        // Cycle plus nodes that depend on it
+        topo_sort.insert(100, vec![101, 50]);
+        topo_sort.insert(101, vec![102]);
+        topo_sort.insert(102, vec![100, 103]); // 103 doesn't exist
+        topo_sort.insert(104, vec![101, 104]);
+
+        let matrix = topo_sort.reachability_matrix();
+        for node in (0..105).chain([200]) {
+            for dep in (0..105).chain([200]) {
+                assert_eq!(
+                    topo_sort.depends_on(&node, &dep),
+                    matrix.reaches(&node, &dep),
+                    "{} -> {}",
+                    node,
+                    dep
+                );
+            }
+        }
+        assert!(matrix.reaches(&104, &0));
+        assert!(matrix.reaches(&100, &100));
+    }
+
+    #[test]
+    fn test_unrelated_to() {
+        let mut topo_sort = TopoSort::with_capacity(6);
+        topo_sort.insert("app", vec!["lib", "util"]);
+        topo_sort.insert("lib", vec!["core"]);
+        topo_sort.insert("util", vec!["core"]);
+        topo_sort.insert("core", vec![]);
+        // Unrelated branch
+        topo_sort.insert("docs", vec!["theme"]);
+        topo_sort.insert("theme", vec![]);
+
+        let mut unrelated = topo_sort.unrelated_to(&"app");
+        unrelated.sort_unstable();
+        assert_eq!(vec![&"docs", &"theme"], unrelated);
+
+        let mut unrelated = topo_sort.unrelated_to(&"lib");
+        unrelated.sort_unstable();
+        assert_eq!(vec![&"app", &"docs", &"theme", &"util"], unrelated);
+    }
+
+    #[test]
+    fn test_undirected_adjacency() {
+        let mut topo_sort = TopoSort::with_capacity(4);
+        topo_sort.insert("A", vec!["B", "A", "F"]); // Self dependency + there is no 'F'
+        topo_sort.insert("B", vec!["C"]);
+        topo_sort.insert("C", vec!["B"]);
+        topo_sort.insert("D", vec![]);
+
+        let adjacency = topo_sort.undirected_adjacency();
+        assert_eq!(4, adjacency.len());
+        assert_eq!(Set::from_iter(vec![&"B"]), adjacency[&"A"]);
+        assert_eq!(Set::from_iter(vec![&"A", &"C"]), adjacency[&"B"]);
+        assert_eq!(Set::from_iter(vec![&"B"]), adjacency[&"C"]);
+        assert!(adjacency[&"D"].is_empty());
+    }
+
+    #[test]
+    fn test_partitions() {
+        let mut topo_sort = TopoSort::with_capacity(6);
+        topo_sort.insert("a3", vec!["a2"]);
+        topo_sort.insert("a2", vec!["a1"]);
+        topo_sort.insert("a1", vec![]);
+        topo_sort.insert("b2", vec!["b1", "x"]); // There is no 'x'
+        topo_sort.insert("b1", vec![]);
+        assert!(topo_sort.is_partitioned());
+
+        let mut partitions: Vec<_> = topo_sort
+            .partitions()
+            .into_iter()
+            .map(|part| part.try_owned_vec_nodes().unwrap())
+            .collect();
+        partitions.sort_unstable();
+        assert_eq!(vec![vec!["a1", "a2", "a3"], vec!["b1", "b2"]], partitions);
+
+        topo_sort.insert("b1", vec!["a3"]);
+        assert!(!topo_sort.is_partitioned());
+        assert_eq!(1, topo_sort.partitions().len());
+        assert!(!TopoSort::<u32>::new().is_partitioned());
+    }
+
+    #[test]
+    fn test_bottlenecks() {
+        let mut topo_sort = TopoSort::with_capacity(8);
+        topo_sort.insert("app", vec!["hub"]);
+        topo_sort.insert("cli", vec!["hub"]);
+        topo_sort.insert("hub", vec!["core", "log"]);
+        topo_sort.insert("core", vec!["log"]);
+        topo_sort.insert("log", vec![]);
+        // Separate component with no chokepoint
+        topo_sort.insert("docs", vec!["theme"]);
+        topo_sort.insert("theme", vec!["docs"]);
+
+        assert_eq!(vec![&"hub"], topo_sort.bottlenecks());
+
+        // A second path around the hub removes it as a chokepoint
+        topo_sort.insert("app", vec!["hub", "core"]);
+        topo_sort.insert("cli", vec!["hub", "app"]);
+        assert!(topo_sort.bottlenecks().is_empty());
+
+        let mut chain = TopoSort::with_capacity(3);
+        chain.insert(3, vec![2]);
+        chain.insert(2, vec![1]);
+        chain.insert(1, vec![]);
+        assert_eq!(vec![&2], chain.bottlenecks());
+    }
+}
@@ -0,0 +1,228 @@
+use std::hash::Hash;
+
+use serde::de::Error;
+use serde::{Deserialize, Serialize};
+
+use crate::graph::Graph;
+use crate::{CycleError, TopoSort};
+
+// *** SortState ***
+
+/// The state of an in-progress sort that can be serialized and resumed later, even in another process (see
+/// `TopoSort::begin_sort`). The state is a snapshot of the graph, so later changes to the `TopoSort` it was
+/// created from have no effect on it.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct SortState<T> {
+    nodes: Vec<T>,
+    // Dependency -> Dependents (as indices into `nodes`)
+    dependents: Vec<Vec<usize>>,
+    // Remaining effective dependencies of each node
+    edges: Vec<usize>,
+    // Nodes with all dependencies emitted, but not yet emitted themselves
+    ready: Vec<usize>,
+    remaining: usize,
+}
+
+impl<T> SortState<T> {
+    /// Emit the next node of the sort, if any remain, else None. If a cycle is detected, an error is returned
+    /// once, after which the sort is finished.
+    pub fn step(&mut self) -> Option<Result<&T, CycleError>> {
+        let node = match self.ready.pop() {
+            Some(node) => node,
+            None if self.remaining > 0 => {
+                self.remaining = 0;
+                return Some(Err(CycleError));
+            }
+            None => return None,
+        };
+
+        for &dependent in &self.dependents[node] {
+            self.edges[dependent] -= 1;
+            if self.edges[dependent] == 0 {
+                self.ready.push(dependent);
+            }
+        }
+        self.remaining -= 1;
+        Some(Ok(&self.nodes[node]))
+    }
+
+    /// Returns the number of nodes that haven't been emitted yet
+    #[inline]
+    pub fn remaining(&self) -> usize {
+        self.remaining
+    }
+
+    /// Serialize this state to JSON so it can be resumed later via `resume`
+    ///
+    /// # Panics
+    ///
+    /// Panics if a node can't be serialized to JSON (e.g. its `Serialize` impl returns an error)
+    pub fn serialize(&self) -> String
+    where
+        T: Serialize,
+    {
+        serde_json::to_string(self).expect("nodes should serialize to JSON")
+    }
+
+    /// Resume a sort from JSON produced by `serialize`. The sort continues exactly where it left off. An error is
+    /// returned if the JSON is invalid or doesn't describe a consistent state (e.g. it was edited by hand).
+    pub fn resume<'de>(json: &'de str) -> Result<Self, serde_json::Error>
+    where
+        T: Deserialize<'de>,
+    {
+        let state: Self = serde_json::from_str(json)?;
+        state.validate().map_err(serde_json::Error::custom)?;
+        Ok(state)
+    }
+
+    // Ensure `step` can never index out of range or underflow
+    fn validate(&self) -> Result<(), &'static str> {
+        let len = self.nodes.len();
+        if self.dependents.len() != len || self.edges.len() != len {
+            return Err("nodes, dependents and edges must have the same length");
+        }
+        // A finished sort (possibly after a cycle) never looks at the rest
+        if self.remaining == 0 {
+            return if self.ready.is_empty() {
+                Ok(())
+            } else {
+                Err("a finished sort can't have ready nodes")
+            };
+        }
+
+        // Unemitted nodes are those that are ready plus those still waiting on a dependency
+        let mut unemitted: Vec<_> = self.edges.iter().map(|&edges| edges > 0).collect();
+        for &node in &self.ready {
+            match unemitted.get_mut(node) {
+                Some(unemitted) if !*unemitted && self.edges[node] == 0 => *unemitted = true,
+                Some(_) => return Err("ready nodes must be unique and have no remaining edges"),
+                None => return Err("ready node out of range"),
+            }
+        }
+        if unemitted.iter().filter(|&&unemitted| unemitted).count() != self.remaining {
+            return Err("remaining doesn't match the number of unemitted nodes");
+        }
+
+        // Every waiting node must count exactly its unemitted dependencies
+        let mut edges = vec![0; len];
+        for (node, dependents) in self.dependents.iter().enumerate() {
+            if !unemitted[node] {
+                continue;
+            }
+            for &dependent in dependents {
+                match edges.get_mut(dependent) {
+                    Some(edges) => *edges += 1,
+                    None => return Err("dependent out of range"),
+                }
+            }
+        }
+        if edges == self.edges {
+            Ok(())
+        } else {
+            Err("edges don't match the unemitted dependencies")
+        }
+    }
+}
+
+impl<T> TopoSort<T>
+where
+    T: Eq + Hash,
+{
+    /// Start a sort whose state can be serialized between steps and resumed later (see `SortState`)
+    pub fn begin_sort(&self) -> SortState<T>
+    where
+        T: Clone,
+    {
+        let graph = Graph::new(&self.node_depends);
+        SortState {
+            edges: graph.edge_counts(),
+            ready: graph.no_edges(),
+            remaining: graph.len(),
+            nodes: graph.nodes.into_iter().cloned().collect(),
+            dependents: graph.dependents,
+        }
+    }
+}
+
+// *** Tests ***
+
+#[cfg(test)]
+mod tests {
+    use crate::{CycleError, SortState, TopoSort};
+
+    #[test]
+    fn test_resume_sort() {
+        let mut topo_sort = TopoSort::with_capacity(5);
+        topo_sort.insert("C", vec!["A", "B"]);
+        topo_sort.insert("E", vec!["B", "C"]);
+        topo_sort.insert("A", vec![]);
+        topo_sort.insert("D", vec!["A", "B"]);
+        topo_sort.insert("B", vec!["A"]);
+
+        let mut state = topo_sort.begin_sort();
+        let mut uninterrupted = Vec::new();
+        while let Some(node) = state.step() {
+            uninterrupted.push(node.unwrap().to_string());
+        }
+        assert_eq!(5, uninterrupted.len());
+
+        let mut state = topo_sort.begin_sort();
+        let mut order = vec![state.step().unwrap().unwrap().to_string()];
+        order.push(state.step().unwrap().unwrap().to_string());
+        let json = state.serialize();
+        drop(state);
+
+        let mut state: SortState<String> = SortState::resume(&json).unwrap();
+        assert_eq!(3, state.remaining());
+        while let Some(node) = state.step() {
+            order.push(node.unwrap().clone());
+        }
+        assert_eq!(uninterrupted, order);
+    }
+
+    #[test]
+    fn test_resume_sort_cycle() {
+        let mut topo_sort = TopoSort::with_capacity(3);
+        topo_sort.insert(1, vec![]);
+        topo_sort.insert(2, vec![1, 3]);
+        topo_sort.insert(3, vec![2]); // cycle
+
+        let mut state = topo_sort.begin_sort();
+        assert_eq!(Some(Ok(&1)), state.step());
+        let mut state: SortState<i32> = SortState::resume(&state.serialize()).unwrap();
+        assert_eq!(Some(Err(CycleError)), state.step());
+        assert_eq!(None, state.step());
+    }
+
+    #[test]
+    fn test_resume_invalid() {
+        let mut topo_sort = TopoSort::with_capacity(3);
+        topo_sort.insert(1, vec![]);
+        topo_sort.insert(2, vec![1]);
+        topo_sort.insert(3, vec![2]);
+        let mut state = topo_sort.begin_sort();
+        state.step();
+        let json = state.serialize();
+        assert!(SortState::<i32>::resume(&json).is_ok());
+
+        let mut value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        value["remaining"] = 3.into();
+        assert!(SortState::<i32>::resume(&value.to_string()).is_err());
+
+        let mut value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        value["ready"] = vec![7].into();
+        assert!(SortState::<i32>::resume(&value.to_string()).is_err());
+
+        let mut value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        value["edges"] = vec![0, 0, 0].into();
+        assert!(SortState::<i32>::resume(&value.to_string()).is_err());
+
+        let mut value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        value["dependents"] = vec![vec![9]; 3].into();
+        assert!(SortState::<i32>::resume(&value.to_string()).is_err());
+
+        let mut value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        value["nodes"] = vec![1, 2].into();
+        assert!(SortState::<i32>::resume(&value.to_string()).is_err());
+    }
+}
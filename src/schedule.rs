@@ -0,0 +1,649 @@
+use std::cmp::Reverse;
+use std::hash::Hash;
+
+use crate::graph::{Graph, LayerSteps, Layers};
+use crate::{CycleError, Map, TopoSort};
+
+#[cfg(feature = "json")]
+use serde::Serialize;
+
+// *** Scheduling ***
+
+impl<T> TopoSort<T>
+where
+    T: Eq + Hash,
+{
+    /// Sort and return a vector (with borrowed nodes) of the results, emitting the nodes in `first` as early as
+    /// possible and the nodes in `last` as late as possible. Among pinned nodes that are ready at the same time,
+    /// the one listed earlier in its slice wins. Pins never override a dependency: a pinned node is only moved as
+    /// far as its dependencies (and dependents) allow, and unknown nodes are ignored. If a cycle is detected, an
+    /// error is returned instead.
+    pub fn sort_with_pins(&self, first: &[&T], last: &[&T]) -> Result<Vec<&T>, CycleError> {
+        let graph = Graph::new(&self.node_depends);
+
+        // Node -> (class, position) where classes are 0 = first, 1 = unpinned, 2 = last
+        let mut pins: Map<&T, (u8, usize)> = Map::with_capacity(first.len() + last.len());
+        pins.extend(last.iter().enumerate().map(|(pos, &node)| (node, (2, pos))));
+        pins.extend(
+            first
+                .iter()
+                .enumerate()
+                .map(|(pos, &node)| (node, (0, pos))),
+        );
+
+        let order =
+            graph.sort_by_key(|node| pins.get(graph.nodes[node]).copied().unwrap_or((1, 0)))?;
+        Ok(graph.to_nodes(order))
+    }
+
+    /// Returns the slack of every node: how long it can be delayed past its earliest start without delaying the
+    /// completion of the whole graph, given the `duration` of each node and unlimited parallelism. It is the
+    /// difference between the node's latest and earliest start times, so nodes on the critical path (the longest
+    /// path through the graph) have zero slack. If a cycle is detected, an error is returned instead.
+    pub fn slack<F: Fn(&T) -> u64>(&self, duration: F) -> Result<Map<&T, u64>, CycleError> {
+        let graph = Graph::new(&self.node_depends);
+        let order = graph.sort_by_key(|node| node)?;
+        let durations: Vec<_> = graph.nodes.iter().map(|&node| duration(node)).collect();
+
+        let mut earliest = vec![0u64; graph.len()];
+        for &node in &order {
+            earliest[node] = graph.depends[node]
+                .iter()
+                .map(|&dep| earliest[dep] + durations[dep])
+                .max()
+                .unwrap_or(0);
+        }
+        let finish = (0..graph.len())
+            .map(|node| earliest[node] + durations[node])
+            .max()
+            .unwrap_or(0);
+
+        let mut latest = vec![0u64; graph.len()];
+        for &node in order.iter().rev() {
+            let latest_finish = graph.dependents[node]
+                .iter()
+                .map(|&dependent| latest[dependent])
+                .min()
+                .unwrap_or(finish);
+            latest[node] = latest_finish - durations[node];
+        }
+
+        Ok((0..graph.len())
+            .map(|node| (graph.nodes[node], latest[node] - earliest[node]))
+            .collect())
+    }
+
+    /// Sort and return a vector (with borrowed nodes) of the results, emitting the ready node with the most
+    /// (effective) dependents first, so the most work is unblocked as soon as possible. Ties are broken by
+    /// iteration order. If a cycle is detected, an error is returned instead.
+    pub fn sort_unblock_most(&self) -> Result<Vec<&T>, CycleError> {
+        let graph = Graph::new(&self.node_depends);
+        let order = graph.sort_by_key(|node| Reverse(graph.dependents[node].len()))?;
+        Ok(graph.to_nodes(order))
+    }
+
+    /// Sort and return a vector (with borrowed nodes) of the results, matching dependencies to nodes by their
+    /// normalized keys instead of by equality. For example, normalizing by trimming a trailing `/` allows `"a/"`
+    /// to satisfy a dependency on `"a"`. The results are still the original (not normalized) nodes. If more than
+    /// one node has the same key, dependencies on that key refer to the last of them in iteration order. If a
+    /// cycle is detected, an error is returned instead.
+    pub fn sort_with_normalizer<K, F>(&self, normalize: F) -> Result<Vec<&T>, CycleError>
+    where
+        K: Eq + Hash,
+        F: Fn(&T) -> K,
+    {
+        let keys: Map<K, usize> = self
+            .node_depends
+            .keys()
+            .enumerate()
+            .map(|(idx, node)| (normalize(node), idx))
+            .collect();
+
+        let depends: Vec<_> = self.node_depends.values().collect();
+        let graph = Graph::from_indices(self.node_depends.keys().collect(), |node| {
+            let mut depends: Vec<_> = depends[node]
+                .iter()
+                .filter_map(|dependency| keys.get(&normalize(dependency)).copied())
+                .collect();
+            depends.sort_unstable();
+            depends.dedup();
+            depends
+        });
+
+        let order = graph.sort_by_key(|node| node)?;
+        Ok(graph.to_nodes(order))
+    }
+
+    /// Sort and return an iterator of layers, where each layer holds nodes whose dependencies are all in earlier
+    /// layers, so nodes within a layer can be processed in parallel. If a cycle is detected, an error is yielded
+    /// last.
+    pub fn layers(&self) -> impl Iterator<Item = Result<Vec<&T>, CycleError>> + '_ {
+        let mut layers = Layers::new(Graph::new(&self.node_depends));
+        std::iter::from_fn(move || {
+            let layer = layers.next()?;
+            Some(layer.map(|layer| layers.graph.to_nodes(layer)))
+        })
+    }
+
+    /// Sort and return a vector of layers (see `layers`). If a cycle is detected, an error is returned instead.
+    #[inline]
+    pub fn try_layers(&self) -> Result<Vec<Vec<&T>>, CycleError> {
+        self.layers().collect()
+    }
+
+    /// Sort and return an iterator of layers, where each layer holds nodes whose dependencies are all in earlier
+    /// layers (so nodes within a layer can be processed in parallel), but split each layer into chunks of at most
+    /// `max` nodes. Chunks never mix nodes from different layers. If a cycle is detected, an error is yielded last.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `max` is zero
+    pub fn chunked_layers(
+        &self,
+        max: usize,
+    ) -> impl Iterator<Item = Result<Vec<&T>, CycleError>> + '_ {
+        assert!(max > 0, "chunk size must be non-zero");
+        let mut layers = Layers::new(Graph::new(&self.node_depends));
+
+        std::iter::from_fn(move || {
+            let chunks: Vec<_> = match layers.next()? {
+                Ok(layer) => layer
+                    .chunks(max)
+                    .map(|chunk| Ok(chunk.iter().map(|&node| layers.graph.nodes[node]).collect()))
+                    .collect(),
+                Err(err) => vec![Err(err)],
+            };
+            Some(chunks)
+        })
+        .flatten()
+    }
+
+    /// Sort and return a consuming iterator of layers (with owned nodes), where each layer holds nodes whose
+    /// dependencies are all in earlier layers, so nodes within a layer can be processed in parallel. If a cycle is
+    /// detected, an error is yielded last. Like `layers`, each layer is only computed once it is requested.
+    pub fn into_layers(self) -> impl Iterator<Item = Result<Vec<T>, CycleError>> {
+        // Keep only the (owned) indices of the graph so that the nodes can be moved out
+        let mut graph = Graph::new(&self.node_depends);
+        let mut steps = LayerSteps::new(&graph);
+        let dependents = std::mem::take(&mut graph.dependents);
+        drop(graph);
+
+        // NOTE: `into_keys` yields in the same order as `keys` (used by the graph) since nothing was modified
+        let mut nodes: Vec<_> = self.node_depends.into_keys().map(Some).collect();
+        std::iter::from_fn(move || {
+            let layer = steps.next_layer(&dependents)?;
+            Some(layer.map(|layer| {
+                layer
+                    .into_iter()
+                    .map(|node| nodes[node].take().expect("node emitted twice"))
+                    .collect()
+            }))
+        })
+    }
+
+    /// Sort and return the parallel execution plan as a JSON array of layers, each an array of nodes, where every
+    /// node only depends on nodes in earlier layers. This allows schedulers outside of Rust to drive their own
+    /// runners. If a cycle is detected, an error is returned instead.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a node can't be serialized to JSON (e.g. its `Serialize` impl returns an error)
+    #[cfg(feature = "json")]
+    pub fn to_schedule_json(&self) -> Result<String, CycleError>
+    where
+        T: Serialize,
+    {
+        let mut layers = Layers::new(Graph::new(&self.node_depends));
+        let mut plan = Vec::new();
+
+        while let Some(layer) = layers.next() {
+            let layer: Vec<_> = layer?
+                .into_iter()
+                .map(|node| layers.graph.nodes[node])
+                .collect();
+            plan.push(layer);
+        }
+
+        Ok(serde_json::to_string(&plan).expect("nodes should serialize to JSON"))
+    }
+
+    /// Sort and return a vector (with borrowed nodes) of the results, trying to keep no more than `max_in_flight`
+    /// nodes "open" at any point. A node is open from the time it is emitted until all of its dependents have been
+    /// emitted (think of results held in memory until consumed). If a cycle is detected, an error is returned instead.
+    ///
+    /// NOTE: This is a best-effort greedy heuristic - at each step the ready node leaving the fewest nodes open is
+    /// picked (preferring nodes that close the most open nodes). If no ready node stays within the bound, the
+    /// best one is emitted anyway, so the bound can be exceeded when it is infeasible or the heuristic misses.
+    pub fn sort_bounded_concurrency(&self, max_in_flight: usize) -> Result<Vec<&T>, CycleError> {
+        let graph = Graph::new(&self.node_depends);
+        let mut edges = graph.edge_counts();
+        let mut pending: Vec<_> = graph
+            .dependents
+            .iter()
+            .map(|dependents| dependents.len())
+            .collect();
+        let mut ready = graph.no_edges();
+        let mut order = Vec::with_capacity(graph.len());
+        let mut open = 0;
+
+        while !ready.is_empty() {
+            // (# open after, # closed) for a ready node
+            let score = |node: usize| {
+                let closes = graph.depends[node]
+                    .iter()
+                    .filter(|&&dep| pending[dep] == 1)
+                    .count();
+                let opens = usize::from(pending[node] > 0);
+                (open + opens - closes, closes)
+            };
+
+            let (pos, &node) = ready
+                .iter()
+                .enumerate()
+                .min_by_key(|&(_, &node)| {
+                    let (after, closes) = score(node);
+                    (after > max_in_flight, after, Reverse(closes), node)
+                })
+                .expect("ready nodes");
+            let (after, _) = score(node);
+            ready.swap_remove(pos);
+            open = after;
+            order.push(node);
+
+            for &dep in &graph.depends[node] {
+                pending[dep] -= 1;
+            }
+            for &dependent in &graph.dependents[node] {
+                edges[dependent] -= 1;
+                if edges[dependent] == 0 {
+                    ready.push(dependent);
+                }
+            }
+        }
+
+        if order.len() == graph.len() {
+            Ok(graph.to_nodes(order))
+        } else {
+            Err(CycleError)
+        }
+    }
+
+    /// Sort and return a vector (with borrowed nodes) of the results, trying to minimize the peak memory in use.
+    /// Each node's result takes `size` bytes (or any other unit) and is held from when the node runs until all of
+    /// its dependents have run (a node without dependents is released right away). While a node runs, its
+    /// dependencies' results and its own are all held. If a cycle is detected, an error is returned instead.
+    ///
+    /// NOTE: If every node has at most one dependent (the graph is a forest of trees), each subtree is run
+    /// contiguously, starting with the subtree with the largest difference between its peak and its result
+    /// (Sethi-Ullman style), which is optimal among such orders. Otherwise, this is a greedy heuristic that
+    /// always runs the ready node with the smallest net change in held memory.
+    pub fn sort_min_peak_memory<F: Fn(&T) -> u64>(&self, size: F) -> Result<Vec<&T>, CycleError> {
+        let graph = Graph::new(&self.node_depends);
+        let topo_order = graph.sort_by_key(|node| node)?;
+        let sizes: Vec<_> = graph.nodes.iter().map(|&node| size(node)).collect();
+
+        let order = if graph
+            .dependents
+            .iter()
+            .all(|dependents| dependents.len() <= 1)
+        {
+            min_peak_tree(&graph, &sizes, topo_order)
+        } else {
+            min_peak_greedy(&graph, &sizes)
+        };
+        Ok(graph.to_nodes(order))
+    }
+}
+
+// Optimal subtree ordering for a forest of in-trees (dependencies are children)
+fn min_peak_tree<T>(graph: &Graph<'_, T>, sizes: &[u64], topo_order: Vec<usize>) -> Vec<usize>
+where
+    T: Eq + Hash,
+{
+    // Held size of a node's result (released at once if nothing depends on it)
+    let held = |node: usize| match graph.dependents[node].len() {
+        0 => 0,
+        _ => sizes[node],
+    };
+    // Children in run order: descending by (peak - held)
+    let mut children = graph.depends.clone();
+    let mut peaks = vec![0u64; graph.len()];
+
+    for node in topo_order {
+        children[node].sort_by_key(|&child| Reverse(peaks[child] - held(child)));
+        let mut before = 0u64;
+        let mut peak = 0u64;
+        for &child in &children[node] {
+            peak = peak.max(before + peaks[child]);
+            before += held(child);
+        }
+        peaks[node] = peak.max(before + sizes[node]);
+    }
+
+    // Post order walk from each root (children are pushed in reverse to be visited in order)
+    let mut order = Vec::with_capacity(graph.len());
+    for root in (0..graph.len()).filter(|&node| graph.dependents[node].is_empty()) {
+        let mut stack = vec![(root, false)];
+        while let Some((node, expanded)) = stack.pop() {
+            if expanded {
+                order.push(node);
+            } else {
+                stack.push((node, true));
+                stack.extend(children[node].iter().rev().map(|&child| (child, false)));
+            }
+        }
+    }
+    order
+}
+
+// Greedy: always run the ready node with the smallest net change in held memory
+fn min_peak_greedy<T>(graph: &Graph<'_, T>, sizes: &[u64]) -> Vec<usize>
+where
+    T: Eq + Hash,
+{
+    let mut edges = graph.edge_counts();
+    let mut pending: Vec<_> = graph
+        .dependents
+        .iter()
+        .map(|dependents| dependents.len())
+        .collect();
+    let mut ready = graph.no_edges();
+    let mut order = Vec::with_capacity(graph.len());
+
+    while !ready.is_empty() {
+        let (pos, &node) = ready
+            .iter()
+            .enumerate()
+            .min_by_key(|&(_, &node)| {
+                let held = if pending[node] > 0 { sizes[node] } else { 0 };
+                let released: u64 = graph.depends[node]
+                    .iter()
+                    .filter(|&&dep| pending[dep] == 1)
+                    .map(|&dep| sizes[dep])
+                    .sum();
+                (i128::from(held) - i128::from(released), node)
+            })
+            .expect("ready nodes");
+        ready.swap_remove(pos);
+        order.push(node);
+
+        for &dep in &graph.depends[node] {
+            pending[dep] -= 1;
+        }
+        for &dependent in &graph.dependents[node] {
+            edges[dependent] -= 1;
+            if edges[dependent] == 0 {
+                ready.push(dependent);
+            }
+        }
+    }
+    order
+}
+
+// *** Tests ***
+
+#[cfg(test)]
+mod tests {
+    use crate::{CycleError, Set, TopoSort};
+
+    // Max # of nodes emitted that still have dependents waiting to be emitted
+    fn max_in_flight(topo_sort: &TopoSort<&'static str>, order: &[&&'static str]) -> usize {
+        let mut open = Set::new();
+        let mut max = 0;
+        for (pos, &&node) in order.iter().enumerate() {
+            for dep in &topo_sort[&node] {
+                if !order[pos + 1..]
+                    .iter()
+                    .any(|&&n| topo_sort[&n].contains(dep))
+                {
+                    open.remove(dep);
+                }
+            }
+            if order[pos + 1..]
+                .iter()
+                .any(|&&n| topo_sort[&n].contains(&node))
+            {
+                open.insert(node);
+            }
+            max = max.max(open.len());
+        }
+        max
+    }
+
+    #[test]
+    fn test_sort_with_pins() {
+        let mut topo_sort = TopoSort::with_capacity(4);
+        topo_sort.insert("A", vec![]);
+        topo_sort.insert("B", vec![]);
+        topo_sort.insert("C", vec!["A"]);
+        topo_sort.insert("E", vec![]);
+
+        // "A" can't move after its dependent "C"
+        let nodes = topo_sort.sort_with_pins(&[&"E"], &[&"A"]).unwrap();
+        assert_eq!(vec![&"E", &"B", &"A", &"C"], nodes);
+
+        // "C" can't move before its dependency "A", but follows it immediately
+        let nodes = topo_sort.sort_with_pins(&[&"C", &"B"], &[]).unwrap();
+        assert_eq!(&"B", nodes[0]);
+        let pos = nodes.iter().position(|&&node| node == "A").unwrap();
+        assert_eq!(&"C", nodes[pos + 1]);
+
+        topo_sort.insert("A", vec!["C"]); // cycle
+        assert_eq!(Err(CycleError), topo_sort.sort_with_pins(&[], &[]));
+    }
+
+    #[test]
+    fn test_sort_bounded_concurrency() {
+        // Two independent chains
+        let mut topo_sort = TopoSort::with_capacity(6);
+        topo_sort.insert("a1", vec![]);
+        topo_sort.insert("a2", vec!["a1"]);
+        topo_sort.insert("a3", vec!["a2"]);
+        topo_sort.insert("b1", vec![]);
+        topo_sort.insert("b2", vec!["b1"]);
+        topo_sort.insert("b3", vec!["b2"]);
+
+        let order = topo_sort.sort_bounded_concurrency(1).unwrap();
+        assert_eq!(6, order.len());
+        assert_eq!(1, max_in_flight(&topo_sort, &order));
+        assert_eq!(
+            topo_sort.try_vec_nodes().unwrap().len(),
+            topo_sort.sort_bounded_concurrency(0).unwrap().len()
+        );
+
+        // Fan in requires all inputs to be open at once
+        topo_sort.insert("c", vec!["a3", "b3"]);
+        let order = topo_sort.sort_bounded_concurrency(2).unwrap();
+        assert_eq!(2, max_in_flight(&topo_sort, &order));
+
+        topo_sort.insert("a1", vec!["c"]); // cycle
+        assert_eq!(Err(CycleError), topo_sort.sort_bounded_concurrency(2));
+    }
+
+    #[test]
+    fn test_layers() {
+        let mut topo_sort = TopoSort::with_capacity(5);
+        topo_sort.insert(1, vec![]);
+        topo_sort.insert(2, vec![1]);
+        topo_sort.insert(3, vec![1]);
+        topo_sort.insert(4, vec![2, 3]);
+        topo_sort.insert(5, vec![]);
+
+        let mut layers = topo_sort.try_layers().unwrap();
+        for layer in &mut layers {
+            layer.sort_unstable();
+        }
+        assert_eq!(vec![vec![&1, &5], vec![&2, &3], vec![&4]], layers);
+
+        topo_sort.insert(1, vec![4]); // cycle
+        let layers: Vec<_> = topo_sort.layers().collect();
+        assert_eq!(vec![Ok(vec![&5]), Err(CycleError)], layers);
+        assert_eq!(Err(CycleError), topo_sort.try_layers());
+    }
+
+    #[test]
+    fn test_chunked_layers() {
+        let mut topo_sort = TopoSort::with_capacity(7);
+        topo_sort.insert("root", vec![]);
+        for node in ["a", "b", "c", "d", "e"] {
+            topo_sort.insert(node, vec!["root"]);
+        }
+        topo_sort.insert("z", vec!["a", "e"]);
+
+        let chunks: Vec<_> = topo_sort
+            .chunked_layers(2)
+            .collect::<Result<_, _>>()
+            .unwrap();
+        let sizes: Vec<_> = chunks.iter().map(|chunk| chunk.len()).collect();
+        assert_eq!(vec![1, 2, 2, 1, 1], sizes);
+        assert_eq!(vec![&"root"], chunks[0]);
+        assert_eq!(vec![&"z"], chunks[4]);
+
+        let mut wide: Vec<_> = chunks[1..4].iter().flatten().copied().collect();
+        wide.sort_unstable();
+        assert_eq!(vec![&"a", &"b", &"c", &"d", &"e"], wide);
+
+        topo_sort.insert("root", vec!["z"]); // cycle
+        let chunks: Vec<_> = topo_sort.chunked_layers(2).collect();
+        assert_eq!(vec![Err(CycleError)], chunks);
+    }
+
+    #[test]
+    fn test_sort_min_peak_memory() {
+        let mut topo_sort = TopoSort::with_capacity(5);
+        topo_sort.insert("out", vec!["a", "b"]);
+        topo_sort.insert("a", vec!["a1"]);
+        topo_sort.insert("a1", vec![]);
+        topo_sort.insert("b", vec![]);
+        let size = |node: &&str| match *node {
+            "a1" => 10,
+            "b" => 5,
+            _ => 1,
+        };
+
+        // Running "b" first is the greedy choice, but holding it while "a1" runs peaks at 16 instead of 11
+        assert_eq!(
+            vec![&"a1", &"a", &"b", &"out"],
+            topo_sort.sort_min_peak_memory(size).unwrap()
+        );
+
+        // Not a tree anymore
+        topo_sort.insert("b", vec!["a1"]);
+        assert_eq!(
+            vec![&"a1", &"a", &"b", &"out"],
+            topo_sort.sort_min_peak_memory(size).unwrap()
+        );
+
+        topo_sort.insert("a1", vec!["out"]); // cycle
+        assert_eq!(Err(CycleError), topo_sort.sort_min_peak_memory(size));
+    }
+
+    #[test]
+    fn test_slack() {
+        let mut topo_sort = TopoSort::with_capacity(5);
+        topo_sort.insert("fetch", vec![]);
+        topo_sort.insert("compile", vec!["fetch"]);
+        topo_sort.insert("docs", vec!["fetch"]);
+        topo_sort.insert("package", vec!["compile", "docs"]);
+        let duration = |node: &&str| match *node {
+            "fetch" => 2,
+            "compile" => 10,
+            "docs" => 3,
+            _ => 1,
+        };
+
+        let slack = topo_sort.slack(duration).unwrap();
+        assert_eq!(4, slack.len());
+        assert_eq!(0, slack[&"fetch"]);
+        assert_eq!(0, slack[&"compile"]);
+        assert_eq!(0, slack[&"package"]);
+        assert_eq!(7, slack[&"docs"]);
+
+        topo_sort.insert("fetch", vec!["package"]); // cycle
+        assert_eq!(Err(CycleError), topo_sort.slack(duration));
+    }
+
+    #[test]
+    fn test_sort_unblock_most() {
+        let mut topo_sort = TopoSort::with_capacity(6);
+        topo_sort.insert("leaf", vec![]);
+        topo_sort.insert("hub", vec![]);
+        topo_sort.insert("a", vec!["hub"]);
+        topo_sort.insert("b", vec!["hub"]);
+        topo_sort.insert("c", vec!["hub", "leaf"]);
+
+        let order = topo_sort.sort_unblock_most().unwrap();
+        assert_eq!(5, order.len());
+        assert_eq!(&"hub", order[0]);
+        assert_eq!(&"leaf", order[1]);
+
+        topo_sort.insert("hub", vec!["c"]); // cycle
+        assert_eq!(Err(CycleError), topo_sort.sort_unblock_most());
+    }
+
+    #[test]
+    fn test_sort_with_normalizer() {
+        let mut topo_sort = TopoSort::with_capacity(3);
+        topo_sort.insert("src/app/", vec!["SRC/LIB", "src/util"]);
+        topo_sort.insert("src/lib/", vec!["src/util/"]);
+        topo_sort.insert("src/util", vec!["src/app"]);
+        let normalize = |node: &&str| node.trim_end_matches('/').to_lowercase();
+
+        // Without normalization, nothing matches
+        assert_eq!(3, topo_sort.try_vec_nodes().unwrap().len());
+
+        // ...but with it, there is a cycle
+        assert_eq!(Err(CycleError), topo_sort.sort_with_normalizer(normalize));
+
+        topo_sort.insert("src/util", vec!["src/util/"]); // Self dependency once normalized
+        assert_eq!(
+            vec![&"src/util", &"src/lib/", &"src/app/"],
+            topo_sort.sort_with_normalizer(normalize).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_into_layers() {
+        let mut topo_sort = TopoSort::with_capacity(5);
+        topo_sort.insert("C", vec!["A", "B"]);
+        topo_sort.insert("E", vec!["B", "C"]);
+        topo_sort.insert("A", vec![]);
+        topo_sort.insert("D", vec!["A", "C", "E"]);
+        topo_sort.insert("B", vec!["A"]);
+
+        let layers: Vec<_> = topo_sort
+            .clone()
+            .into_layers()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(
+            vec![vec!["A"], vec!["B"], vec!["C"], vec!["E"], vec!["D"]],
+            layers
+        );
+
+        topo_sort.insert("B", vec![]);
+        topo_sort.insert("A", vec!["D"]); // cycle
+        let mut layers = topo_sort.into_layers();
+        assert_eq!(Some(Ok(vec!["B"])), layers.next());
+        assert_eq!(Some(Err(CycleError)), layers.next());
+        assert_eq!(None, layers.next());
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn test_to_schedule_json() {
+        let mut topo_sort = TopoSort::with_capacity(5);
+        topo_sort.insert("app", vec!["lib", "log"]);
+        topo_sort.insert("lib", vec!["core"]);
+        topo_sort.insert("log", vec!["core"]);
+        topo_sort.insert("core", vec![]);
+
+        let json = topo_sort.to_schedule_json().unwrap();
+        let mut layers: Vec<Vec<String>> = serde_json::from_str(&json).unwrap();
+        layers[1].sort_unstable();
+        assert_eq!(vec![vec!["core"], vec!["lib", "log"], vec!["app"]], layers);
+
+        topo_sort.insert("core", vec!["app"]); // cycle
+        assert_eq!(Err(CycleError), topo_sort.to_schedule_json());
+    }
+}
@@ -0,0 +1,291 @@
+use std::hash::Hash;
+use std::sync::{Condvar, Mutex, MutexGuard, PoisonError};
+
+use crate::graph::Graph;
+use crate::{CycleError, TopoSort};
+
+// *** Scheduler ***
+
+/// Manually driven sort for concurrent execution. Unlike the iterators, a node isn't considered done the moment
+/// it is handed out: its dependents only become ready once it is reported via `mark_done`, so several workers
+/// can pull nodes and finish them in any order.
+pub struct Scheduler<'d, T> {
+    graph: Graph<'d, T>,
+    // Remaining (not done) dependencies of each node
+    edges: Vec<usize>,
+    ready: Vec<usize>,
+    in_flight: Vec<bool>,
+    in_flight_len: usize,
+    // Nodes that are not done yet
+    remaining: usize,
+}
+
+impl<'d, T> Scheduler<'d, T>
+where
+    T: Eq + Hash,
+{
+    fn new(graph: Graph<'d, T>) -> Self {
+        Scheduler {
+            edges: graph.edge_counts(),
+            ready: graph.no_edges(),
+            in_flight: vec![false; graph.len()],
+            in_flight_len: 0,
+            remaining: graph.len(),
+            graph,
+        }
+    }
+
+    /// Hand out a node whose dependencies are all done, if any. Returns `Ok(None)` if no node is ready right now,
+    /// either because nodes handed out earlier aren't done yet or because all nodes are done (see `is_finished`).
+    /// If no node can ever become ready again because the remaining nodes are part of (or depend on) a cycle,
+    /// an error is returned instead.
+    pub fn pop_ready(&mut self) -> Result<Option<&'d T>, CycleError> {
+        match self.ready.pop() {
+            Some(node) => {
+                self.in_flight[node] = true;
+                self.in_flight_len += 1;
+                Ok(Some(self.graph.nodes[node]))
+            }
+            None if self.in_flight_len == 0 && self.remaining > 0 => Err(CycleError),
+            None => Ok(None),
+        }
+    }
+
+    /// Report a node handed out by `pop_ready` as done, which readies any dependents whose dependencies are now
+    /// all done. Returns false (and does nothing) if the node isn't currently handed out.
+    pub fn mark_done(&mut self, node: &T) -> bool {
+        let node = match self.graph.index.get(node) {
+            Some(&node) if self.in_flight[node] => node,
+            _ => return false,
+        };
+
+        self.in_flight[node] = false;
+        self.in_flight_len -= 1;
+        self.remaining -= 1;
+        for &dependent in &self.graph.dependents[node] {
+            self.edges[dependent] -= 1;
+            if self.edges[dependent] == 0 {
+                self.ready.push(dependent);
+            }
+        }
+        true
+    }
+
+    /// Returns the number of nodes that have been handed out, but are not done yet
+    #[inline]
+    pub fn in_flight(&self) -> usize {
+        self.in_flight_len
+    }
+
+    /// Returns true if every node is done otherwise false
+    #[inline]
+    pub fn is_finished(&self) -> bool {
+        self.remaining == 0
+    }
+}
+
+// *** ConcurrentTopoSort ***
+
+/// A thread-safe `Scheduler` that can be shared by a pool of workers (e.g. via `std::thread::scope`). Each worker
+/// calls `pop_ready` to get a node, which blocks until one is ready, and `mark_done` once it has processed it.
+pub struct ConcurrentTopoSort<'d, T> {
+    scheduler: Mutex<Scheduler<'d, T>>,
+    // Signaled whenever a node is done, as that may ready other nodes, finish the sort or reveal a cycle
+    done: Condvar,
+}
+
+impl<'d, T> ConcurrentTopoSort<'d, T>
+where
+    T: Eq + Hash,
+{
+    /// Hand out a node whose dependencies are all done, blocking until one is ready. Returns `Ok(None)` once
+    /// all nodes are done. If the remaining nodes can never become ready because they are part of (or depend on)
+    /// a cycle, an error is returned instead (to every waiting worker).
+    pub fn pop_ready(&self) -> Result<Option<&'d T>, CycleError> {
+        let mut scheduler = self.lock();
+        loop {
+            match scheduler.pop_ready()? {
+                Some(node) => return Ok(Some(node)),
+                None if scheduler.is_finished() => return Ok(None),
+                None => {
+                    scheduler = self
+                        .done
+                        .wait(scheduler)
+                        .unwrap_or_else(PoisonError::into_inner);
+                }
+            }
+        }
+    }
+
+    /// Hand out a node whose dependencies are all done without blocking (see `Scheduler::pop_ready`)
+    #[inline]
+    pub fn try_pop_ready(&self) -> Result<Option<&'d T>, CycleError> {
+        self.lock().pop_ready()
+    }
+
+    /// Report a node handed out by `pop_ready` as done, waking any waiting workers. Returns false (and does
+    /// nothing) if the node isn't currently handed out.
+    pub fn mark_done(&self, node: &T) -> bool {
+        let marked = self.lock().mark_done(node);
+        if marked {
+            self.done.notify_all();
+        }
+        marked
+    }
+
+    /// Returns the number of nodes that have been handed out, but are not done yet
+    #[inline]
+    pub fn in_flight(&self) -> usize {
+        self.lock().in_flight()
+    }
+
+    /// Returns true if every node is done otherwise false
+    #[inline]
+    pub fn is_finished(&self) -> bool {
+        self.lock().is_finished()
+    }
+
+    // The scheduler is never left in an inconsistent state, so a panic in another worker doesn't matter
+    #[inline]
+    fn lock(&self) -> MutexGuard<'_, Scheduler<'d, T>> {
+        self.scheduler
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl<T> TopoSort<T>
+where
+    T: Eq + Hash,
+{
+    /// Start the sort process and return a scheduler which hands out ready nodes and is told when each of them
+    /// is done (see `Scheduler`)
+    #[inline]
+    pub fn scheduler(&self) -> Scheduler<'_, T> {
+        Scheduler::new(Graph::new(&self.node_depends))
+    }
+
+    /// Start the sort process and return a scheduler that can be shared between threads (see
+    /// `ConcurrentTopoSort`)
+    #[inline]
+    pub fn concurrent(&self) -> ConcurrentTopoSort<'_, T> {
+        ConcurrentTopoSort {
+            scheduler: Mutex::new(self.scheduler()),
+            done: Condvar::new(),
+        }
+    }
+}
+
+// *** Tests ***
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+    use std::thread;
+
+    use crate::{CycleError, TopoSort};
+
+    #[test]
+    fn test_scheduler() {
+        let mut topo_sort = TopoSort::with_capacity(4);
+        topo_sort.insert("app", vec!["lib", "log"]);
+        topo_sort.insert("lib", vec!["core"]);
+        topo_sort.insert("log", vec!["core"]);
+        topo_sort.insert("core", vec![]);
+
+        let mut scheduler = topo_sort.scheduler();
+        assert_eq!(Ok(Some(&"core")), scheduler.pop_ready());
+        // Dependents aren't ready until their dependency is done
+        assert_eq!(Ok(None), scheduler.pop_ready());
+        assert!(!scheduler.mark_done(&"lib"));
+        assert!(scheduler.mark_done(&"core"));
+        assert!(!scheduler.mark_done(&"core"));
+
+        // Both can be worked on at once and finish out of order
+        let first = scheduler.pop_ready().unwrap().unwrap();
+        let second = scheduler.pop_ready().unwrap().unwrap();
+        assert_eq!(2, scheduler.in_flight());
+        assert!(scheduler.mark_done(second));
+        assert_eq!(Ok(None), scheduler.pop_ready());
+        assert!(scheduler.mark_done(first));
+
+        assert_eq!(Ok(Some(&"app")), scheduler.pop_ready());
+        assert!(!scheduler.is_finished());
+        assert!(scheduler.mark_done(&"app"));
+        assert!(scheduler.is_finished());
+        assert_eq!(Ok(None), scheduler.pop_ready());
+    }
+
+    #[test]
+    fn test_scheduler_cycle() {
+        let mut topo_sort = TopoSort::with_capacity(3);
+        topo_sort.insert(1, vec![]);
+        topo_sort.insert(2, vec![1, 3]);
+        topo_sort.insert(3, vec![2]);
+
+        let mut scheduler = topo_sort.scheduler();
+        assert_eq!(Ok(Some(&1)), scheduler.pop_ready());
+        // Not a cycle while a node is still in flight
+        assert_eq!(Ok(None), scheduler.pop_ready());
+        assert!(scheduler.mark_done(&1));
+        assert_eq!(Err(CycleError), scheduler.pop_ready());
+        assert!(!scheduler.is_finished());
+    }
+
+    #[test]
+    fn test_concurrent() {
+        let mut topo_sort = TopoSort::with_capacity(50);
+        for node in 0..50_u32 {
+            topo_sort.insert(node, (1..=3).filter_map(|step| node.checked_sub(step * 5)));
+        }
+
+        let concurrent = topo_sort.concurrent();
+        let order = Mutex::new(Vec::new());
+        thread::scope(|scope| {
+            for _ in 0..4 {
+                scope.spawn(|| {
+                    while let Some(&node) = concurrent.pop_ready().unwrap() {
+                        order.lock().unwrap().push(node);
+                        assert!(concurrent.mark_done(&node));
+                    }
+                });
+            }
+        });
+
+        assert!(concurrent.is_finished());
+        assert_eq!(0, concurrent.in_flight());
+        let order = order.into_inner().unwrap();
+        assert_eq!(50, order.len());
+        assert_eq!(Ok(()), topo_sort.verify_order(&order));
+    }
+
+    #[test]
+    fn test_concurrent_cycle() {
+        let mut topo_sort = TopoSort::with_capacity(3);
+        topo_sort.insert(1, vec![]);
+        topo_sort.insert(2, vec![1, 3]);
+        topo_sort.insert(3, vec![2]);
+
+        let concurrent = topo_sort.concurrent();
+        let results = Mutex::new(Vec::new());
+        thread::scope(|scope| {
+            for _ in 0..3 {
+                scope.spawn(|| loop {
+                    match concurrent.pop_ready() {
+                        Ok(Some(node)) => {
+                            concurrent.mark_done(node);
+                        }
+                        result => {
+                            results.lock().unwrap().push(result.map(|_| ()));
+                            break;
+                        }
+                    }
+                });
+            }
+        });
+
+        // Every worker learns about the cycle
+        assert_eq!(vec![Err(CycleError); 3], results.into_inner().unwrap());
+        assert_eq!(Err(CycleError), concurrent.try_pop_ready());
+    }
+}
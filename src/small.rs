@@ -0,0 +1,328 @@
+use std::hash::Hash;
+use std::iter::Flatten;
+use std::slice;
+
+use crate::graph::Graph;
+use crate::{CycleError, Map, Set};
+
+// *** SmallSet ***
+
+/// A set that stores up to `N` items inline and only spills to a heap allocated set once it outgrows that. Most
+/// nodes only have a handful of dependencies, so this avoids an allocation per node for typical graphs.
+#[derive(Clone, Debug)]
+pub struct SmallSet<T, const N: usize>(Repr<T, N>);
+
+#[derive(Clone, Debug)]
+enum Repr<T, const N: usize> {
+    // Items `..len` are always `Some`
+    Inline(usize, [Option<T>; N]),
+    Spilled(Set<T>),
+}
+
+impl<T, const N: usize> SmallSet<T, N>
+where
+    T: Eq + Hash,
+{
+    /// Initialize a new empty set. It will not allocate until more than `N` items are inserted
+    #[inline]
+    pub fn new() -> Self {
+        SmallSet(Repr::Inline(0, std::array::from_fn(|_| None)))
+    }
+
+    /// Returns true if the set has outgrown its inline storage otherwise false
+    #[inline]
+    pub fn is_spilled(&self) -> bool {
+        matches!(self.0, Repr::Spilled(_))
+    }
+
+    /// Returns true if the set contains the item otherwise false
+    pub fn contains(&self, item: &T) -> bool {
+        match &self.0 {
+            Repr::Inline(len, items) => items[..*len].iter().any(|i| i.as_ref() == Some(item)),
+            Repr::Spilled(set) => set.contains(item),
+        }
+    }
+
+    /// Insert an item, returning true if it wasn't in the set yet otherwise false
+    pub fn insert(&mut self, item: T) -> bool {
+        if self.contains(&item) {
+            return false;
+        }
+
+        match &mut self.0 {
+            Repr::Inline(len, items) if *len < N => {
+                items[*len] = Some(item);
+                *len += 1;
+            }
+            Repr::Inline(_, items) => {
+                let mut set = Set::with_capacity(N + 1);
+                set.extend(items.iter_mut().filter_map(Option::take));
+                set.insert(item);
+                self.0 = Repr::Spilled(set);
+            }
+            Repr::Spilled(set) => {
+                set.insert(item);
+            }
+        }
+        true
+    }
+
+    /// Remove an item, returning true if it was in the set otherwise false. A spilled set is not moved back inline.
+    pub fn remove(&mut self, item: &T) -> bool {
+        match &mut self.0 {
+            Repr::Inline(len, items) => {
+                match items[..*len].iter().position(|i| i.as_ref() == Some(item)) {
+                    Some(pos) => {
+                        *len -= 1;
+                        items[pos] = items[*len].take();
+                        true
+                    }
+                    None => false,
+                }
+            }
+            Repr::Spilled(set) => set.remove(item),
+        }
+    }
+
+    /// Returns the number of items in the set
+    #[inline]
+    pub fn len(&self) -> usize {
+        match &self.0 {
+            Repr::Inline(len, _) => *len,
+            Repr::Spilled(set) => set.len(),
+        }
+    }
+
+    /// Returns true if the set is empty otherwise false
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns an iterator over the items of the set
+    #[inline]
+    pub fn iter(&self) -> SmallSetIter<'_, T> {
+        match &self.0 {
+            Repr::Inline(len, items) => SmallSetIter::Inline(items[..*len].iter().flatten()),
+            Repr::Spilled(set) => SmallSetIter::Spilled(set.iter()),
+        }
+    }
+}
+
+impl<T, const N: usize> Default for SmallSet<T, N>
+where
+    T: Eq + Hash,
+{
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T, const N: usize> Extend<T> for SmallSet<T, N>
+where
+    T: Eq + Hash,
+{
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for item in iter {
+            self.insert(item);
+        }
+    }
+}
+
+impl<T, const N: usize> FromIterator<T> for SmallSet<T, N>
+where
+    T: Eq + Hash,
+{
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut set = Self::new();
+        set.extend(iter);
+        set
+    }
+}
+
+impl<'a, T, const N: usize> IntoIterator for &'a SmallSet<T, N>
+where
+    T: Eq + Hash,
+{
+    type Item = &'a T;
+    type IntoIter = SmallSetIter<'a, T>;
+
+    #[inline]
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+// *** SmallSetIter ***
+
+/// Iterator over the items of a `SmallSet`
+pub enum SmallSetIter<'a, T> {
+    /// Items stored inline
+    Inline(Flatten<slice::Iter<'a, Option<T>>>),
+    /// Items of a spilled set
+    Spilled(<&'a Set<T> as IntoIterator>::IntoIter),
+}
+
+impl<'a, T> Iterator for SmallSetIter<'a, T> {
+    type Item = &'a T;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        match self {
+            SmallSetIter::Inline(iter) => iter.next(),
+            SmallSetIter::Spilled(iter) => iter.next(),
+        }
+    }
+}
+
+// *** SmallTopoSort ***
+
+/// A variant of `TopoSort` that stores the dependencies of each node in a `SmallSet`, so nodes with up to `N`
+/// dependencies don't need a heap allocation for them. Ties are broken by iteration order of the nodes, which is
+/// only insertion order with the `indexmap` feature enabled (see "Deterministic Order" in the crate docs).
+///
+/// NOTE: This is a separate type rather than the storage of `TopoSort`, since `TopoSort` hands out its
+/// dependency sets as `&Set<T>` (e.g. `get`, `iter` and `Index`), which a `SmallSet` can't be borrowed as
+#[derive(Clone)]
+pub struct SmallTopoSort<T, const N: usize = 4>
+where
+    T: Eq + Hash,
+{
+    // Dependent -> Dependencies
+    node_depends: Map<T, SmallSet<T, N>>,
+}
+
+impl<T, const N: usize> SmallTopoSort<T, N>
+where
+    T: Eq + Hash,
+{
+    /// Initialize a new struct with zero capacity. It will not allocate until the first insertion
+    #[inline]
+    pub fn new() -> Self {
+        SmallTopoSort {
+            node_depends: Map::new(),
+        }
+    }
+
+    /// Initialize an empty struct with a given capacity
+    #[inline]
+    pub fn with_capacity(capacity: usize) -> Self {
+        SmallTopoSort {
+            node_depends: Map::with_capacity(capacity),
+        }
+    }
+
+    /// Insert into this struct with the given node and an iterator of its dependencies. If the node was
+    /// already inserted, its dependencies are overwritten (use `add_dependencies` to merge them instead)
+    #[inline]
+    pub fn insert<I: IntoIterator<Item = T>>(&mut self, node: T, i: I) {
+        self.node_depends.insert(node, i.into_iter().collect());
+    }
+
+    /// Add the given dependencies to those already present for the node, inserting the node if it doesn't exist
+    #[inline]
+    pub fn add_dependencies<I: IntoIterator<Item = T>>(&mut self, node: T, i: I) {
+        self.node_depends.entry(node).or_default().extend(i);
+    }
+
+    /// Returns the dependency set of a node (as inserted), if found, else None
+    #[inline]
+    pub fn get(&self, node: &T) -> Option<&SmallSet<T, N>> {
+        self.node_depends.get(node)
+    }
+
+    /// Start the sort process and return an iterator of the results. If a cycle is detected, the nodes sorted
+    /// before it are yielded, followed by an error.
+    pub fn nodes(&self) -> impl Iterator<Item = Result<&T, CycleError>> + '_ {
+        let depends: Vec<_> = self.node_depends.values().collect();
+        let graph = Graph::from_fn(self.node_depends.keys().collect(), |node| depends[node]);
+        let order = graph.partial_sort_by_key(|node| node);
+        let err = (order.len() < graph.len()).then_some(Err(CycleError));
+
+        order
+            .into_iter()
+            .map(move |node| Ok(graph.nodes[node]))
+            .chain(err)
+    }
+
+    /// Sort and return a vector (with borrowed nodes) of the results. If a cycle is detected, an error is
+    /// returned instead
+    #[inline]
+    pub fn try_vec_nodes(&self) -> Result<Vec<&T>, CycleError> {
+        self.nodes().collect()
+    }
+
+    /// Returns true if there aren't any nodes added otherwise false
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.node_depends.is_empty()
+    }
+
+    /// Returns the number of nodes added to the collection
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.node_depends.len()
+    }
+}
+
+impl<T, const N: usize> Default for SmallTopoSort<T, N>
+where
+    T: Eq + Hash,
+{
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// *** Tests ***
+
+#[cfg(test)]
+mod tests {
+    use crate::{CycleError, SmallSet, SmallTopoSort};
+
+    #[test]
+    fn test_small_set() {
+        let mut set: SmallSet<i32, 2> = SmallSet::new();
+        assert!(set.insert(1));
+        assert!(set.insert(2));
+        assert!(!set.insert(1));
+        assert!(!set.is_spilled());
+
+        assert!(set.insert(3));
+        assert!(set.is_spilled());
+        assert_eq!(3, set.len());
+        assert!(set.remove(&2));
+        assert!(!set.remove(&2));
+        let mut items: Vec<_> = set.iter().copied().collect();
+        items.sort();
+        assert_eq!(vec![1, 3], items);
+
+        let mut set: SmallSet<i32, 3> = vec![1, 2, 3].into_iter().collect();
+        assert!(set.remove(&1));
+        assert!(set.contains(&3));
+        assert!(!set.contains(&1));
+        assert_eq!(2, set.len());
+    }
+
+    #[test]
+    fn test_small_topo_sort() {
+        let mut topo_sort: SmallTopoSort<_> = SmallTopoSort::with_capacity(5);
+        topo_sort.insert("C", vec!["A", "B"]);
+        topo_sort.insert("E", vec!["B", "C"]);
+        topo_sort.insert("A", vec![]);
+        topo_sort.insert("D", vec!["A", "C"]);
+        topo_sort.add_dependencies("D", vec!["E", "A"]);
+        topo_sort.insert("B", vec!["A"]);
+
+        assert_eq!(3, topo_sort.get(&"D").unwrap().len());
+        assert_eq!(
+            vec![&"A", &"B", &"C", &"E", &"D"],
+            topo_sort.try_vec_nodes().unwrap()
+        );
+
+        topo_sort.add_dependencies("A", vec!["D"]); // cycle
+        assert_eq!(Err(CycleError), topo_sort.try_vec_nodes());
+    }
+}
@@ -0,0 +1,166 @@
+use std::hash::Hash;
+use std::ops::{Deref, DerefMut};
+
+use crate::graph::Graph;
+use crate::{CycleError, Map, TopoSort};
+
+// *** StableTopoSort ***
+
+/// A `TopoSort` that remembers the last order it emitted and keeps subsequent sorts as close to it as possible.
+/// This is useful when re-sorting after every edit and a minimal difference between results is desired. All
+/// `TopoSort` methods are available via `Deref`/`DerefMut`.
+#[derive(Clone)]
+pub struct StableTopoSort<T>
+where
+    T: Eq + Hash,
+{
+    topo_sort: TopoSort<T>,
+    // Last successfully emitted order
+    order: Vec<T>,
+}
+
+impl<T> StableTopoSort<T>
+where
+    T: Eq + Hash,
+{
+    /// Initialize a new struct with zero capacity and no previous order
+    #[inline]
+    pub fn new() -> Self {
+        Self::from_topo_sort(TopoSort::new())
+    }
+
+    /// Initialize a new struct from an existing `TopoSort` with no previous order
+    #[inline]
+    pub fn from_topo_sort(topo_sort: TopoSort<T>) -> Self {
+        StableTopoSort {
+            topo_sort,
+            order: Vec::new(),
+        }
+    }
+
+    /// Sort and return (and remember) the new order. Whenever more than one node is ready, the one that was emitted
+    /// earliest in the previous order is emitted first and nodes that weren't in the previous order are emitted
+    /// as late as possible. As a result, nodes whose dependencies didn't change keep their relative positions.
+    /// If a cycle is detected, an error is returned instead and the previous order is retained.
+    pub fn sort(&mut self) -> Result<&[T], CycleError>
+    where
+        T: Clone,
+    {
+        let graph = Graph::new(&self.topo_sort.node_depends);
+        let position: Map<_, _> = self
+            .order
+            .iter()
+            .enumerate()
+            .map(|(position, node)| (node, position))
+            .collect();
+
+        let order = graph.sort_by_key(|node| {
+            position
+                .get(graph.nodes[node])
+                .copied()
+                .unwrap_or(usize::MAX)
+        })?;
+        self.order = order
+            .into_iter()
+            .map(|node| graph.nodes[node].clone())
+            .collect();
+        Ok(&self.order)
+    }
+
+    /// Returns the order emitted by the last successful `sort` (empty if never sorted)
+    #[inline]
+    pub fn last_order(&self) -> &[T] {
+        &self.order
+    }
+
+    /// Reclaim ownership of the inner `TopoSort`, discarding the previous order
+    #[inline]
+    pub fn into_inner(self) -> TopoSort<T> {
+        self.topo_sort
+    }
+}
+
+impl<T> Default for StableTopoSort<T>
+where
+    T: Eq + Hash,
+{
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> From<TopoSort<T>> for StableTopoSort<T>
+where
+    T: Eq + Hash,
+{
+    #[inline]
+    fn from(topo_sort: TopoSort<T>) -> Self {
+        Self::from_topo_sort(topo_sort)
+    }
+}
+
+impl<T> Deref for StableTopoSort<T>
+where
+    T: Eq + Hash,
+{
+    type Target = TopoSort<T>;
+
+    #[inline]
+    fn deref(&self) -> &Self::Target {
+        &self.topo_sort
+    }
+}
+
+impl<T> DerefMut for StableTopoSort<T>
+where
+    T: Eq + Hash,
+{
+    #[inline]
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.topo_sort
+    }
+}
+
+// *** Tests ***
+
+#[cfg(test)]
+mod tests {
+    use crate::{CycleError, StableTopoSort};
+
+    #[test]
+    fn test_stable_sort() {
+        let mut topo_sort = StableTopoSort::new();
+        for node in 0..10 {
+            topo_sort.insert(node, vec![]);
+        }
+        topo_sort.insert(10, vec![3, 7]);
+        topo_sort.insert(11, vec![10, 1]);
+
+        let first = topo_sort.sort().unwrap().to_vec();
+        assert_eq!(first, topo_sort.last_order());
+
+        // Adding an independent node doesn't disturb the relative order of the others
+        topo_sort.insert(12, vec![]);
+        let second = topo_sort.sort().unwrap().to_vec();
+        assert_eq!(13, second.len());
+        let without_new: Vec<_> = second.into_iter().filter(|&node| node != 12).collect();
+        assert_eq!(first, without_new);
+
+        // Re-sorting unchanged is identical
+        let third = topo_sort.sort().unwrap().to_vec();
+        assert_eq!(third, topo_sort.sort().unwrap());
+    }
+
+    #[test]
+    fn test_stable_sort_cycle() {
+        let mut topo_sort = StableTopoSort::new();
+        topo_sort.insert(1, vec![2]);
+        topo_sort.insert(2, vec![]);
+        assert_eq!(&[2, 1], topo_sort.sort().unwrap());
+
+        topo_sort.insert(2, vec![1]); // cycle
+        assert_eq!(Err(CycleError), topo_sort.sort());
+        assert_eq!(&[2, 1], topo_sort.last_order());
+    }
+}
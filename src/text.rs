@@ -0,0 +1,121 @@
+use std::{error, fmt};
+
+use crate::{DegreeExceeded, TopoSort};
+
+// *** ParseError ***
+
+/// An error type returned when a line of text can't be parsed into a node and its dependencies
+#[derive(Clone, Copy, fmt::Debug, PartialEq)]
+pub enum ParseError {
+    /// The line has no `:` separating the node from its dependencies
+    MissingColon,
+    /// The node before the `:` is empty or contains whitespace
+    InvalidNode,
+    /// Inserting the dependencies would exceed the maximum degree (see `TopoSort::with_max_degree`)
+    DegreeExceeded(DegreeExceeded),
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseError::MissingColon => f.write_str("missing ':' after node"),
+            ParseError::InvalidNode => f.write_str("node is empty or contains whitespace"),
+            ParseError::DegreeExceeded(err) => fmt::Display::fmt(err, f),
+        }
+    }
+}
+
+impl error::Error for ParseError {}
+
+impl From<DegreeExceeded> for ParseError {
+    #[inline]
+    fn from(err: DegreeExceeded) -> Self {
+        ParseError::DegreeExceeded(err)
+    }
+}
+
+// *** Text ***
+
+impl TopoSort<String> {
+    /// Parse a single line of the form `node: dep1 dep2` and add it to this struct. Everything after a `#` is
+    /// a comment, and blank (or comment only) lines are ignored. Dependencies are merged with those of earlier
+    /// lines for the same node (like `add_dependencies`), so a node may be spread over several lines. Since only
+    /// nodes are sorted, a dependency needs its own line (e.g. `dep1:`) to be included in the results.
+    ///
+    /// This is intended for streaming, e.g. from `BufRead::lines`.
+    pub fn insert_line(&mut self, line: &str) -> Result<(), ParseError> {
+        let line = match line.split_once('#') {
+            Some((line, _)) => line,
+            None => line,
+        };
+        if line.trim().is_empty() {
+            return Ok(());
+        }
+
+        let (node, depends) = line.split_once(':').ok_or(ParseError::MissingColon)?;
+        let node = node.trim();
+        if node.is_empty() || node.contains(char::is_whitespace) {
+            return Err(ParseError::InvalidNode);
+        }
+
+        self.try_add_dependencies(
+            node.to_string(),
+            depends.split_whitespace().map(str::to_string),
+        )?;
+        Ok(())
+    }
+}
+
+// *** Tests ***
+
+#[cfg(test)]
+mod tests {
+    use crate::{ParseError, Set, TopoSort};
+
+    #[test]
+    fn test_insert_line() {
+        let text = "# Build graph\n\
+                    app: lib util # the app\n\
+                    \n\
+                    lib: core\n\
+                    util:\n   \n\
+                    core:\n\
+                    util: core";
+
+        let mut topo_sort = TopoSort::new();
+        for line in text.lines() {
+            topo_sort.insert_line(line).unwrap();
+        }
+
+        assert_eq!(4, topo_sort.len());
+        assert_eq!(
+            Set::from_iter(vec!["core".to_string()]),
+            topo_sort[&"util".to_string()]
+        );
+        let nodes = topo_sort.try_vec_nodes().unwrap();
+        assert_eq!("core", nodes[0]);
+        assert_eq!("app", nodes[3]);
+    }
+
+    #[test]
+    fn test_insert_line_errors() {
+        let mut topo_sort = TopoSort::new();
+        assert_eq!(
+            Err(ParseError::MissingColon),
+            topo_sort.insert_line("app lib")
+        );
+        assert_eq!(Err(ParseError::InvalidNode), topo_sort.insert_line(": lib"));
+        assert_eq!(
+            Err(ParseError::InvalidNode),
+            topo_sort.insert_line("my app: lib")
+        );
+        assert_eq!(Ok(()), topo_sort.insert_line("# app lib"));
+        assert!(topo_sort.is_empty());
+
+        let mut topo_sort = TopoSort::with_max_degree(1);
+        assert!(matches!(
+            topo_sort.insert_line("app: lib util"),
+            Err(ParseError::DegreeExceeded(_))
+        ));
+    }
+}
@@ -0,0 +1,130 @@
+use std::hash::Hash;
+use std::{error, fmt};
+
+use crate::{Map, TopoSort};
+
+// *** OrderViolation ***
+
+/// An error type returned when a proposed order is not a valid topological order of the graph (see
+/// `TopoSort::verify_order`)
+#[derive(Clone, Copy, fmt::Debug, PartialEq)]
+pub enum OrderViolation<T> {
+    /// The order contains a node that was never inserted
+    Unknown(T),
+    /// The order contains the same node more than once
+    Duplicate(T),
+    /// A node of the graph is missing from the order
+    Missing(T),
+    /// The dependent (first) is placed before one of its dependencies (second)
+    OutOfOrder(T, T),
+}
+
+impl<T> fmt::Display for OrderViolation<T>
+where
+    T: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OrderViolation::Unknown(node) => write!(f, "unknown node {:?} in order", node),
+            OrderViolation::Duplicate(node) => write!(f, "node {:?} appears more than once", node),
+            OrderViolation::Missing(node) => write!(f, "node {:?} is missing from order", node),
+            OrderViolation::OutOfOrder(dependent, dependency) => write!(
+                f,
+                "node {:?} is placed before its dependency {:?}",
+                dependent, dependency
+            ),
+        }
+    }
+}
+
+impl<T> error::Error for OrderViolation<T> where T: fmt::Debug {}
+
+// *** Verification ***
+
+impl<T> TopoSort<T>
+where
+    T: Eq + Hash,
+{
+    /// Verify that `order` is a valid topological order of the graph: it contains every node exactly once (and
+    /// nothing else) and never places a node before one of its dependencies. Only effective dependencies are
+    /// checked, so self references and dependencies that aren't nodes are ignored, just as they are by the sort.
+    /// If not, the first violation found is returned (unknown or duplicate nodes first, then missing nodes, then
+    /// the earliest misplaced node in the order).
+    pub fn verify_order<'a>(&'a self, order: &'a [T]) -> Result<(), OrderViolation<&'a T>> {
+        let mut positions: Map<&T, usize> = Map::with_capacity(order.len());
+        for (pos, node) in order.iter().enumerate() {
+            if !self.node_depends.contains_key(node) {
+                return Err(OrderViolation::Unknown(node));
+            }
+            if positions.insert(node, pos).is_some() {
+                return Err(OrderViolation::Duplicate(node));
+            }
+        }
+
+        if let Some(node) = self
+            .node_depends
+            .keys()
+            .find(|node| !positions.contains_key(node))
+        {
+            return Err(OrderViolation::Missing(node));
+        }
+
+        for (pos, node) in order.iter().enumerate() {
+            let depends = &self.node_depends[node];
+            // Every dependency that is a node has a position by now
+            if let Some(dependency) = depends
+                .iter()
+                .find(|&dependency| positions.get(dependency).is_some_and(|&dep| dep > pos))
+            {
+                return Err(OrderViolation::OutOfOrder(node, dependency));
+            }
+        }
+        Ok(())
+    }
+}
+
+// *** Tests ***
+
+#[cfg(test)]
+mod tests {
+    use crate::{OrderViolation, TopoSort};
+
+    #[test]
+    fn test_verify_order() {
+        let mut topo_sort = TopoSort::with_capacity(4);
+        topo_sort.insert(1, vec![]);
+        topo_sort.insert(2, vec![1, 2]); // Self reference is ignored
+        topo_sort.insert(3, vec![1, 5]); // Missing node is ignored
+        topo_sort.insert(4, vec![2, 3]);
+
+        assert_eq!(Ok(()), topo_sort.verify_order(&[1, 2, 3, 4]));
+        assert_eq!(Ok(()), topo_sort.verify_order(&[1, 3, 2, 4]));
+        let order = topo_sort.try_owned_vec_nodes().unwrap();
+        assert_eq!(Ok(()), topo_sort.verify_order(&order));
+
+        assert_eq!(
+            Err(OrderViolation::OutOfOrder(&2, &1)),
+            topo_sort.verify_order(&[2, 1, 3, 4])
+        );
+        assert_eq!(
+            Err(OrderViolation::OutOfOrder(&3, &1)),
+            topo_sort.verify_order(&[3, 4, 1, 2])
+        );
+        assert_eq!(
+            Err(OrderViolation::Unknown(&5)),
+            topo_sort.verify_order(&[1, 2, 5, 3, 4])
+        );
+        assert_eq!(
+            Err(OrderViolation::Duplicate(&1)),
+            topo_sort.verify_order(&[1, 2, 1, 3, 4])
+        );
+        assert_eq!(
+            Err(OrderViolation::Missing(&3)),
+            topo_sort.verify_order(&[1, 2, 4])
+        );
+        assert_eq!(
+            "node 2 is placed before its dependency 1",
+            OrderViolation::OutOfOrder(&2, &1).to_string()
+        );
+    }
+}
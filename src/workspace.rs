@@ -0,0 +1,133 @@
+use std::hash::Hash;
+use std::mem;
+use std::sync::Arc;
+
+use crate::frozen::Csr;
+use crate::{CycleError, InnerIter, Set, TopoSort, TopoSortIter};
+
+// *** SortWorkspace ***
+
+/// Scratch buffers that can be reused across sorts (see `TopoSort::iter_with_workspace`) to avoid allocating
+/// them again for every sort. Once warmed up, only the per node lookups (which borrow the nodes) are still
+/// allocated per sort.
+#[derive(Clone, Debug, Default)]
+pub struct SortWorkspace {
+    // Adjacency
+    pub(crate) offsets: Vec<u32>,
+    pub(crate) targets: Vec<u32>,
+    pub(crate) in_degree: Vec<u32>,
+    pub(crate) pairs: Vec<(u32, u32)>,
+    pub(crate) cursors: Vec<u32>,
+    // Sort state
+    pub(crate) edges: Vec<u32>,
+    pub(crate) no_edges: Vec<u32>,
+    pub(crate) emitted: Vec<bool>,
+}
+
+impl SortWorkspace {
+    /// Initialize a new workspace. It will not allocate until the first sort
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+// Take a buffer out of the workspace, resetting it to `len` default values
+pub(crate) fn reuse<U: Clone + Default>(buf: &mut Vec<U>, len: usize) -> Vec<U> {
+    let mut buf = mem::take(buf);
+    buf.clear();
+    buf.resize(len, U::default());
+    buf
+}
+
+impl<T> TopoSort<T>
+where
+    T: Eq + Hash,
+{
+    /// Start the sort process and return an iterator of the results (just like `iter`), using the buffers of
+    /// `workspace` instead of allocating new ones. The buffers are handed back when the iterator is dropped.
+    pub fn iter_with_workspace<'w>(
+        &self,
+        workspace: &'w mut SortWorkspace,
+    ) -> TopoSortWorkspaceIter<'_, 'w, T> {
+        let entries: Vec<_> = self.node_depends.iter().collect();
+        let csr = Arc::new(Csr::new_in(&entries, workspace));
+        let inner = InnerIter::from_csr_in(csr, self.comparator.clone(), workspace);
+        TopoSortWorkspaceIter {
+            iter: TopoSortIter {
+                inner,
+                entries,
+                node_depends: &self.node_depends,
+            },
+            workspace,
+        }
+    }
+}
+
+// *** TopoSortWorkspaceIter ***
+
+/// Iterator over the final node and dependent set of the topological sort, which hands its buffers back to a
+/// `SortWorkspace` when dropped
+pub struct TopoSortWorkspaceIter<'d, 'w, T> {
+    iter: TopoSortIter<'d, T>,
+    workspace: &'w mut SortWorkspace,
+}
+
+impl<'d, T> Iterator for TopoSortWorkspaceIter<'d, '_, T>
+where
+    T: Eq + Hash,
+{
+    type Item = Result<(&'d T, &'d Set<T>), CycleError>;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        self.iter.next()
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.iter.size_hint()
+    }
+}
+
+impl<T> Drop for TopoSortWorkspaceIter<'_, '_, T> {
+    fn drop(&mut self) {
+        self.iter.inner.recycle(self.workspace);
+    }
+}
+
+// *** Tests ***
+
+#[cfg(test)]
+mod tests {
+    use crate::{CycleError, SortWorkspace, TopoSort};
+
+    #[test]
+    fn test_iter_with_workspace() {
+        let mut topo_sort = TopoSort::with_capacity(5);
+        topo_sort.insert("C", vec!["A", "B"]);
+        topo_sort.insert("E", vec!["B", "C"]);
+        topo_sort.insert("A", vec![]);
+        topo_sort.insert("D", vec!["A", "C", "E"]);
+        topo_sort.insert("B", vec!["A"]);
+
+        let mut workspace = SortWorkspace::new();
+        for _ in 0..3 {
+            let nodes: Result<Vec<_>, _> = topo_sort
+                .iter_with_workspace(&mut workspace)
+                .map(|result| result.map(|(&node, _)| node))
+                .collect();
+            assert_eq!(vec!["A", "B", "C", "E", "D"], nodes.unwrap());
+        }
+        assert_eq!(8, workspace.targets.len());
+        assert!(workspace.emitted.capacity() >= 5);
+
+        // A smaller graph reuses the same buffers
+        let mut other = TopoSort::new();
+        other.insert("X", vec!["Y"]);
+        other.insert("Y", vec!["X"]); // cycle
+        let mut iter = other.iter_with_workspace(&mut workspace);
+        assert_eq!(Some(Err(CycleError)), iter.next());
+        assert_eq!(None, iter.next());
+    }
+}